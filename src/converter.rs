@@ -1 +1,4 @@
 pub mod ddr2osu;
+pub mod ddr2quaver;
+pub mod sm2ssq;
+pub mod ssq2sm;
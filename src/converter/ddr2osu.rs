@@ -5,7 +5,9 @@ use clap::Clap;
 use log::{debug, info, trace, warn};
 use thiserror::Error;
 
+use crate::ddr::analysis::rating;
 use crate::ddr::ssq;
+use crate::ddr::transform::{modifiers::Modifiers, simplify, style};
 use crate::osu::beatmap;
 use crate::osu::types::*;
 
@@ -63,6 +65,25 @@ pub struct Config {
         display_order = 3
     )]
     pub shock_action: ShockAction,
+    #[clap(
+        long,
+        about = "Generate missing Beginner and Basic charts from harder charts",
+        display_order = 3
+    )]
+    pub generate_easier: bool,
+    #[clap(
+        long,
+        about = "Convert Single charts to Double charts or the other way around if one style is missing",
+        display_order = 3
+    )]
+    pub convert_styles: bool,
+    #[clap(
+        long = "modifier",
+        about = "Also convert charts with modifiers (e.g. mirror or left+no-freeze), can be repeated",
+        number_of_values = 1,
+        display_order = 3
+    )]
+    pub modifiers: Vec<Modifiers>,
     #[clap(
         long = "hp",
         about = "Range of HP drain (beginner:challenge)",
@@ -81,14 +102,22 @@ pub struct Config {
 
 #[derive(Clap, Debug, Clone)]
 pub struct ConfigMetadata {
-    #[clap(long, about = "Song title to use in beatmap", display_order = 4)]
+    #[clap(
+        long,
+        about = "Song title to use in converted charts",
+        display_order = 4
+    )]
     pub title: Option<String>,
-    #[clap(long, about = "Artist name to use in beatmap", display_order = 4)]
+    #[clap(
+        long,
+        about = "Artist name to use in converted charts",
+        display_order = 4
+    )]
     pub artist: Option<String>,
     #[clap(
         long,
         default_value = "Dance Dance Revolution",
-        about = "Source to use in beatmap",
+        about = "Source to use in converted charts",
         display_order = 4
     )]
     pub source: String,
@@ -100,8 +129,18 @@ impl fmt::Display for Config {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "ddr2osu ({}shock→{:?} hp{} acc{})",
+            "ddr2osu ({}{}{}shock→{:?} hp{} acc{})",
             if self.stops { "stops " } else { "" },
+            if self.convert_styles {
+                "converted "
+            } else {
+                ""
+            },
+            if self.generate_easier {
+                "generated "
+            } else {
+                ""
+            },
             self.shock_action,
             self.hp_drain,
             self.accuracy
@@ -116,7 +155,8 @@ pub enum ShockAction {
     //Static(Vec<u8>),
 }
 
-struct ShockStepGenerator {
+/// Generates the columns that replace shocks, cycling through pairs of opposite columns.
+pub struct ShockStepGenerator {
     last: u8,
     columns: u8,
     mode: ShockAction,
@@ -129,11 +169,15 @@ impl Iterator for ShockStepGenerator {
         match &self.mode {
             ShockAction::Ignore => None,
             ShockAction::Step => {
-                let columns = match self.last {
-                    0 | 3 => vec![0, 3],
-                    1 | 2 => vec![1, 2],
-                    4 | 7 => vec![4, 7],
-                    5 | 6 => vec![5, 6],
+                let columns = match (self.columns, self.last) {
+                    // solo: outer, upper and inner columns
+                    (6, 0) | (6, 5) => vec![0, 5],
+                    (6, 1) | (6, 4) => vec![1, 4],
+                    (6, 2) | (6, 3) => vec![2, 3],
+                    (_, 0) | (_, 3) => vec![0, 3],
+                    (_, 1) | (_, 2) => vec![1, 2],
+                    (_, 4) | (_, 7) => vec![4, 7],
+                    (_, 5) | (_, 6) => vec![5, 6],
                     _ => vec![],
                 };
                 self.last = (self.last + 1) % self.columns;
@@ -144,7 +188,7 @@ impl Iterator for ShockStepGenerator {
 }
 
 impl ShockStepGenerator {
-    fn new(columns: u8, mode: ShockAction) -> Self {
+    pub fn new(columns: u8, mode: ShockAction) -> Self {
         Self {
             last: 0,
             columns,
@@ -153,35 +197,18 @@ impl ShockStepGenerator {
     }
 }
 
-fn get_time_from_beats(beats: f32, tempo_changes: &ssq::TempoChanges) -> Option<Time> {
-    for tempo_change in tempo_changes.to_vec() {
-        // For TempoChanges that are infinitely short but exactly cover that beat, use the start
-        // time of that TempoChange
-        if (beats - tempo_change.start_beats).abs() < 0.001
-            && (beats - tempo_change.end_beats).abs() < 0.001
-        {
-            return Some(tempo_change.start_ms);
-        }
-
-        if beats < tempo_change.end_beats {
-            return Some(
-                tempo_change.start_ms
-                    + ((beats - tempo_change.start_beats) * tempo_change.beat_length) as u32,
-            );
-        }
-    }
-
-    None
+fn get_time_from_beats(beats: f32, timing_map: &ssq::TimingMap) -> Option<Time> {
+    timing_map.time_at(beats).map(|time| time.round() as Time)
 }
 
 impl From<ssq::TempoChange> for beatmap::TimingPoint {
     fn from(tempo_change: ssq::TempoChange) -> Self {
         beatmap::TimingPoint {
-            time: tempo_change.start_ms,
-            beat_length: if tempo_change.beat_length == f32::INFINITY {
-                10000.0
-            } else {
-                tempo_change.beat_length
+            time: tempo_change.start_ms.round() as Time,
+            beat_length: match tempo_change.kind {
+                ssq::TempoChangeKind::Scroll => tempo_change.beat_length as f32,
+                // osu! has no stops, a very slow tempo is the closest approximation
+                ssq::TempoChangeKind::Stop | ssq::TempoChangeKind::Delay => 10000.0,
             },
             meter: 4,
             sample_set: SampleSet::BeatmapDefault,
@@ -200,14 +227,14 @@ impl ssq::Step {
     fn to_hit_objects(
         &self,
         num_columns: u8,
-        tempo_changes: &ssq::TempoChanges,
+        timing_map: &ssq::TimingMap,
         shock_step_generator: &mut ShockStepGenerator,
     ) -> Option<Vec<beatmap::HitObject>> {
         let mut hit_objects = Vec::new();
 
         match self {
             ssq::Step::Step { beats, row } => {
-                let time = get_time_from_beats(*beats, tempo_changes);
+                let time = get_time_from_beats(*beats, timing_map);
 
                 match time {
                     Some(time) => {
@@ -234,8 +261,8 @@ impl ssq::Step {
                 }
             }
             ssq::Step::Freeze { start, end, row } => {
-                let time = get_time_from_beats(*start, tempo_changes);
-                let end_time = get_time_from_beats(*end, tempo_changes);
+                let time = get_time_from_beats(*start, timing_map);
+                let end_time = get_time_from_beats(*end, timing_map);
 
                 match (time, end_time) {
                     (Some(time), Some(end_time)) => {
@@ -278,13 +305,16 @@ impl ssq::Step {
                         beatmap::hit_object::ManiaHitCircleBuilder::default()
                             .column(column as u8)
                             .columns(num_columns)
-                            .time(get_time_from_beats(*beats, tempo_changes)?)
+                            .time(get_time_from_beats(*beats, timing_map)?)
                             .build()
                             .unwrap()
                             .into(),
                     )
                 }
             }
+            ssq::Step::Extra { kind, .. } => {
+                debug!("Extra step with type {} has no hit object", kind);
+            }
         }
 
         Some(hit_objects)
@@ -293,11 +323,35 @@ impl ssq::Step {
 
 struct ConvertedChart {
     level: ssq::Level,
+    /// Foot rating estimated from the chart, used if the musicdb has no level for it
+    estimated_level: u8,
+    /// Chart was generated from a harder chart
+    generated: bool,
+    modifiers: Option<Modifiers>,
     hit_objects: beatmap::HitObjects,
     timing_points: beatmap::TimingPoints,
 }
 
 impl ConvertedChart {
+    /// Returns the difficulty as `f32` from 0.0 to 1.0 for the HP drain and accuracy ranges.
+    ///
+    /// Without musicdb levels, the estimated level is used instead of the difficulty, because
+    /// it better reflects how hard the chart actually is.
+    fn relative_difficulty(&self, config: &Config) -> f32 {
+        match config.metadata.levels {
+            Some(_) if !self.generated => self.level.relative_difficulty(),
+            _ => rating::relative_level(self.estimated_level),
+        }
+    }
+
+    /// Returns the level with the modifiers (e.g. “Single Expert Mirror”).
+    fn name(&self) -> String {
+        match &self.modifiers {
+            Some(modifiers) => format!("{} {}", self.level, modifiers),
+            None => self.level.to_string(),
+        }
+    }
+
     fn to_beatmap(&self, config: &Config) -> beatmap::Beatmap {
         beatmap::BeatmapBuilder::default()
             .general(
@@ -327,22 +381,33 @@ impl ConvertedChart {
                             .clone(),
                     )
                     .creator(format!("{}", config))
-                    .version(match &config.metadata.levels {
-                        Some(levels) => {
-                            let level = self.level.to_value(levels);
-                            format!("{} (Lv. {})", self.level, level)
-                        }
-                        None => format!("{}", self.level),
-                    })
+                    .version(
+                        match config
+                            .metadata
+                            .levels
+                            .as_ref()
+                            .and_then(|levels| self.level.to_value(levels))
+                        {
+                            _ if self.generated => {
+                                format!(
+                                    "{} (generated, Lv. ~{})",
+                                    self.name(),
+                                    self.estimated_level
+                                )
+                            }
+                            Some(level) => format!("{} (Lv. {})", self.name(), level),
+                            None => format!("{} (Lv. ~{})", self.name(), self.estimated_level),
+                        },
+                    )
                     .source(config.metadata.source.clone())
                     .build()
                     .unwrap(),
             )
             .difficulty(
                 beatmap::DifficultyBuilder::default()
-                    .hp_drain_rate(config.hp_drain.map_from(self.level.relative_difficulty()))
-                    .circle_size(f32::from(self.level.players) * 4.0)
-                    .overall_difficulty(config.accuracy.map_from(self.level.relative_difficulty()))
+                    .hp_drain_rate(config.hp_drain.map_from(self.relative_difficulty(config)))
+                    .circle_size(f32::from(self.level.style.columns()))
+                    .overall_difficulty(config.accuracy.map_from(self.relative_difficulty(config)))
                     .approach_rate(8.0)
                     .slider_multiplier(0.64)
                     .slider_tick_rate(1.0)
@@ -363,7 +428,7 @@ impl ssq::SSQ {
         let mut timing_points = beatmap::TimingPoints(Vec::new());
 
         for entry in self.tempo_changes.to_vec() {
-            if config.stops || entry.beat_length != f32::INFINITY {
+            if config.stops || entry.kind == ssq::TempoChangeKind::Scroll {
                 trace!("Converting {:?} to to timing point", entry);
                 timing_points.push(entry.into());
             }
@@ -373,27 +438,66 @@ impl ssq::SSQ {
             self.tempo_changes.len()
         );
 
+        let timing_map = ssq::TimingMap::new(&self.tempo_changes);
+
+        let mut generated_charts = Vec::new();
+        if config.convert_styles {
+            generated_charts.append(&mut style::convert_missing(&self.charts));
+        }
+        if config.generate_easier {
+            let charts: Vec<ssq::Chart> = self
+                .charts
+                .iter()
+                .chain(generated_charts.iter())
+                .cloned()
+                .collect();
+            generated_charts.append(&mut simplify::generate_missing(&charts));
+        }
+        if !generated_charts.is_empty() {
+            info!("Generated {} step charts", generated_charts.len());
+        }
+
         let mut converted_charts = Vec::new();
 
-        for chart in &self.charts {
+        let mut charts: Vec<(ssq::Chart, bool, Option<Modifiers>)> = self
+            .charts
+            .iter()
+            .map(|chart| (chart.clone(), false))
+            .chain(generated_charts.into_iter().map(|chart| (chart, true)))
+            .map(|(chart, generated)| (chart, generated, None))
+            .collect();
+        let mut modified_charts = Vec::new();
+        for modifiers in &config.modifiers {
+            for (chart, generated, _) in &charts {
+                modified_charts.push((modifiers.apply(chart), *generated, Some(modifiers.clone())));
+            }
+        }
+        charts.append(&mut modified_charts);
+
+        for (chart, generated, modifiers) in charts {
             debug!("Converting chart {} to beatmap", chart.difficulty);
             let mut hit_objects = beatmap::HitObjects(Vec::new());
 
+            let columns = chart.difficulty.style.columns();
             let mut shock_step_generator =
-                ShockStepGenerator::new(chart.difficulty.players * 4, config.shock_action.clone());
+                ShockStepGenerator::new(columns, config.shock_action.clone());
             for step in &chart.steps {
                 trace!("Converting {:?} to hit object", step);
-                if let Some(mut step_hit_objects) = step.to_hit_objects(
-                    chart.difficulty.players * 4,
-                    &self.tempo_changes,
-                    &mut shock_step_generator,
-                ) {
+                if let Some(mut step_hit_objects) =
+                    step.to_hit_objects(columns, &timing_map, &mut shock_step_generator)
+                {
                     hit_objects.append(&mut step_hit_objects);
                 }
             }
 
+            let estimated_level = rating::estimate_level(&chart, &self.tempo_changes);
+            debug!("Estimated level {}", estimated_level);
+
             let converted_chart = ConvertedChart {
                 level: chart.difficulty.clone(),
+                estimated_level,
+                generated,
+                modifiers,
                 hit_objects,
                 timing_points: timing_points.clone(),
             };
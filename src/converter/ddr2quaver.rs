@@ -0,0 +1,251 @@
+use std::fmt;
+
+use clap::Clap;
+use log::{debug, info, trace, warn};
+
+use crate::converter::ddr2osu::{ConfigMetadata, ShockAction, ShockStepGenerator};
+use crate::ddr::analysis::rating;
+use crate::ddr::ssq;
+use crate::quaver::qua;
+
+#[derive(Debug, Clap, Clone)]
+pub struct Config {
+    #[clap(skip = "audio.wav")]
+    pub audio_filename: String,
+    #[clap(
+        long = "no-stops",
+        about = "Disable stops",
+        parse(from_flag = std::ops::Not::not),
+        display_order = 3
+    )]
+    pub stops: bool,
+    #[clap(
+        arg_enum,
+        long,
+        default_value = "step",
+        about = "What to do with shocks",
+        display_order = 3
+    )]
+    pub shock_action: ShockAction,
+    #[clap(flatten)]
+    pub metadata: ConfigMetadata,
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ddr2quaver ({}shock→{:?})",
+            if self.stops { "stops " } else { "" },
+            self.shock_action
+        )
+    }
+}
+
+/// Returns the game mode and whether it has a scratch key for a style.
+///
+/// Quaver only has 4 and 7 keys, 8 columns are played as 7 keys with a scratch key.
+fn game_mode(style: ssq::Style) -> Option<(qua::GameMode, bool)> {
+    match style.columns() {
+        4 => Some((qua::GameMode::Keys4, false)),
+        8 => Some((qua::GameMode::Keys7, true)),
+        _ => None,
+    }
+}
+
+/// Returns the indices of the columns that are stepped on.
+fn active_columns(row: &ssq::Row) -> Vec<u8> {
+    let columns: Vec<bool> = row.clone().into();
+    columns
+        .iter()
+        .enumerate()
+        .filter(|(_, active)| **active)
+        .map(|(column, _)| column as u8)
+        .collect()
+}
+
+fn get_time_from_beats(beats: f32, timing_map: &ssq::TimingMap) -> Option<i32> {
+    timing_map.time_at(beats).map(|time| time.round() as i32)
+}
+
+impl ssq::Step {
+    fn to_quaver_hit_objects(
+        &self,
+        timing_map: &ssq::TimingMap,
+        shock_step_generator: &mut ShockStepGenerator,
+    ) -> Option<Vec<qua::HitObject>> {
+        let (beats, end_beats, columns) = match self {
+            ssq::Step::Step { beats, row } => (*beats, None, active_columns(row)),
+            ssq::Step::Freeze { start, end, row } => (*start, Some(*end), active_columns(row)),
+            ssq::Step::Shock { beats } => (
+                *beats,
+                None,
+                shock_step_generator.next().unwrap_or_default(),
+            ),
+            ssq::Step::Extra { kind, .. } => {
+                debug!("Extra step with type {} has no hit object", kind);
+                return Some(Vec::new());
+            }
+        };
+
+        let start_time = match get_time_from_beats(beats, timing_map) {
+            Some(time) => time,
+            None => {
+                warn!("Could not get start time of step, skipping");
+                return None;
+            }
+        };
+        let end_time = match end_beats.map(|end| get_time_from_beats(end, timing_map)) {
+            Some(None) => {
+                warn!("Could not get end time of freeze, skipping");
+                return None;
+            }
+            Some(end_time) => end_time,
+            None => None,
+        };
+
+        Some(
+            columns
+                .into_iter()
+                .map(|column| qua::HitObject {
+                    start_time,
+                    lane: column + 1,
+                    end_time,
+                })
+                .collect(),
+        )
+    }
+}
+
+impl ssq::TempoChanges {
+    /// Converts tempo changes to timing points and stops to slider velocities of 0 (and 1 after
+    /// the stop), since Quaver has no stops.
+    fn to_quaver_timing(&self, stops: bool) -> (Vec<qua::TimingPoint>, Vec<qua::SliderVelocity>) {
+        let mut timing_points = Vec::new();
+        let mut slider_velocities = Vec::new();
+
+        for (i, tempo_change) in self.iter().enumerate() {
+            trace!("Converting {:?} to timing", tempo_change);
+            match tempo_change.kind {
+                ssq::TempoChangeKind::Scroll => timing_points.push(qua::TimingPoint {
+                    start_time: tempo_change.start_ms as f32,
+                    bpm: (60000.0 / tempo_change.beat_length) as f32,
+                }),
+                ssq::TempoChangeKind::Stop | ssq::TempoChangeKind::Delay if stops => {
+                    slider_velocities.push(qua::SliderVelocity {
+                        start_time: tempo_change.start_ms as f32,
+                        multiplier: 0.0,
+                    });
+                    if let Some(next) = self.get(i + 1) {
+                        slider_velocities.push(qua::SliderVelocity {
+                            start_time: next.start_ms as f32,
+                            multiplier: 1.0,
+                        });
+                    }
+                }
+                ssq::TempoChangeKind::Stop | ssq::TempoChangeKind::Delay => {}
+            }
+        }
+
+        (timing_points, slider_velocities)
+    }
+}
+
+impl ssq::SSQ {
+    /// Converts all Single and Double (and Couple and Battle) charts to Quaver maps.
+    ///
+    /// Solo charts are skipped, because Quaver has no 6 key mode.
+    pub fn to_quaver_maps(&self, config: &Config) -> Vec<qua::Map> {
+        debug!("Configuration: {:?}", config);
+
+        let (timing_points, slider_velocities) = self.tempo_changes.to_quaver_timing(config.stops);
+        debug!(
+            "Converted {} tempo changes to {} timing points and {} slider velocities",
+            self.tempo_changes.len(),
+            timing_points.len(),
+            slider_velocities.len()
+        );
+
+        let timing_map = ssq::TimingMap::new(&self.tempo_changes);
+
+        let mut maps = Vec::new();
+
+        for chart in &self.charts {
+            let (mode, has_scratch_key) = match game_mode(chart.difficulty.style) {
+                Some(mode) => mode,
+                None => {
+                    warn!(
+                        "Quaver has no mode with {} keys, skipping chart {}",
+                        chart.difficulty.style.columns(),
+                        chart.difficulty
+                    );
+                    continue;
+                }
+            };
+            debug!("Converting chart {} to map", chart.difficulty);
+
+            let mut shock_step_generator = ShockStepGenerator::new(
+                chart.difficulty.style.columns(),
+                config.shock_action.clone(),
+            );
+            let mut hit_objects = Vec::new();
+            for step in &chart.steps {
+                trace!("Converting {:?} to hit objects", step);
+                if let Some(mut step_hit_objects) =
+                    step.to_quaver_hit_objects(&timing_map, &mut shock_step_generator)
+                {
+                    hit_objects.append(&mut step_hit_objects);
+                }
+            }
+            hit_objects.sort_by_key(|hit_object| (hit_object.start_time, hit_object.lane));
+
+            let difficulty_name = match config
+                .metadata
+                .levels
+                .as_ref()
+                .and_then(|levels| chart.difficulty.to_value(levels))
+            {
+                Some(level) => format!("{} (Lv. {})", chart.difficulty, level),
+                None => format!(
+                    "{} (Lv. ~{})",
+                    chart.difficulty,
+                    rating::estimate_level(chart, &self.tempo_changes)
+                ),
+            };
+
+            debug!("Converted to map with {} hit objects", hit_objects.len());
+
+            maps.push(qua::Map {
+                audio_file: config.audio_filename.clone(),
+                song_preview_time: 0,
+                background_file: String::new(),
+                map_id: -1,
+                map_set_id: -1,
+                mode,
+                title: config
+                    .metadata
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| "unknown title".to_string()),
+                artist: config
+                    .metadata
+                    .artist
+                    .clone()
+                    .unwrap_or_else(|| "unknown artist".to_string()),
+                source: config.metadata.source.clone(),
+                tags: String::new(),
+                creator: config.to_string(),
+                difficulty_name,
+                description: String::new(),
+                has_scratch_key,
+                timing_points: timing_points.clone(),
+                slider_velocities: slider_velocities.clone(),
+                hit_objects,
+            });
+        }
+
+        info!("Converted {} step charts to maps", maps.len());
+
+        maps
+    }
+}
@@ -0,0 +1,372 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use log::{debug, info, warn};
+use thiserror::Error;
+
+use crate::ddr::analysis::NoteRow;
+use crate::ddr::ssq;
+use crate::ddr::transform::{sort_steps, steps_from_rows};
+use crate::stepmania::simfile::{Difficulty, Note, NoteData, Simfile, StepsType};
+
+/// Resolution of the tempo changes of converted charts (used by most SSQ files)
+const TICKS_PER_SECOND: u32 = 150;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("simfile has no tempo (#BPMS is empty)")]
+    NoTempo,
+    #[error("tempo {bpm} BPM at beat {beats} is not supported (only positive tempos are)")]
+    UnsupportedTempo { beats: f32, bpm: f64 },
+}
+
+impl From<StepsType> for ssq::Style {
+    fn from(steps_type: StepsType) -> Self {
+        match steps_type {
+            StepsType::DanceSingle => Self::Single,
+            StepsType::DanceDouble => Self::Double,
+            StepsType::DanceCouple => Self::Couple,
+            StepsType::DanceSolo => Self::Solo,
+        }
+    }
+}
+
+impl From<&NoteData> for ssq::Level {
+    fn from(note_data: &NoteData) -> Self {
+        Self {
+            style: note_data.steps_type.into(),
+            difficulty: match note_data.difficulty {
+                Difficulty::Beginner => 0,
+                Difficulty::Easy => 1,
+                Difficulty::Medium => 2,
+                Difficulty::Hard => 3,
+                Difficulty::Challenge => 4,
+                Difficulty::Edit => 5,
+            },
+            style_byte: None,
+        }
+    }
+}
+
+/// Returns the position of a line of a measure in beats.
+fn line_beats(measure: usize, line: usize, lines: usize) -> f32 {
+    4.0 * (measure as f32 + line as f32 / lines as f32)
+}
+
+impl NoteData {
+    /// Converts the notes to a chart.
+    ///
+    /// Holds and rolls become freezes, taps and lifts become steps. Since shocks always cover all
+    /// panels, every line with a mine becomes a shock. Fakes are removed.
+    fn to_chart(&self) -> ssq::Chart {
+        let columns = self.steps_type.columns();
+        let level = ssq::Level::from(self);
+
+        // rows and shocks by measure and line, so notes of one line are always combined
+        let mut rows: BTreeMap<(usize, usize), NoteRow> = BTreeMap::new();
+        let mut shocks: BTreeMap<(usize, usize), f32> = BTreeMap::new();
+        // line of the head of the currently held note of every column
+        let mut holds: Vec<Option<(usize, usize)>> = vec![None; columns];
+
+        for (measure, lines) in self.measures.iter().enumerate() {
+            for (line, notes) in lines.0.iter().enumerate() {
+                let beats = line_beats(measure, line, lines.0.len());
+                let key = (measure, line);
+
+                for (column, note) in notes.iter().enumerate().take(columns) {
+                    let new_row = || NoteRow {
+                        beats,
+                        columns: vec![false; columns],
+                        freeze_ends: vec![None; columns],
+                    };
+                    match note {
+                        // DDR has no lifts
+                        Note::Tap | Note::Lift => {
+                            rows.entry(key).or_insert_with(new_row).columns[column] = true
+                        }
+                        // DDR has no rolls
+                        Note::HoldHead | Note::RollHead => {
+                            rows.entry(key).or_insert_with(new_row).columns[column] = true;
+                            if let Some((measure, line)) = holds[column].replace(key) {
+                                warn!(
+                                    "Hold in measure {} (line {}) has no end, converting it to a step",
+                                    measure, line
+                                );
+                            }
+                        }
+                        Note::Tail => match holds[column].take() {
+                            Some(head) => {
+                                rows.get_mut(&head).unwrap().freeze_ends[column] = Some(beats)
+                            }
+                            None => debug!("Ignoring tail without hold at beat {}", beats),
+                        },
+                        Note::Mine => {
+                            shocks.insert(key, beats);
+                        }
+                        Note::Fake => debug!("Removing fake note at beat {}", beats),
+                        Note::Empty => {}
+                    }
+                }
+            }
+        }
+        for (measure, line) in holds.into_iter().flatten() {
+            warn!(
+                "Hold in measure {} (line {}) has no end, converting it to a step",
+                measure, line
+            );
+        }
+
+        let rows: Vec<NoteRow> = rows.into_values().collect();
+        let mut steps = steps_from_rows(&rows, level.style);
+        steps.extend(
+            shocks
+                .values()
+                .map(|beats| ssq::Step::Shock { beats: *beats }),
+        );
+        sort_steps(&mut steps);
+
+        ssq::Chart {
+            difficulty: level,
+            steps,
+        }
+    }
+}
+
+impl Simfile {
+    /// Converts the timing and the charts to an [`SSQ`].
+    ///
+    /// The offset is applied to the start of the first tempo change. Since SSQ files always start
+    /// at 0 ms, it is lost when the SSQ is written. The meter, description and radar values of
+    /// the charts are not converted.
+    ///
+    /// [`SSQ`]: ../../ddr/ssq/struct.SSQ.html
+    pub fn to_ssq(&self) -> Result<ssq::SSQ, Error> {
+        let mut bpms = self.bpms.clone();
+        bpms.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        if bpms.is_empty() {
+            return Err(Error::NoTempo);
+        }
+        if let Some((beats, bpm)) = bpms
+            .iter()
+            .find(|(_, bpm)| !(*bpm > 0.0 && bpm.is_finite()))
+        {
+            return Err(Error::UnsupportedTempo {
+                beats: *beats,
+                bpm: *bpm,
+            });
+        }
+
+        // delays before stops, since steps are hit after delays and before stops
+        let mut pauses: Vec<(f32, ssq::TempoChangeKind, f64)> = Vec::new();
+        for (kind, list) in &[
+            (ssq::TempoChangeKind::Delay, &self.delays),
+            (ssq::TempoChangeKind::Stop, &self.stops),
+        ] {
+            for (beats, duration) in list.iter() {
+                if *duration < 0.0 {
+                    warn!(
+                        "Negative {:?} at beat {} is not supported, removing it",
+                        kind, beats
+                    );
+                } else if *duration > 0.0 {
+                    pauses.push((*beats, *kind, duration * 1000.0));
+                }
+            }
+        }
+        pauses.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let charts: Vec<ssq::Chart> = self
+            .charts
+            .iter()
+            .map(|note_data| {
+                debug!(
+                    "Converting chart {} {}",
+                    note_data.steps_type, note_data.difficulty
+                );
+                note_data.to_chart()
+            })
+            .collect();
+        info!("Converted {} charts to steps", charts.len());
+
+        // the charts end after the measure of the last step or tempo change
+        let last = charts
+            .iter()
+            .flat_map(|chart| chart.steps.iter())
+            .map(|step| match step {
+                ssq::Step::Step { beats, .. }
+                | ssq::Step::Shock { beats }
+                | ssq::Step::Extra { beats, .. } => *beats,
+                ssq::Step::Freeze { end, .. } => *end,
+            })
+            .chain(bpms.iter().map(|(beats, _)| *beats))
+            .chain(pauses.iter().map(|(beats, _, _)| *beats))
+            .fold(0.0, f32::max);
+        let end = 4.0 * ((last / 4.0).floor() + 1.0);
+
+        let mut boundaries: Vec<f32> = bpms
+            .iter()
+            .map(|(beats, _)| *beats)
+            .chain(pauses.iter().map(|(beats, _, _)| *beats))
+            .filter(|beats| *beats > 0.0)
+            .collect();
+        boundaries.push(0.0);
+        boundaries.push(end);
+        boundaries.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        boundaries.dedup();
+
+        let mut changes = Vec::new();
+        let mut time = -self.offset * 1000.0;
+        for window in boundaries.windows(2) {
+            let (start, end) = (window[0], window[1]);
+
+            for (beats, kind, duration) in &pauses {
+                // pauses before the first beat are moved to it
+                if *beats == start || (start == 0.0 && *beats < 0.0) {
+                    changes.push(ssq::TempoChange {
+                        start_ms: time,
+                        start_beats: start,
+                        end_beats: start,
+                        beat_length: f64::INFINITY,
+                        kind: *kind,
+                    });
+                    time += duration;
+                }
+            }
+
+            // the first tempo also applies before its position
+            let bpm = bpms
+                .iter()
+                .rev()
+                .find(|(beats, _)| *beats <= start)
+                .unwrap_or(&bpms[0])
+                .1;
+            let beat_length = 60000.0 / bpm;
+            changes.push(ssq::TempoChange {
+                start_ms: time,
+                start_beats: start,
+                end_beats: end,
+                beat_length,
+                kind: ssq::TempoChangeKind::Scroll,
+            });
+            time += beat_length * f64::from(end - start);
+        }
+        debug!("Converted timing to {} tempo changes", changes.len());
+
+        Ok(ssq::SSQ {
+            tempo_changes: ssq::TempoChanges {
+                ticks_per_second: TICKS_PER_SECOND,
+                first_measure: 0,
+                first_tick: 0,
+                changes,
+            },
+            charts,
+            background_changes: Vec::new(),
+            light_cues: Vec::new(),
+            unknown_chunks: Vec::new(),
+            chunk_order: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Row, Step, Style, TempoChange, TempoChangeKind, TempoChanges};
+
+    const SIMFILE: &str = "#TITLE:Title;
+#OFFSET:-0.125;
+#BPMS:0=150,4=300;
+#STOPS:-1=0.25,2=0.5;
+#DELAYS:2=0.125;
+#NOTES:
+     dance-single:
+     :
+     Hard:
+     9:
+     :
+1000
+0200
+M300
+0002
+,
+1000
+0000
+0000
+0000
+;
+";
+
+    #[test]
+    fn test_to_ssq() {
+        let ssq = Simfile::parse(SIMFILE).unwrap().to_ssq().unwrap();
+
+        let change = |start_ms, start_beats, end_beats, beat_length, kind| TempoChange {
+            start_ms,
+            start_beats,
+            end_beats,
+            beat_length,
+            kind,
+        };
+        assert_eq!(
+            ssq.tempo_changes,
+            TempoChanges {
+                ticks_per_second: 150,
+                first_measure: 0,
+                first_tick: 0,
+                changes: vec![
+                    // stop before the first beat, moved to it (after the offset)
+                    change(125.0, 0.0, 0.0, f64::INFINITY, TempoChangeKind::Stop),
+                    change(375.0, 0.0, 2.0, 400.0, TempoChangeKind::Scroll),
+                    // delay before stop at the same beat
+                    change(1175.0, 2.0, 2.0, f64::INFINITY, TempoChangeKind::Delay),
+                    change(1300.0, 2.0, 2.0, f64::INFINITY, TempoChangeKind::Stop),
+                    change(1800.0, 2.0, 4.0, 400.0, TempoChangeKind::Scroll),
+                    // the last window ends after the measure of the last step
+                    change(2600.0, 4.0, 8.0, 200.0, TempoChangeKind::Scroll),
+                ],
+            }
+        );
+
+        assert_eq!(ssq.charts.len(), 1);
+        assert_eq!(
+            ssq.charts[0].difficulty,
+            ssq::Level::new(Style::Single, 3).unwrap()
+        );
+        assert_eq!(
+            ssq.charts[0].steps,
+            vec![
+                Step::Step {
+                    beats: 0.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Freeze {
+                    start: 1.0,
+                    end: 2.0,
+                    row: Row::new(0b0010, Style::Single),
+                },
+                // the mine becomes a shock
+                Step::Shock { beats: 2.0 },
+                // the hold without tail becomes a step
+                Step::Step {
+                    beats: 3.0,
+                    row: Row::new(0b1000, Style::Single),
+                },
+                Step::Step {
+                    beats: 4.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_ssq_unsupported_tempo() {
+        let simfile = Simfile::parse(&SIMFILE.replace("4=300", "4=-300")).unwrap();
+        assert!(matches!(
+            simfile.to_ssq(),
+            Err(Error::UnsupportedTempo { beats, .. }) if beats == 4.0
+        ));
+        let simfile = Simfile::parse(&SIMFILE.replace("#BPMS:0=150,4=300;", "")).unwrap();
+        assert!(matches!(simfile.to_ssq(), Err(Error::NoTempo)));
+    }
+}
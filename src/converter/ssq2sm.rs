@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use clap::Clap;
+use log::{debug, info, warn};
+
+use crate::converter::ddr2osu::ConfigMetadata;
+use crate::ddr::analysis::groove_radar::GrooveRadar;
+use crate::ddr::analysis::quantization::{common_snap, Snap};
+use crate::ddr::analysis::{rating, rows, shocks};
+use crate::ddr::ssq;
+use crate::stepmania::simfile::{Difficulty, Measure, Note, NoteData, Simfile, StepsType};
+
+/// Tempo changes (in BPM) that differ by less than this are merged
+const BPM_TOLERANCE: f64 = 0.001;
+
+#[derive(Debug, Clap, Clone)]
+pub struct Config {
+    #[clap(skip = "audio.wav")]
+    pub audio_filename: String,
+    #[clap(flatten)]
+    pub metadata: ConfigMetadata,
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ssq2sm")
+    }
+}
+
+impl From<ssq::Style> for StepsType {
+    fn from(style: ssq::Style) -> Self {
+        match style {
+            ssq::Style::Single => Self::DanceSingle,
+            ssq::Style::Double => Self::DanceDouble,
+            ssq::Style::Solo => Self::DanceSolo,
+            // StepMania has no battle mode, both players have their own steps like in couple
+            ssq::Style::Couple | ssq::Style::Battle => Self::DanceCouple,
+        }
+    }
+}
+
+impl From<&ssq::Level> for Difficulty {
+    fn from(level: &ssq::Level) -> Self {
+        match level.difficulty {
+            0 => Self::Beginner,
+            1 => Self::Easy,
+            2 => Self::Medium,
+            3 => Self::Hard,
+            4 => Self::Challenge,
+            _ => Self::Edit,
+        }
+    }
+}
+
+/// Splits notes (position, column, note) into measures with the coarsest snap that fits all
+/// notes of a measure.
+///
+/// Mines are only placed on lines without other notes in their column. Notes before the first
+/// measure can not be stored and are removed.
+fn to_measures(notes: &[(f32, usize, Note)], columns: usize) -> Vec<Measure> {
+    let mut by_measure: BTreeMap<i32, Vec<&(f32, usize, Note)>> = BTreeMap::new();
+    for note in notes {
+        let (measure, _) = Snap::HundredNinetySecond.measure_index(note.0);
+        if measure < 0 {
+            warn!(
+                "Note at beat {} is before the first measure, removing it",
+                note.0
+            );
+            continue;
+        }
+        by_measure.entry(measure).or_default().push(note);
+    }
+
+    let last = match by_measure.keys().last() {
+        Some(last) => *last,
+        None => return Vec::new(),
+    };
+
+    (0..=last)
+        .map(|measure| {
+            let notes = match by_measure.get(&measure) {
+                Some(notes) => notes,
+                None => return Measure::empty(4, columns),
+            };
+            let positions: Vec<f32> = notes.iter().map(|(beats, _, _)| *beats).collect();
+            let snap = common_snap(&positions);
+
+            let mut lines = Measure::empty(usize::from(snap.per_measure()), columns);
+            // mines first, so other notes replace them
+            let mut sorted = notes.clone();
+            sorted.sort_by_key(|(_, _, note)| *note != Note::Mine);
+            for (beats, column, note) in sorted {
+                let (_, index) = snap.measure_index(*beats);
+                let cell = &mut lines.0[usize::from(index)][*column];
+                if *note != Note::Mine || *cell == Note::Empty {
+                    *cell = *note;
+                }
+            }
+            lines
+        })
+        .collect()
+}
+
+impl ssq::Chart {
+    fn to_note_data(&self, tempo_changes: &ssq::TempoChanges, config: &Config) -> NoteData {
+        let columns = usize::from(self.difficulty.style.columns());
+
+        let mut notes = Vec::new();
+        for row in rows(self) {
+            for (column, active) in row.columns.iter().enumerate() {
+                if !active {
+                    continue;
+                }
+                match row.freeze_ends[column] {
+                    Some(end) => {
+                        notes.push((row.beats, column, Note::HoldHead));
+                        notes.push((end, column, Note::Tail));
+                    }
+                    None => notes.push((row.beats, column, Note::Tap)),
+                }
+            }
+        }
+        for beats in shocks(self) {
+            for column in 0..columns {
+                notes.push((beats, column, Note::Mine));
+            }
+        }
+        for step in &self.steps {
+            if let ssq::Step::Extra { kind, .. } = step {
+                debug!("Extra step with type {} has no note, removing it", kind);
+            }
+        }
+
+        let meter = match config
+            .metadata
+            .levels
+            .as_ref()
+            .and_then(|levels| self.difficulty.to_value(levels))
+        {
+            Some(level) => level,
+            None => rating::estimate_level(self, tempo_changes),
+        };
+        let radar = GrooveRadar::new(self, tempo_changes);
+
+        let difficulty = Difficulty::from(&self.difficulty);
+        NoteData {
+            steps_type: self.difficulty.style.into(),
+            // StepMania identifies edits by their description
+            description: if difficulty == Difficulty::Edit {
+                self.difficulty.to_string()
+            } else {
+                String::new()
+            },
+            difficulty,
+            meter,
+            radar_values: vec![
+                radar.stream / 100.0,
+                radar.voltage / 100.0,
+                radar.air / 100.0,
+                radar.freeze / 100.0,
+                radar.chaos / 100.0,
+            ],
+            measures: to_measures(&notes, columns),
+        }
+    }
+}
+
+impl ssq::SSQ {
+    pub fn to_simfile(&self, config: &Config) -> Simfile {
+        debug!("Configuration: {:?}", config);
+
+        let mut bpms: Vec<(f32, f64)> = Vec::new();
+        for tempo_change in self.tempo_changes.iter() {
+            if tempo_change.kind != ssq::TempoChangeKind::Scroll {
+                continue;
+            }
+            let bpm = 60000.0 / tempo_change.beat_length;
+            if !matches!(bpms.last(), Some((_, last)) if (last - bpm).abs() < BPM_TOLERANCE) {
+                bpms.push((tempo_change.start_beats, bpm));
+            }
+        }
+
+        // the last tempo change (end of the chart) is a stop without duration
+        let pauses = |pauses: Vec<ssq::Stop>| -> Vec<(f32, f64)> {
+            pauses
+                .into_iter()
+                .filter(|pause| pause.duration_ms > 0.0)
+                .map(|pause| (pause.beats, pause.duration_ms / 1000.0))
+                .collect()
+        };
+        let stops = pauses(self.tempo_changes.stops());
+        let delays = pauses(self.tempo_changes.delays());
+        debug!(
+            "Converted tempo changes to {} BPM changes, {} stops and {} delays",
+            bpms.len(),
+            stops.len(),
+            delays.len()
+        );
+
+        // negated time of beat 0, extrapolated from the first tempo change
+        let offset = self
+            .tempo_changes
+            .iter()
+            .find(|tempo_change| tempo_change.kind == ssq::TempoChangeKind::Scroll)
+            .map_or(0.0, |first| {
+                (f64::from(first.start_beats) * first.beat_length - first.start_ms) / 1000.0
+            });
+
+        let charts: Vec<NoteData> = self
+            .charts
+            .iter()
+            .map(|chart| {
+                debug!("Converting chart {} to notes", chart.difficulty);
+                chart.to_note_data(&self.tempo_changes, config)
+            })
+            .collect();
+        info!("Converted {} step charts to notes", charts.len());
+
+        Simfile {
+            title: config
+                .metadata
+                .title
+                .clone()
+                .unwrap_or_else(|| "unknown title".to_string()),
+            artist: config
+                .metadata
+                .artist
+                .clone()
+                .unwrap_or_else(|| "unknown artist".to_string()),
+            genre: config.metadata.source.clone(),
+            credit: config.to_string(),
+            music: config.audio_filename.clone(),
+            offset,
+            bpms,
+            stops,
+            delays,
+            charts,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Chart, Level, Row, Step, Style, TempoChange, TempoChangeKind};
+
+    fn config() -> Config {
+        Config {
+            audio_filename: "audio.wav".to_string(),
+            metadata: ConfigMetadata {
+                title: None,
+                artist: None,
+                source: "Dance Dance Revolution".to_string(),
+                levels: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_to_measures() {
+        let notes = [
+            // removed, since it is before the first measure
+            (-1.0, 0, Note::Tap),
+            // 8th and 12th in one measure need 24th
+            (0.0, 0, Note::Tap),
+            (0.5, 1, Note::Tap),
+            (4.0 / 3.0, 2, Note::Tap),
+            // hold across the measure line
+            (3.0, 3, Note::HoldHead),
+            (5.0, 3, Note::Tail),
+            // shock on a line with a step
+            (6.0, 0, Note::Mine),
+            (6.0, 1, Note::Mine),
+            (6.0, 2, Note::Mine),
+            (6.0, 3, Note::Mine),
+            (6.0, 0, Note::Tap),
+        ];
+
+        let mut first = Measure::empty(24, 4);
+        first.0[0][0] = Note::Tap;
+        first.0[3][1] = Note::Tap;
+        first.0[8][2] = Note::Tap;
+        first.0[18][3] = Note::HoldHead;
+        let mut second = Measure::empty(4, 4);
+        second.0[1][3] = Note::Tail;
+        second.0[2] = vec![Note::Tap, Note::Mine, Note::Mine, Note::Mine];
+
+        assert_eq!(to_measures(&notes, 4), vec![first, second]);
+        assert_eq!(to_measures(&[], 4), Vec::new());
+    }
+
+    #[test]
+    fn test_to_simfile() {
+        let scroll = |start_ms, start_beats, end_beats, beat_length| TempoChange {
+            start_ms,
+            start_beats,
+            end_beats,
+            beat_length,
+            kind: TempoChangeKind::Scroll,
+        };
+        let stop = |start_ms, beats| TempoChange {
+            start_ms,
+            start_beats: beats,
+            end_beats: beats,
+            beat_length: f64::INFINITY,
+            kind: TempoChangeKind::Stop,
+        };
+        let ssq = ssq::SSQ {
+            tempo_changes: ssq::TempoChanges {
+                ticks_per_second: 150,
+                first_measure: 0,
+                first_tick: 0,
+                changes: vec![
+                    // the first tempo change does not start at beat 0
+                    scroll(1000.0, 4.0, 8.0, 500.0),
+                    // same tempo, merged with the previous one
+                    scroll(3000.0, 8.0, 12.0, 500.0),
+                    stop(5000.0, 12.0),
+                    scroll(5500.0, 12.0, 16.0, 250.0),
+                    // end of the chart
+                    stop(6500.0, 16.0),
+                ],
+            },
+            charts: vec![Chart {
+                difficulty: Level::new(Style::Single, 3).unwrap(),
+                steps: vec![
+                    Step::Step {
+                        beats: 4.0,
+                        row: Row::new(0b0001, Style::Single),
+                    },
+                    Step::Freeze {
+                        start: 6.0,
+                        end: 10.0,
+                        row: Row::new(0b1000, Style::Single),
+                    },
+                ],
+            }],
+            background_changes: Vec::new(),
+            light_cues: Vec::new(),
+            unknown_chunks: Vec::new(),
+            chunk_order: Vec::new(),
+        };
+
+        let simfile = ssq.to_simfile(&config());
+        // beat 0 is one second before the start
+        assert_eq!(simfile.offset, 1.0);
+        assert_eq!(simfile.bpms, vec![(4.0, 120.0), (12.0, 240.0)]);
+        assert_eq!(simfile.stops, vec![(12.0, 0.5)]);
+        assert_eq!(simfile.delays, Vec::new());
+
+        assert_eq!(simfile.charts.len(), 1);
+        let note_data = &simfile.charts[0];
+        assert_eq!(note_data.steps_type, StepsType::DanceSingle);
+        assert_eq!(note_data.difficulty, Difficulty::Hard);
+        let mut second = Measure::empty(4, 4);
+        second.0[0][0] = Note::Tap;
+        second.0[2][3] = Note::HoldHead;
+        let mut third = Measure::empty(4, 4);
+        third.0[2][3] = Note::Tail;
+        assert_eq!(
+            note_data.measures,
+            vec![Measure::empty(4, 4), second, third]
+        );
+    }
+
+    #[test]
+    fn test_sm2ssq_round_trip() {
+        let simfile = Simfile::parse(
+            "#OFFSET:0.25;
+#BPMS:0=150,8=300;
+#STOPS:4=0.5;
+#DELAYS:6=0.25;
+#NOTES:
+     dance-single:
+     :
+     Hard:
+     9:
+     :
+1000
+0200
+0000
+0300
+,
+MMMM
+0010
+0000
+0001
+;
+",
+        )
+        .unwrap();
+
+        let converted = simfile.to_ssq().unwrap().to_simfile(&config());
+        assert_eq!(converted.offset, simfile.offset);
+        assert_eq!(converted.bpms, simfile.bpms);
+        assert_eq!(converted.stops, simfile.stops);
+        assert_eq!(converted.delays, simfile.delays);
+        assert_eq!(converted.charts.len(), 1);
+        assert_eq!(converted.charts[0].measures, simfile.charts[0].measures);
+    }
+}
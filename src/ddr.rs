@@ -1,3 +1,7 @@
+pub mod analysis;
 pub mod arc;
 pub mod musicdb;
+pub mod render;
+pub mod scoring;
 pub mod ssq;
+pub mod transform;
@@ -0,0 +1,172 @@
+//! Analysis of step charts.
+//!
+//! Most analyses are not interested in how the SSQ file stores steps (freezes that start on a
+//! jump are split into a step and a freeze), but in which panels are stepped on at which
+//! position. [`rows`] provides this view of a chart.
+//!
+//! [`rows`]: fn.rows.html
+pub mod diff;
+pub mod groove_radar;
+pub mod parity;
+pub mod patterns;
+pub mod quantization;
+pub mod rating;
+pub mod stats;
+
+use std::cmp::Ordering;
+
+use crate::ddr::ssq::{Chart, Step};
+
+/// All panels that are stepped on at one position of a chart.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoteRow {
+    pub beats: f32,
+    /// Panels that are stepped on (including freeze starts)
+    pub columns: Vec<bool>,
+    /// Position at which each column that starts a freeze in this row is released
+    pub freeze_ends: Vec<Option<f32>>,
+}
+
+impl NoteRow {
+    fn new(beats: f32, columns: usize) -> Self {
+        Self {
+            beats,
+            columns: vec![false; columns],
+            freeze_ends: vec![None; columns],
+        }
+    }
+
+    /// Returns the number of panels that are stepped on.
+    pub fn count_active(&self) -> usize {
+        self.columns.iter().filter(|column| **column).count()
+    }
+
+    /// Returns true if more than one panel is stepped on.
+    pub fn is_jump(&self) -> bool {
+        self.count_active() > 1
+    }
+
+    /// Returns the number of freezes started in this row.
+    pub fn count_freezes(&self) -> usize {
+        self.freeze_ends.iter().filter(|end| end.is_some()).count()
+    }
+
+    /// Returns the position at which the last freeze of this row ends.
+    pub fn last_freeze_end(&self) -> Option<f32> {
+        self.freeze_ends
+            .iter()
+            .filter_map(|end| *end)
+            .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+    }
+}
+
+/// Merges the steps and freezes of a chart into rows, ordered by their position.
+///
+/// Shocks and extra steps are not included.
+pub fn rows(chart: &Chart) -> Vec<NoteRow> {
+    let columns = usize::from(chart.difficulty.style.columns());
+
+    let mut steps: Vec<(f32, Vec<bool>, Option<f32>)> = chart
+        .steps
+        .iter()
+        .filter_map(|step| match step {
+            Step::Step { beats, row } => Some((*beats, row.clone().into(), None)),
+            Step::Freeze { start, end, row } => Some((*start, row.clone().into(), Some(*end))),
+            Step::Shock { .. } | Step::Extra { .. } => None,
+        })
+        .collect();
+    // freezes are stored at the position of their end
+    steps.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+    let mut rows: Vec<NoteRow> = Vec::new();
+
+    for (beats, step_columns, end) in steps {
+        let row = match rows.last_mut() {
+            Some(row) if row.beats == beats => row,
+            _ => {
+                rows.push(NoteRow::new(beats, columns));
+                rows.last_mut().unwrap()
+            }
+        };
+        for (column, active) in step_columns.into_iter().enumerate().take(columns) {
+            if active {
+                row.columns[column] = true;
+                if end.is_some() {
+                    row.freeze_ends[column] = end;
+                }
+            }
+        }
+    }
+
+    rows
+}
+
+/// Returns the positions of all shocks of a chart.
+pub fn shocks(chart: &Chart) -> Vec<f32> {
+    chart
+        .steps
+        .iter()
+        .filter_map(|step| match step {
+            Step::Shock { beats } => Some(*beats),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Level, Row, Style};
+
+    #[test]
+    fn test_rows() {
+        let chart = Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: vec![
+                Step::Step {
+                    beats: 1.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Step {
+                    beats: 2.0,
+                    row: Row::new(0b1000, Style::Single),
+                },
+                Step::Freeze {
+                    start: 2.0,
+                    end: 3.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Shock { beats: 4.0 },
+            ],
+        };
+        assert_eq!(
+            rows(&chart),
+            vec![
+                NoteRow {
+                    beats: 1.0,
+                    columns: vec![true, false, false, false],
+                    freeze_ends: vec![None; 4],
+                },
+                NoteRow {
+                    beats: 2.0,
+                    columns: vec![true, false, false, true],
+                    freeze_ends: vec![Some(3.0), None, None, None],
+                },
+            ]
+        );
+        assert_eq!(shocks(&chart), vec![4.0]);
+    }
+
+    #[test]
+    fn test_note_row() {
+        let row = NoteRow {
+            beats: 0.0,
+            columns: vec![true, false, true, true],
+            freeze_ends: vec![Some(2.0), None, Some(3.0), None],
+        };
+        assert_eq!(row.count_active(), 3);
+        assert!(row.is_jump());
+        assert_eq!(row.count_freezes(), 2);
+        assert_eq!(row.last_freeze_end(), Some(3.0));
+    }
+}
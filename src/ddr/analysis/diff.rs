@@ -0,0 +1,777 @@
+//! Differences between two SSQ files.
+//!
+//! Charts are matched by their [`Level`]. Rows (including freeze starts) at the same position are
+//! compared by their columns, rows that only exist in one file are reported as moved if a row with
+//! the same columns and freezes exists nearby in the other file. Freezes and shocks are compared
+//! separately, tempo changes by the tempo at every position where one of the files changes it.
+//!
+//! [`Level`]: ../../ssq/struct.Level.html
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use super::{rows, shocks, NoteRow};
+use crate::ddr::ssq::{Chart, Level, Row, Stop, TempoChanges, TimingMap, SSQ};
+
+/// Maximum distance (in beats) of a step that is reported as moved instead of removed and added
+const MOVE_DISTANCE: f32 = 1.0;
+const BPM_TOLERANCE: f32 = 0.01;
+/// Tolerance for durations of stops (in milliseconds)
+const DURATION_TOLERANCE: f64 = 1.0;
+/// Positions are compared in this fraction of a beat (the resolution of SSQ files)
+const TICKS_PER_BEAT: f32 = 1024.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeKind {
+    ChartAdded,
+    ChartRemoved,
+    StepAdded(Row),
+    StepRemoved(Row),
+    /// Row at the same position with different columns
+    StepChanged {
+        from: Row,
+        to: Row,
+    },
+    /// Row with the same columns (and freezes) at a nearby position
+    StepMoved {
+        row: Row,
+        to: f32,
+    },
+    FreezeAdded {
+        row: Row,
+        end: f32,
+    },
+    FreezeRemoved {
+        row: Row,
+        end: f32,
+    },
+    FreezeChanged {
+        row: Row,
+        from_end: f32,
+        to_end: f32,
+    },
+    ShockAdded,
+    ShockRemoved,
+    /// Tempo in BPM
+    TempoChanged {
+        from: f32,
+        to: f32,
+    },
+    /// Duration of stops in milliseconds
+    StopAdded(f64),
+    StopRemoved(f64),
+    StopChanged {
+        from: f64,
+        to: f64,
+    },
+}
+
+impl ChangeKind {
+    /// Returns a short name (e.g. `step_added`) for machine-readable output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ChartAdded => "chart_added",
+            Self::ChartRemoved => "chart_removed",
+            Self::StepAdded(_) => "step_added",
+            Self::StepRemoved(_) => "step_removed",
+            Self::StepChanged { .. } => "step_changed",
+            Self::StepMoved { .. } => "step_moved",
+            Self::FreezeAdded { .. } => "freeze_added",
+            Self::FreezeRemoved { .. } => "freeze_removed",
+            Self::FreezeChanged { .. } => "freeze_changed",
+            Self::ShockAdded => "shock_added",
+            Self::ShockRemoved => "shock_removed",
+            Self::TempoChanged { .. } => "tempo_changed",
+            Self::StopAdded(_) => "stop_added",
+            Self::StopRemoved(_) => "stop_removed",
+            Self::StopChanged { .. } => "stop_changed",
+        }
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ChartAdded => write!(f, "chart added"),
+            Self::ChartRemoved => write!(f, "chart removed"),
+            Self::StepAdded(row) => write!(f, "added [{}]", row),
+            Self::StepRemoved(row) => write!(f, "removed [{}]", row),
+            Self::StepChanged { from, to } => write!(f, "changed [{}] → [{}]", from, to),
+            Self::StepMoved { row, to } => {
+                write!(f, "moved [{}] to {}", row, format_position(*to))
+            }
+            Self::FreezeAdded { row, end } => {
+                write!(f, "added freeze [{}] until {}", row, format_position(*end))
+            }
+            Self::FreezeRemoved { row, end } => {
+                write!(
+                    f,
+                    "removed freeze [{}] until {}",
+                    row,
+                    format_position(*end)
+                )
+            }
+            Self::FreezeChanged {
+                row,
+                from_end,
+                to_end,
+            } => write!(
+                f,
+                "changed end of freeze [{}] from {} to {}",
+                row,
+                format_position(*from_end),
+                format_position(*to_end)
+            ),
+            Self::ShockAdded => write!(f, "added shock"),
+            Self::ShockRemoved => write!(f, "removed shock"),
+            Self::TempoChanged { from, to } => write!(
+                f,
+                "tempo changed from {} to {} BPM",
+                format_number(f64::from(*from)),
+                format_number(f64::from(*to))
+            ),
+            Self::StopAdded(duration) => {
+                write!(f, "added stop ({} ms)", format_number(*duration))
+            }
+            Self::StopRemoved(duration) => {
+                write!(f, "removed stop ({} ms)", format_number(*duration))
+            }
+            Self::StopChanged { from, to } => write!(
+                f,
+                "changed stop from {} ms to {} ms",
+                format_number(*from),
+                format_number(*to)
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    /// Chart of the change, `None` for changes of the tempo
+    pub level: Option<Level>,
+    /// Position in the first file (in the second file for additions)
+    pub beats: f32,
+    pub kind: ChangeKind,
+}
+
+impl Change {
+    /// Returns the fields of the change for machine-readable output: chart, position (in beats),
+    /// kind and the old and new value (`-` if there is none).
+    pub fn record(&self) -> [String; 5] {
+        let row = |row: &Row| -> String {
+            let columns: Vec<bool> = row.clone().into();
+            columns
+                .iter()
+                .map(|active| if *active { '1' } else { '0' })
+                .collect()
+        };
+        let none = || "-".to_string();
+        let (from, to) = match &self.kind {
+            ChangeKind::ChartAdded
+            | ChangeKind::ChartRemoved
+            | ChangeKind::ShockAdded
+            | ChangeKind::ShockRemoved => (none(), none()),
+            ChangeKind::StepAdded(added) => (none(), row(added)),
+            ChangeKind::StepRemoved(removed) => (row(removed), none()),
+            ChangeKind::StepChanged { from, to } => (row(from), row(to)),
+            ChangeKind::StepMoved { row: moved, to } => (row(moved), to.to_string()),
+            ChangeKind::FreezeAdded { row: added, end } => {
+                (none(), format!("{}:{}", row(added), end))
+            }
+            ChangeKind::FreezeRemoved { row: removed, end } => {
+                (format!("{}:{}", row(removed), end), none())
+            }
+            ChangeKind::FreezeChanged {
+                row: changed,
+                from_end,
+                to_end,
+            } => (
+                format!("{}:{}", row(changed), from_end),
+                format!("{}:{}", row(changed), to_end),
+            ),
+            ChangeKind::TempoChanged { from, to } => (from.to_string(), to.to_string()),
+            ChangeKind::StopAdded(duration) => (none(), duration.to_string()),
+            ChangeKind::StopRemoved(duration) => (duration.to_string(), none()),
+            ChangeKind::StopChanged { from, to } => (from.to_string(), to.to_string()),
+        };
+
+        [
+            self.level
+                .as_ref()
+                .map_or("tempo".to_string(), |level| level.to_string()),
+            self.beats.to_string(),
+            self.kind.name().to_string(),
+            from,
+            to,
+        ]
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", format_position(self.beats), self.kind)
+    }
+}
+
+/// Formats a position as measure and beat within the measure (both starting at 1), e.g.
+/// `m12 b2.5`.
+pub fn format_position(beats: f32) -> String {
+    let measure = (beats / 4.0).floor();
+    let beat = beats - measure * 4.0 + 1.0;
+    format!("m{} b{}", measure + 1.0, format_number(f64::from(beat)))
+}
+
+/// Formats a number with at most three decimals and without trailing zeroes.
+fn format_number(number: f64) -> String {
+    let formatted = format!("{:.3}", number);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn tick(beats: f32) -> i64 {
+    (beats * TICKS_PER_BEAT).round() as i64
+}
+
+/// Returns all changes from `a` to `b`: tempo changes first, then the changes of every chart.
+pub fn diff(a: &SSQ, b: &SSQ) -> Vec<Change> {
+    let mut changes = diff_tempo(&a.tempo_changes, &b.tempo_changes);
+
+    for chart in &a.charts {
+        match b
+            .charts
+            .iter()
+            .find(|other| other.difficulty == chart.difficulty)
+        {
+            Some(other) => changes.append(&mut diff_charts(chart, other)),
+            None => changes.push(Change {
+                level: Some(chart.difficulty.clone()),
+                beats: 0.0,
+                kind: ChangeKind::ChartRemoved,
+            }),
+        }
+    }
+    for chart in &b.charts {
+        if !a
+            .charts
+            .iter()
+            .any(|other| other.difficulty == chart.difficulty)
+        {
+            changes.push(Change {
+                level: Some(chart.difficulty.clone()),
+                beats: 0.0,
+                kind: ChangeKind::ChartAdded,
+            });
+        }
+    }
+
+    changes
+}
+
+/// Returns the stops without the end of the chart (which is stored as a stop).
+fn stops(tempo_changes: &TempoChanges) -> Vec<Stop> {
+    let end = tempo_changes.last().map(|last| last.start_beats);
+    tempo_changes
+        .stops()
+        .into_iter()
+        .filter(|stop| !(Some(stop.beats) == end && stop.duration_ms == 0.0))
+        .collect()
+}
+
+/// Returns the changes of the tempo and the stops from `a` to `b`.
+pub fn diff_tempo(a: &TempoChanges, b: &TempoChanges) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    let timing_a = TimingMap::new(a);
+    let timing_b = TimingMap::new(b);
+    let positions: BTreeMap<i64, f32> = a
+        .iter()
+        .chain(b.iter())
+        .map(|tempo_change| (tick(tempo_change.start_beats), tempo_change.start_beats))
+        .collect();
+    let mut last = None;
+    for beats in positions.values() {
+        let bpm = match (timing_a.bpm_at(*beats), timing_b.bpm_at(*beats)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => continue,
+        };
+        if (bpm.0 - bpm.1).abs() > BPM_TOLERANCE && Some(bpm) != last {
+            changes.push(Change {
+                level: None,
+                beats: *beats,
+                kind: ChangeKind::TempoChanged {
+                    from: bpm.0,
+                    to: bpm.1,
+                },
+            });
+        }
+        last = Some(bpm);
+    }
+
+    let stops_a: BTreeMap<i64, Stop> = stops(a)
+        .into_iter()
+        .map(|stop| (tick(stop.beats), stop))
+        .collect();
+    let stops_b: BTreeMap<i64, Stop> = stops(b)
+        .into_iter()
+        .map(|stop| (tick(stop.beats), stop))
+        .collect();
+    let ticks: BTreeSet<&i64> = stops_a.keys().chain(stops_b.keys()).collect();
+    for tick in ticks {
+        let kind = match (stops_a.get(tick), stops_b.get(tick)) {
+            (Some(from), Some(to)) => {
+                if (from.duration_ms - to.duration_ms).abs() <= DURATION_TOLERANCE {
+                    continue;
+                }
+                ChangeKind::StopChanged {
+                    from: from.duration_ms,
+                    to: to.duration_ms,
+                }
+            }
+            (Some(from), None) => ChangeKind::StopRemoved(from.duration_ms),
+            (None, Some(to)) => ChangeKind::StopAdded(to.duration_ms),
+            (None, None) => unreachable!(),
+        };
+        let beats = stops_a
+            .get(tick)
+            .or_else(|| stops_b.get(tick))
+            .unwrap()
+            .beats;
+        changes.push(Change {
+            level: None,
+            beats,
+            kind,
+        });
+    }
+
+    changes.sort_by(|a, b| a.beats.partial_cmp(&b.beats).unwrap());
+    changes
+}
+
+/// Returns the length of the freezes of a row (relative to its position).
+fn freeze_lengths(row: &NoteRow) -> Vec<Option<f32>> {
+    row.freeze_ends
+        .iter()
+        .map(|end| end.map(|end| end - row.beats))
+        .collect()
+}
+
+/// Returns the changes of the steps, freezes and shocks from `a` to `b`.
+///
+/// Both charts are expected to have the same style (the style of `a` is used).
+pub fn diff_charts(a: &Chart, b: &Chart) -> Vec<Change> {
+    let style = a.difficulty.style;
+    let level = Some(a.difficulty.clone());
+    let row = |columns: &[bool]| Row::from_columns(columns, style);
+    let mut changes: Vec<(f32, ChangeKind)> = Vec::new();
+
+    let rows_a: BTreeMap<i64, NoteRow> = rows(a)
+        .into_iter()
+        .map(|row| (tick(row.beats), row))
+        .collect();
+    let rows_b: BTreeMap<i64, NoteRow> = rows(b)
+        .into_iter()
+        .map(|row| (tick(row.beats), row))
+        .collect();
+
+    let mut removed: Vec<&NoteRow> = Vec::new();
+    for (tick, row_a) in &rows_a {
+        match rows_b.get(tick) {
+            Some(row_b) if row_a.columns != row_b.columns => changes.push((
+                row_a.beats,
+                ChangeKind::StepChanged {
+                    from: row(&row_a.columns),
+                    to: row(&row_b.columns),
+                },
+            )),
+            Some(_) => {}
+            None => removed.push(row_a),
+        }
+    }
+    let mut added: Vec<Option<&NoteRow>> = rows_b
+        .iter()
+        .filter(|(tick, _)| !rows_a.contains_key(tick))
+        .map(|(_, row)| Some(row))
+        .collect();
+
+    // position of moved rows in `b` by their position in `a`
+    let mut moved: BTreeMap<i64, f32> = BTreeMap::new();
+    for row_a in removed {
+        let target = added
+            .iter_mut()
+            .filter(|candidate| match candidate {
+                Some(row_b) => {
+                    row_b.columns == row_a.columns
+                        && freeze_lengths(row_b) == freeze_lengths(row_a)
+                        && (row_b.beats - row_a.beats).abs() <= MOVE_DISTANCE
+                }
+                None => false,
+            })
+            .min_by(|x, y| {
+                let distance = |row: &Option<&NoteRow>| (row.unwrap().beats - row_a.beats).abs();
+                distance(x).partial_cmp(&distance(y)).unwrap()
+            });
+        match target {
+            Some(target) => {
+                let to = target.unwrap().beats;
+                *target = None;
+                moved.insert(tick(row_a.beats), to);
+                changes.push((
+                    row_a.beats,
+                    ChangeKind::StepMoved {
+                        row: row(&row_a.columns),
+                        to,
+                    },
+                ));
+            }
+            None => changes.push((row_a.beats, ChangeKind::StepRemoved(row(&row_a.columns)))),
+        }
+    }
+    for row_b in added.into_iter().flatten() {
+        changes.push((row_b.beats, ChangeKind::StepAdded(row(&row_b.columns))));
+    }
+
+    // freezes by position (in `b` for moved rows) and column
+    let mut freezes_a: BTreeMap<(i64, usize), (f32, f32)> = BTreeMap::new();
+    for row_a in rows_a.values() {
+        let offset = moved
+            .get(&tick(row_a.beats))
+            .map_or(0.0, |to| to - row_a.beats);
+        for (column, end) in row_a.freeze_ends.iter().enumerate() {
+            if let Some(end) = end {
+                let start = row_a.beats + offset;
+                freezes_a.insert((tick(start), column), (row_a.beats, end + offset));
+            }
+        }
+    }
+    let mut freezes_b: BTreeMap<(i64, usize), (f32, f32)> = BTreeMap::new();
+    for row_b in rows_b.values() {
+        for (column, end) in row_b.freeze_ends.iter().enumerate() {
+            if let Some(end) = end {
+                freezes_b.insert((tick(row_b.beats), column), (row_b.beats, *end));
+            }
+        }
+    }
+    let columns = usize::from(style.columns());
+    let freeze_row = |column: usize| {
+        let mut columns = vec![false; columns];
+        columns[column] = true;
+        Row::from_columns(&columns, style)
+    };
+    let keys: BTreeSet<&(i64, usize)> = freezes_a.keys().chain(freezes_b.keys()).collect();
+    for key in keys {
+        let column = key.1;
+        match (freezes_a.get(key), freezes_b.get(key)) {
+            (Some((start, from_end)), Some((_, to_end))) => {
+                if tick(*from_end) != tick(*to_end) {
+                    changes.push((
+                        *start,
+                        ChangeKind::FreezeChanged {
+                            row: freeze_row(column),
+                            from_end: *from_end,
+                            to_end: *to_end,
+                        },
+                    ));
+                }
+            }
+            (Some((start, end)), None) => changes.push((
+                *start,
+                ChangeKind::FreezeRemoved {
+                    row: freeze_row(column),
+                    end: *end,
+                },
+            )),
+            (None, Some((start, end))) => changes.push((
+                *start,
+                ChangeKind::FreezeAdded {
+                    row: freeze_row(column),
+                    end: *end,
+                },
+            )),
+            (None, None) => unreachable!(),
+        }
+    }
+
+    let shocks_a: BTreeMap<i64, f32> = shocks(a)
+        .into_iter()
+        .map(|beats| (tick(beats), beats))
+        .collect();
+    let shocks_b: BTreeMap<i64, f32> = shocks(b)
+        .into_iter()
+        .map(|beats| (tick(beats), beats))
+        .collect();
+    for (tick, beats) in &shocks_a {
+        if !shocks_b.contains_key(tick) {
+            changes.push((*beats, ChangeKind::ShockRemoved));
+        }
+    }
+    for (tick, beats) in &shocks_b {
+        if !shocks_a.contains_key(tick) {
+            changes.push((*beats, ChangeKind::ShockAdded));
+        }
+    }
+
+    // stable sort, so changes at the same position keep the order above
+    changes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    changes
+        .into_iter()
+        .map(|(beats, kind)| Change {
+            level: level.clone(),
+            beats,
+            kind,
+        })
+        .collect()
+}
+
+/// Returns the number of changes of each kind (by [`ChangeKind::name`]).
+///
+/// [`ChangeKind::name`]: enum.ChangeKind.html#method.name
+pub fn summary(changes: &[Change]) -> BTreeMap<&'static str, usize> {
+    let mut summary = BTreeMap::new();
+    for change in changes {
+        *summary.entry(change.kind.name()).or_insert(0) += 1;
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Step, Style, TempoChange, TempoChangeKind};
+
+    fn step(beats: f32, columns: u8) -> Step {
+        Step::Step {
+            beats,
+            row: Row::new(columns, Style::Single),
+        }
+    }
+
+    fn chart(difficulty: u8, steps: Vec<Step>) -> Chart {
+        Chart {
+            difficulty: Level::new(Style::Single, difficulty).unwrap(),
+            steps,
+        }
+    }
+
+    /// Tempo changes with `bpm` for 16 beats and a stop with `stop` ms at beat 8
+    fn tempo_changes(bpm: f64, stop: f64) -> TempoChanges {
+        let beat_length = 60000.0 / bpm;
+        let mut changes = vec![TempoChange {
+            start_ms: 0.0,
+            start_beats: 0.0,
+            end_beats: 8.0,
+            beat_length,
+            kind: TempoChangeKind::Scroll,
+        }];
+        if stop > 0.0 {
+            changes.push(TempoChange {
+                start_ms: 8.0 * beat_length,
+                start_beats: 8.0,
+                end_beats: 8.0,
+                beat_length: f64::INFINITY,
+                kind: TempoChangeKind::Stop,
+            });
+        }
+        changes.push(TempoChange {
+            start_ms: 8.0 * beat_length + stop,
+            start_beats: 8.0,
+            end_beats: 16.0,
+            beat_length,
+            kind: TempoChangeKind::Scroll,
+        });
+        changes.push(TempoChange {
+            start_ms: 16.0 * beat_length + stop,
+            start_beats: 16.0,
+            end_beats: 16.0,
+            beat_length: f64::INFINITY,
+            kind: TempoChangeKind::Stop,
+        });
+        TempoChanges {
+            ticks_per_second: 150,
+            first_measure: 0,
+            first_tick: 0,
+            changes,
+        }
+    }
+
+    #[test]
+    fn test_format_position() {
+        assert_eq!(format_position(0.0), "m1 b1");
+        assert_eq!(format_position(13.5), "m4 b2.5");
+        assert_eq!(format_position(1.0 / 3.0), "m1 b1.333");
+    }
+
+    #[test]
+    fn test_diff_charts() {
+        let a = chart(
+            3,
+            vec![
+                step(0.0, 0b0001),
+                step(1.0, 0b0010),
+                step(2.0, 0b1000),
+                step(4.0, 0b0100),
+                Step::Freeze {
+                    start: 5.0,
+                    end: 6.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Freeze {
+                    start: 8.0,
+                    end: 9.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Shock { beats: 10.0 },
+            ],
+        );
+        let b = chart(
+            3,
+            vec![
+                step(0.0, 0b0001),
+                step(1.0, 0b0110),
+                step(2.5, 0b1000),
+                step(7.0, 0b0010),
+                Step::Freeze {
+                    start: 5.0,
+                    end: 7.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Freeze {
+                    start: 8.5,
+                    end: 9.5,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Shock { beats: 11.0 },
+            ],
+        );
+        let kinds: Vec<(f32, ChangeKind)> = diff_charts(&a, &b)
+            .into_iter()
+            .map(|change| (change.beats, change.kind))
+            .collect();
+        let single = |columns| Row::new(columns, Style::Single);
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    1.0,
+                    ChangeKind::StepChanged {
+                        from: single(0b0010),
+                        to: single(0b0110)
+                    }
+                ),
+                (
+                    2.0,
+                    ChangeKind::StepMoved {
+                        row: single(0b1000),
+                        to: 2.5
+                    }
+                ),
+                (4.0, ChangeKind::StepRemoved(single(0b0100))),
+                (
+                    5.0,
+                    ChangeKind::FreezeChanged {
+                        row: single(0b0001),
+                        from_end: 6.0,
+                        to_end: 7.0
+                    }
+                ),
+                (7.0, ChangeKind::StepAdded(single(0b0010))),
+                (
+                    8.0,
+                    ChangeKind::StepMoved {
+                        row: single(0b0001),
+                        to: 8.5
+                    }
+                ),
+                (10.0, ChangeKind::ShockRemoved),
+                (11.0, ChangeKind::ShockAdded),
+            ]
+        );
+        assert!(diff_charts(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn test_diff_tempo() {
+        assert!(diff_tempo(&tempo_changes(150.0, 0.0), &tempo_changes(150.0, 0.0)).is_empty());
+
+        let changes = diff_tempo(&tempo_changes(150.0, 0.0), &tempo_changes(160.0, 100.0));
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].beats, 0.0);
+        assert!(matches!(
+            changes[0].kind,
+            ChangeKind::TempoChanged { from, to } if (from - 150.0).abs() < 0.01 && (to - 160.0).abs() < 0.01
+        ));
+        assert_eq!(changes[1].beats, 8.0);
+        assert!(
+            matches!(changes[1].kind, ChangeKind::StopAdded(duration) if (duration - 100.0).abs() < 0.01)
+        );
+
+        let changes = diff_tempo(&tempo_changes(150.0, 100.0), &tempo_changes(150.0, 200.0));
+        assert_eq!(changes.len(), 1);
+        assert!(matches!(changes[0].kind, ChangeKind::StopChanged { .. }));
+    }
+
+    fn ssq(charts: Vec<Chart>) -> SSQ {
+        SSQ {
+            tempo_changes: tempo_changes(150.0, 0.0),
+            charts,
+            background_changes: Vec::new(),
+            light_cues: Vec::new(),
+            unknown_chunks: Vec::new(),
+            chunk_order: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = ssq(vec![
+            chart(1, vec![step(0.0, 0b0001)]),
+            chart(2, Vec::new()),
+        ]);
+        let b = ssq(vec![
+            chart(1, vec![step(0.0, 0b0001)]),
+            chart(3, Vec::new()),
+        ]);
+        let changes = diff(&a, &b);
+        assert_eq!(
+            changes
+                .iter()
+                .map(|change| (change.level.clone().unwrap(), change.kind.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    Level::new(Style::Single, 2).unwrap(),
+                    ChangeKind::ChartRemoved
+                ),
+                (
+                    Level::new(Style::Single, 3).unwrap(),
+                    ChangeKind::ChartAdded
+                ),
+            ]
+        );
+        assert_eq!(summary(&changes)["chart_added"], 1);
+    }
+
+    #[test]
+    fn test_change_output() {
+        let change = Change {
+            level: Some(Level::new(Style::Single, 3).unwrap()),
+            beats: 5.0,
+            kind: ChangeKind::StepChanged {
+                from: Row::new(0b0010, Style::Single),
+                to: Row::new(0b0110, Style::Single),
+            },
+        };
+        assert_eq!(change.to_string(), "m2 b2 changed [ ↓  ] → [ ↓↑ ]");
+        assert_eq!(
+            change.record(),
+            [
+                "Single Expert".to_string(),
+                "5".to_string(),
+                "step_changed".to_string(),
+                "0100".to_string(),
+                "0110".to_string()
+            ]
+        );
+    }
+}
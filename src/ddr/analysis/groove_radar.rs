@@ -0,0 +1,310 @@
+//! Groove Radar values of a chart.
+//!
+//! The formulas follow the community formulas for DDR X and later mixes, the results have not
+//! been compared with the game’s values. Each value is computed from a base value and mapped
+//! piecewise linearly, so that the reference value results in 100. Values above 100 are possible.
+//!
+//! The base value of Chaos is not fully known, so it is approximated from the snap of the steps
+//! and the tempo changes.
+use std::fmt;
+
+use super::quantization::Snap;
+use super::stats::ChartStats;
+use super::{rows, NoteRow};
+use crate::ddr::ssq::{Chart, TempoChangeKind, TempoChanges, TimingMap};
+
+/// Number of beats of the window used for the peak density of Voltage.
+const VOLTAGE_WINDOW: f32 = 4.0;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GrooveRadar {
+    /// Average density of steps
+    pub stream: f32,
+    /// Peak density of steps
+    pub voltage: f32,
+    /// Jumps and shocks
+    pub air: f32,
+    /// Share of the chart where freezes are held
+    pub freeze: f32,
+    /// Irregularity of the rhythm and tempo
+    pub chaos: f32,
+}
+
+impl GrooveRadar {
+    pub fn new(chart: &Chart, tempo_changes: &TempoChanges) -> Self {
+        let stats = ChartStats::new(chart, tempo_changes);
+        let rows = rows(chart);
+
+        let length = stats.length / 1000.0;
+        if length <= 0.0 || rows.is_empty() {
+            return Self::default();
+        }
+        let length_minutes = length / 60.0;
+        let total_beats = tempo_changes
+            .last()
+            .map(|change| change.end_beats)
+            .unwrap_or(0.0)
+            .max(rows.last().unwrap().beats);
+        let average_bpm = f64::from(total_beats) / length_minutes;
+
+        let notes_per_minute = f64::from(stats.steps) / length_minutes;
+        let stream = piecewise(notes_per_minute, 300.0, 3.0, 139.0, 161.0);
+
+        let peak_density = f64::from(peak_notes(&rows, VOLTAGE_WINDOW)) * average_bpm / 4.0;
+        let voltage = piecewise(peak_density, 600.0, 6.0, -594.0, 1194.0);
+
+        let air_per_minute = f64::from(stats.jumps + stats.shocks) / length_minutes;
+        let air = piecewise(air_per_minute, 55.0, 0.55, -36.0, 91.0);
+
+        let freeze_rate = if total_beats > 0.0 {
+            f64::from(frozen_beats(&rows) / total_beats) * 10000.0
+        } else {
+            0.0
+        };
+        let freeze = piecewise(freeze_rate, 3500.0, 35.0, -2484.0, 5984.0);
+
+        let chaos_degree =
+            (irregularity(&rows) * 1000.0 + tempo_variation(tempo_changes) * 100.0) / length;
+        let chaos = piecewise(chaos_degree, 2000.0, 20.0, -21605.0, 23605.0);
+
+        Self {
+            stream: stream as f32,
+            voltage: voltage as f32,
+            air: air as f32,
+            freeze: freeze as f32,
+            chaos: chaos as f32,
+        }
+    }
+}
+
+impl fmt::Display for GrooveRadar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Stream {:.0}, Voltage {:.0}, Air {:.0}, Freeze {:.0}, Chaos {:.0}",
+            self.stream, self.voltage, self.air, self.freeze, self.chaos
+        )
+    }
+}
+
+/// Maps `value` to `value / divisor` up to `threshold` and to
+/// `(value - offset) * 100 / range` above it.
+fn piecewise(value: f64, threshold: f64, divisor: f64, offset: f64, range: f64) -> f64 {
+    if value <= threshold {
+        value / divisor
+    } else {
+        (value - offset) * 100.0 / range
+    }
+}
+
+/// Returns the highest number of rows in any window of `window` beats.
+fn peak_notes(rows: &[NoteRow], window: f32) -> u32 {
+    let mut peak = 0;
+    let mut start = 0;
+    for (end, row) in rows.iter().enumerate() {
+        while row.beats - rows[start].beats >= window {
+            start += 1;
+        }
+        peak = peak.max(end - start + 1);
+    }
+    peak as u32
+}
+
+/// Returns the number of beats in which at least one freeze is held.
+fn frozen_beats(rows: &[NoteRow]) -> f32 {
+    let mut frozen = 0.0;
+    let mut held_until = f32::NEG_INFINITY;
+
+    // rows are ordered by their start, so overlapping freezes can be merged on the fly
+    for row in rows {
+        if let Some(end) = row.last_freeze_end() {
+            let start = row.beats.max(held_until);
+            if end > start {
+                frozen += end - start;
+                held_until = end;
+            }
+        }
+    }
+
+    frozen
+}
+
+/// Returns the sum of the active columns of all rows weighted by how fine their snap is.
+fn irregularity(rows: &[NoteRow]) -> f64 {
+    rows.iter()
+        .map(|row| row.count_active() as f64 * snap_weight(row.beats))
+        .sum()
+}
+
+/// Weight of the snap of a step at `beats`: quarter notes are regular, finer snaps are
+/// increasingly irregular.
+fn snap_weight(beats: f32) -> f64 {
+    match Snap::of(beats) {
+        Snap::Fourth => 0.0,
+        Snap::Eighth => 0.5,
+        Snap::Twelfth | Snap::Sixteenth => 1.0,
+        Snap::TwentyFourth | Snap::ThirtySecond => 2.0,
+        Snap::SixtyFourth | Snap::HundredNinetySecond => 4.0,
+    }
+}
+
+/// Returns the sum of all changes of the tempo (in BPM); stops count as changes to 0 BPM and
+/// back.
+///
+/// The last tempo change only marks the end of the chart and is ignored.
+fn tempo_variation(tempo_changes: &TempoChanges) -> f64 {
+    let timing_map = TimingMap::new(tempo_changes);
+    let mut variation = 0.0;
+    let mut last_bpm: Option<f64> = None;
+
+    for change in tempo_changes
+        .iter()
+        .take(tempo_changes.len().saturating_sub(1))
+    {
+        match change.kind {
+            TempoChangeKind::Scroll => {
+                if let Some(bpm) = timing_map.bpm_at(change.start_beats) {
+                    let bpm = f64::from(bpm);
+                    if let Some(last_bpm) = last_bpm {
+                        variation += (bpm - last_bpm).abs();
+                    }
+                    last_bpm = Some(bpm);
+                }
+            }
+            TempoChangeKind::Stop | TempoChangeKind::Delay => {
+                if let Some(last_bpm) = last_bpm {
+                    variation += last_bpm * 2.0;
+                }
+            }
+        }
+    }
+
+    variation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Level, Row, Step, Style, TempoChange};
+
+    /// 120 BPM for 120 beats (one minute)
+    fn tempo_changes() -> TempoChanges {
+        TempoChanges {
+            ticks_per_second: 150,
+            first_measure: 0,
+            first_tick: 0,
+            changes: vec![
+                TempoChange {
+                    start_ms: 0.0,
+                    start_beats: 0.0,
+                    end_beats: 120.0,
+                    beat_length: 500.0,
+                    kind: TempoChangeKind::Scroll,
+                },
+                TempoChange {
+                    start_ms: 60000.0,
+                    start_beats: 120.0,
+                    end_beats: 120.0,
+                    beat_length: f64::INFINITY,
+                    kind: TempoChangeKind::Stop,
+                },
+            ],
+        }
+    }
+
+    fn chart(steps: Vec<Step>) -> Chart {
+        Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps,
+        }
+    }
+
+    #[test]
+    fn test_piecewise() {
+        assert_eq!(piecewise(300.0, 300.0, 3.0, 139.0, 161.0), 100.0);
+        assert_eq!(piecewise(150.0, 300.0, 3.0, 139.0, 161.0), 50.0);
+        assert!((piecewise(300.1, 300.0, 3.0, 139.0, 161.0) - 100.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_stream_and_voltage() {
+        // one step on every beat
+        let steps = (0..120)
+            .map(|beat| Step::Step {
+                beats: beat as f32,
+                row: Row::new(0b0001, Style::Single),
+            })
+            .collect();
+        let radar = GrooveRadar::new(&chart(steps), &tempo_changes());
+        assert_eq!(radar.stream, 40.0);
+        // 4 steps per measure at 120 BPM
+        assert_eq!(radar.voltage, 20.0);
+        assert_eq!(radar.air, 0.0);
+        assert_eq!(radar.freeze, 0.0);
+        assert_eq!(radar.chaos, 0.0);
+    }
+
+    #[test]
+    fn test_air_and_freeze() {
+        let steps = vec![
+            Step::Step {
+                beats: 0.0,
+                row: Row::new(0b1001, Style::Single),
+            },
+            Step::Freeze {
+                start: 4.0,
+                end: 16.0,
+                row: Row::new(0b0010, Style::Single),
+            },
+            Step::Freeze {
+                start: 12.0,
+                end: 20.0,
+                row: Row::new(0b0100, Style::Single),
+            },
+            Step::Shock { beats: 100.0 },
+        ];
+        let radar = GrooveRadar::new(&chart(steps), &tempo_changes());
+        // 2 air per minute
+        assert!((radar.air - 2.0 / 0.55).abs() < 0.001);
+        // 16 of 120 beats are frozen
+        assert!((radar.freeze - 16.0 / 120.0 * 10000.0 / 35.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_frozen_beats() {
+        let rows = vec![
+            NoteRow {
+                beats: 0.0,
+                columns: vec![true, true, false, false],
+                freeze_ends: vec![Some(2.0), Some(4.0), None, None],
+            },
+            NoteRow {
+                beats: 3.0,
+                columns: vec![false, false, true, false],
+                freeze_ends: vec![None, None, Some(5.0), None],
+            },
+            NoteRow {
+                beats: 8.0,
+                columns: vec![false, false, false, true],
+                freeze_ends: vec![None, None, None, Some(9.0)],
+            },
+        ];
+        assert_eq!(frozen_beats(&rows), 6.0);
+    }
+
+    #[test]
+    fn test_snap_weight() {
+        assert_eq!(snap_weight(3.0), 0.0);
+        assert_eq!(snap_weight(3.5), 0.5);
+        assert_eq!(snap_weight(3.25), 1.0);
+        assert_eq!(snap_weight(1.0 / 3.0), 1.0);
+        assert_eq!(snap_weight(3.125), 2.0);
+        assert_eq!(snap_weight(3.0625), 4.0);
+    }
+
+    #[test]
+    fn test_empty() {
+        let radar = GrooveRadar::new(&chart(Vec::new()), &TempoChanges::default());
+        assert_eq!(radar, GrooveRadar::default());
+    }
+}
@@ -0,0 +1,535 @@
+//! Assignment of feet to the steps of a chart (step parity).
+//!
+//! The solver does a dynamic programming search over the positions of both feet. Every row is
+//! stepped by assigning each of its arrows to one foot, which costs according to how far the
+//! feet move and which techniques (crossovers, footswitches, jacks, doublesteps, brackets) the
+//! assignment requires. The sequence with the lowest total cost is returned.
+//!
+//! A foot holding a freeze can not step on other arrows until the freeze ends.
+use super::{rows, NoteRow};
+use crate::ddr::ssq::Chart;
+
+/// Cost per panel distance a foot moves
+const MOVEMENT: f32 = 1.0;
+/// Cost of hitting two arrows with one foot
+const BRACKET: f32 = 2.0;
+/// Cost of stepping on a panel the other foot is on
+const FOOTSWITCH: f32 = 3.0;
+/// Cost of hitting the same arrow with the same foot twice
+const JACK: f32 = 0.5;
+/// Cost of stepping with the same foot twice on different arrows (for steps one beat apart)
+const DOUBLESTEP: f32 = 20.0;
+/// Cost of facing backwards (left foot right of the right foot)
+const CROSSOVER: f32 = 4.0;
+/// Cost of facing sideways (both feet in the same column)
+const FACING: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Foot {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Technique {
+    /// The left foot moves right of the right foot (or vice versa)
+    Crossover,
+    /// A foot steps on the panel the other foot was on
+    Footswitch,
+    /// The same foot hits the same arrow again
+    Jack,
+    /// One foot hits two arrows
+    Bracket,
+    /// The same foot hits two different arrows in succession
+    Doublestep,
+}
+
+/// Row of a chart with the feet that hit its arrows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FootedRow {
+    pub beats: f32,
+    /// Foot for every active column (`None` for inactive columns)
+    pub feet: Vec<Option<Foot>>,
+    /// Techniques needed to step the row with these feet
+    pub techniques: Vec<Technique>,
+}
+
+/// Returns how often `technique` is used in `rows`.
+pub fn count(rows: &[FootedRow], technique: Technique) -> usize {
+    rows.iter()
+        .filter(|row| row.techniques.contains(&technique))
+        .count()
+}
+
+/// Returns the feet for all rows (as returned by [`rows`]) of a chart.
+///
+/// [`rows`]: ../fn.rows.html
+pub fn solve(chart: &Chart) -> Vec<FootedRow> {
+    solve_rows(&rows(chart))
+}
+
+/// Position of a panel on the pad(s) as (x, y) with y pointing upwards.
+fn panel_position(columns: usize, column: usize) -> (f32, f32) {
+    match columns {
+        6 => [
+            (0.0, 1.0),
+            (0.0, 2.0),
+            (1.0, 0.0),
+            (1.0, 2.0),
+            (2.0, 2.0),
+            (2.0, 1.0),
+        ][column],
+        _ => {
+            let (x, y) = [(0.0, 1.0), (1.0, 0.0), (1.0, 2.0), (2.0, 1.0)][column % 4];
+            // second pad is right of the first one
+            (x + 3.0 * (column / 4) as f32, y)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct State {
+    /// Panels (as bit mask of columns) the left foot is on
+    left: u8,
+    /// Panels (as bit mask of columns) the right foot is on
+    right: u8,
+    left_hold: Option<f32>,
+    right_hold: Option<f32>,
+    /// Foot that stepped the previous row (if it was stepped with only one foot)
+    last: Option<Foot>,
+    cost: f32,
+    parent: usize,
+    feet: Vec<Option<Foot>>,
+    techniques: Vec<Technique>,
+}
+
+impl State {
+    fn same_position(&self, other: &Self) -> bool {
+        self.left == other.left
+            && self.right == other.right
+            && self.left_hold == other.left_hold
+            && self.right_hold == other.right_hold
+            && self.last == other.last
+    }
+}
+
+struct Solver {
+    positions: Vec<(f32, f32)>,
+}
+
+impl Solver {
+    /// Returns the position of a foot that is on the panels in `mask` (the center of brackets).
+    fn center(&self, mask: u8) -> (f32, f32) {
+        let panels: Vec<&(f32, f32)> = self
+            .positions
+            .iter()
+            .enumerate()
+            .filter(|(column, _)| mask & (1 << column) != 0)
+            .map(|(_, position)| position)
+            .collect();
+        let count = panels.len().max(1) as f32;
+        (
+            panels.iter().map(|(x, _)| x).sum::<f32>() / count,
+            panels.iter().map(|(_, y)| y).sum::<f32>() / count,
+        )
+    }
+
+    fn distance(&self, a: u8, b: u8) -> f32 {
+        let (a, b) = (self.center(a), self.center(b));
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+    }
+
+    /// Two arrows can be hit with one foot if their panels are diagonally adjacent.
+    fn is_bracketable(&self, mask: u8) -> bool {
+        match mask.count_ones() {
+            0 | 1 => true,
+            2 => {
+                let columns: Vec<usize> = (0..self.positions.len())
+                    .filter(|column| mask & (1 << column) != 0)
+                    .collect();
+                let (a, b) = (self.positions[columns[0]], self.positions[columns[1]]);
+                (a.0 - b.0).abs() <= 1.0 && (a.1 - b.1).abs() <= 1.0
+            }
+            _ => false,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn transition(
+        &self,
+        state: &State,
+        parent: usize,
+        row: &NoteRow,
+        left_mask: u8,
+        right_mask: u8,
+        interval: f32,
+        ignore_holds: bool,
+    ) -> Option<State> {
+        let holding =
+            |hold: Option<f32>| !ignore_holds && matches!(hold, Some(end) if end > row.beats);
+
+        for (mask, hold) in [(left_mask, state.left_hold), (right_mask, state.right_hold)].iter() {
+            if *mask != 0 && holding(*hold) {
+                return None;
+            }
+            if !self.is_bracketable(*mask) {
+                return None;
+            }
+        }
+
+        let left = if left_mask != 0 {
+            left_mask
+        } else {
+            state.left
+        };
+        let right = if right_mask != 0 {
+            right_mask
+        } else {
+            state.right
+        };
+        if left & right != 0 {
+            return None;
+        }
+
+        let mut cost = state.cost;
+        let mut techniques = Vec::new();
+
+        cost += MOVEMENT * (self.distance(state.left, left) + self.distance(state.right, right));
+
+        if left_mask.count_ones() == 2 || right_mask.count_ones() == 2 {
+            cost += BRACKET;
+            techniques.push(Technique::Bracket);
+        }
+
+        if left_mask & state.right != 0 || right_mask & state.left != 0 {
+            cost += FOOTSWITCH;
+            techniques.push(Technique::Footswitch);
+        }
+
+        let used = match (left_mask != 0, right_mask != 0) {
+            (true, false) => Some(Foot::Left),
+            (false, true) => Some(Foot::Right),
+            _ => None,
+        };
+        if let (Some(foot), Some(last)) = (used, state.last) {
+            if foot == last {
+                let (before, after, other_hold) = match foot {
+                    Foot::Left => (state.left, left, state.right_hold),
+                    Foot::Right => (state.right, right, state.left_hold),
+                };
+                if before == after {
+                    cost += JACK;
+                    techniques.push(Technique::Jack);
+                } else if !holding(other_hold) {
+                    // doublesteps are only necessary if the other foot is holding a freeze
+                    cost += DOUBLESTEP / interval.max(0.25);
+                    techniques.push(Technique::Doublestep);
+                }
+            }
+        }
+
+        let (left_x, _) = self.center(left);
+        let (right_x, _) = self.center(right);
+        if left_x > right_x {
+            cost += CROSSOVER;
+            // only the row that crosses the feet is counted
+            if self.center(state.left).0 <= self.center(state.right).0 {
+                techniques.push(Technique::Crossover);
+            }
+        } else if (left_x - right_x).abs() < f32::EPSILON {
+            cost += FACING;
+        }
+
+        let hold_end = |mask: u8, previous: Option<f32>| {
+            if mask == 0 {
+                previous.filter(|_| holding(previous))
+            } else {
+                row.freeze_ends
+                    .iter()
+                    .enumerate()
+                    .filter(|(column, _)| mask & (1 << column) != 0)
+                    .filter_map(|(_, end)| *end)
+                    .fold(None, |acc: Option<f32>, end| {
+                        Some(acc.map_or(end, |acc| acc.max(end)))
+                    })
+            }
+        };
+
+        let feet = (0..row.columns.len())
+            .map(|column| {
+                if left_mask & (1 << column) != 0 {
+                    Some(Foot::Left)
+                } else if right_mask & (1 << column) != 0 {
+                    Some(Foot::Right)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        Some(State {
+            left,
+            right,
+            left_hold: hold_end(left_mask, state.left_hold),
+            right_hold: hold_end(right_mask, state.right_hold),
+            last: used,
+            cost,
+            parent,
+            feet,
+            techniques,
+        })
+    }
+
+    fn step(
+        &self,
+        states: &[State],
+        row: &NoteRow,
+        interval: f32,
+        ignore_holds: bool,
+    ) -> Vec<State> {
+        let active: Vec<usize> = row
+            .columns
+            .iter()
+            .enumerate()
+            .filter(|(_, active)| **active)
+            .map(|(column, _)| column)
+            .collect();
+
+        let mut next: Vec<State> = Vec::new();
+
+        for (parent, state) in states.iter().enumerate() {
+            // every bit of the assignment selects the foot of one arrow (set bits: right foot)
+            for assignment in 0..(1u32 << active.len()) {
+                let mut left_mask = 0;
+                let mut right_mask = 0;
+                for (i, column) in active.iter().enumerate() {
+                    if assignment & (1 << i) != 0 {
+                        right_mask |= 1 << column;
+                    } else {
+                        left_mask |= 1 << column;
+                    }
+                }
+
+                if let Some(candidate) = self.transition(
+                    state,
+                    parent,
+                    row,
+                    left_mask,
+                    right_mask,
+                    interval,
+                    ignore_holds,
+                ) {
+                    match next
+                        .iter_mut()
+                        .find(|state| state.same_position(&candidate))
+                    {
+                        Some(existing) => {
+                            if candidate.cost < existing.cost {
+                                *existing = candidate;
+                            }
+                        }
+                        None => next.push(candidate),
+                    }
+                }
+            }
+        }
+
+        next
+    }
+}
+
+fn solve_rows(rows: &[NoteRow]) -> Vec<FootedRow> {
+    let columns = match rows.first() {
+        Some(row) => row.columns.len(),
+        None => return Vec::new(),
+    };
+    let solver = Solver {
+        positions: (0..columns)
+            .map(|column| panel_position(columns, column))
+            .collect(),
+    };
+
+    // feet start on the outer panels (or on the inner panels of both pads for double)
+    let (home_left, home_right) = match columns {
+        6 => (0, 5),
+        8 => (3, 4),
+        _ => (0, 3),
+    };
+    let mut layers = vec![vec![State {
+        left: 1 << home_left,
+        right: 1 << home_right,
+        left_hold: None,
+        right_hold: None,
+        last: None,
+        cost: 0.0,
+        parent: 0,
+        feet: Vec::new(),
+        techniques: Vec::new(),
+    }]];
+
+    let mut last_beats: Option<f32> = None;
+    for row in rows {
+        let interval = last_beats.map_or(f32::INFINITY, |last| row.beats - last);
+        let states = layers.last().unwrap();
+        let mut next = solver.step(states, row, interval, false);
+        if next.is_empty() {
+            // both feet are holding freezes or the row has too many arrows to be stepped
+            next = solver.step(states, row, interval, true);
+        }
+        if next.is_empty() {
+            // fall back to not moving at all, so the search can continue
+            next = states
+                .iter()
+                .enumerate()
+                .map(|(parent, state)| State {
+                    parent,
+                    feet: vec![None; columns],
+                    techniques: Vec::new(),
+                    ..state.clone()
+                })
+                .collect();
+        }
+        layers.push(next);
+        last_beats = Some(row.beats);
+    }
+
+    // follow the cheapest path back to the start
+    let mut index = layers
+        .last()
+        .unwrap()
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.cost.partial_cmp(&b.cost).unwrap())
+        .map(|(index, _)| index)
+        .unwrap();
+    let mut footed_rows = Vec::new();
+    for (layer, row) in layers[1..].iter().zip(rows.iter()).rev() {
+        let state = &layer[index];
+        footed_rows.push(FootedRow {
+            beats: row.beats,
+            feet: state.feet.clone(),
+            techniques: state.techniques.clone(),
+        });
+        index = state.parent;
+    }
+    footed_rows.reverse();
+
+    footed_rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Row, Style};
+
+    const L: Option<Foot> = Some(Foot::Left);
+    const R: Option<Foot> = Some(Foot::Right);
+
+    fn row(beats: f32, columns: u8, style: Style) -> NoteRow {
+        let columns: Vec<bool> = Row::new(columns, style).into();
+        NoteRow {
+            beats,
+            freeze_ends: vec![None; columns.len()],
+            columns,
+        }
+    }
+
+    fn single(rows: &[u8]) -> Vec<NoteRow> {
+        rows.iter()
+            .enumerate()
+            .map(|(i, columns)| row(i as f32, *columns, Style::Single))
+            .collect()
+    }
+
+    fn feet(rows: &[FootedRow]) -> Vec<Vec<Option<Foot>>> {
+        rows.iter().map(|row| row.feet.clone()).collect()
+    }
+
+    #[test]
+    fn test_alternating() {
+        let footed = solve_rows(&single(&[0b0001, 0b1000, 0b0001, 0b1000]));
+        assert_eq!(
+            feet(&footed),
+            vec![
+                vec![L, None, None, None],
+                vec![None, None, None, R],
+                vec![L, None, None, None],
+                vec![None, None, None, R],
+            ]
+        );
+        assert!(footed.iter().all(|row| row.techniques.is_empty()));
+    }
+
+    #[test]
+    fn test_crossover() {
+        // ← ↓ → ↑ ←
+        let footed = solve_rows(&single(&[0b0001, 0b0010, 0b1000, 0b0100, 0b0001]));
+        assert_eq!(
+            feet(&footed),
+            vec![
+                vec![L, None, None, None],
+                vec![None, R, None, None],
+                vec![None, None, None, L],
+                vec![None, None, R, None],
+                vec![L, None, None, None],
+            ]
+        );
+        assert_eq!(count(&footed, Technique::Crossover), 1);
+        assert_eq!(count(&footed, Technique::Doublestep), 0);
+    }
+
+    #[test]
+    fn test_jump_and_jack() {
+        let footed = solve_rows(&single(&[0b1001, 0b0001, 0b0001, 0b1000]));
+        assert_eq!(footed[0].feet, vec![L, None, None, R]);
+        assert_eq!(count(&footed, Technique::Jack), 1);
+    }
+
+    #[test]
+    fn test_freeze() {
+        // left foot holds ← while the right foot steps ↓ →
+        let mut rows = single(&[0b0001, 0b0010, 0b1000]);
+        rows[0].freeze_ends[0] = Some(4.0);
+        let footed = solve_rows(&rows);
+        assert_eq!(
+            feet(&footed),
+            vec![
+                vec![L, None, None, None],
+                vec![None, R, None, None],
+                vec![None, None, None, R],
+            ]
+        );
+        assert_eq!(count(&footed, Technique::Doublestep), 0);
+    }
+
+    #[test]
+    fn test_bracket() {
+        let footed = solve_rows(&single(&[0b0111]));
+        assert_eq!(
+            footed[0].feet.iter().filter(|foot| foot.is_some()).count(),
+            3
+        );
+        assert_eq!(footed[0].techniques, vec![Technique::Bracket]);
+    }
+
+    #[test]
+    fn test_double() {
+        let rows = vec![
+            row(0.0, 0b00001000, Style::Double),
+            row(1.0, 0b00010000, Style::Double),
+        ];
+        let footed = solve_rows(&rows);
+        assert_eq!(
+            feet(&footed),
+            vec![
+                vec![None, None, None, L, None, None, None, None],
+                vec![None, None, None, None, R, None, None, None],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(solve_rows(&[]), Vec::new());
+    }
+}
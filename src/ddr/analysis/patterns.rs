@@ -0,0 +1,455 @@
+//! Detection of common DDR step patterns.
+//!
+//! Rhythm based patterns (streams, gallops, jacks, drills, spins) are detected from the rows of a
+//! chart, patterns that depend on which foot steps an arrow (candles, crossovers) from the result
+//! of the [`parity`] solver.
+//!
+//! [`parity`]: ../parity/index.html
+use std::fmt;
+
+use super::parity::{self, Foot, FootedRow, Technique};
+use super::{rows, NoteRow};
+use crate::ddr::ssq::{Chart, TempoChanges, TimingMap};
+
+/// Tolerance (in beats) when comparing intervals between rows
+const TOLERANCE: f32 = 0.01;
+/// Longest interval (in beats) between two hits of a jack
+const JACK_INTERVAL: f32 = 0.5;
+const MIN_JACK_ROWS: usize = 3;
+/// Longest interval (in beats) between two steps of a drill
+const DRILL_INTERVAL: f32 = 0.5;
+const MIN_DRILL_ROWS: usize = 5;
+/// Longest interval (in beats) between the two steps of a gallop
+const GALLOP_INTERVAL: f32 = 0.25;
+const MIN_SPIN_ROWS: usize = 4;
+/// Longest interval (in beats) between two rows of a stream
+const STREAM_INTERVAL: f32 = 0.5;
+const MIN_STREAM_ROWS: usize = 16;
+const MIN_JUMP_STREAM_ROWS: usize = 8;
+/// Share of jumps needed for a jump stream
+const JUMP_STREAM_SHARE: f32 = 0.25;
+/// Share of rows a pattern has to cover to be used as tag
+const TAG_SHARE: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PatternKind {
+    /// The same arrow (or jump) hit repeatedly
+    Jack,
+    /// Two arrows alternating
+    Drill,
+    /// A foot moving from up to down (or down to up) in one step
+    Candle,
+    /// Two quick steps followed by a longer pause
+    Gallop,
+    /// Arrows going around the pad
+    Spin,
+    /// Left foot right of the right foot (or vice versa)
+    Crossover,
+    /// Long run of eighth notes or faster
+    Stream,
+    /// Run of eighth notes or faster with many jumps
+    JumpStream,
+}
+
+impl fmt::Display for PatternKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Jack => "jack",
+            Self::Drill => "drill",
+            Self::Candle => "candle",
+            Self::Gallop => "gallop",
+            Self::Spin => "spin",
+            Self::Crossover => "crossover",
+            Self::Stream => "stream",
+            Self::JumpStream => "jump stream",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Occurrence of a pattern in a chart.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub start_beats: f32,
+    pub end_beats: f32,
+    /// Time of the first row (in milliseconds)
+    pub start: Option<f64>,
+    /// Time of the last row (in milliseconds)
+    pub end: Option<f64>,
+    /// Number of rows that are part of the pattern
+    pub rows: usize,
+}
+
+/// Returns all patterns found in a chart, ordered by their start.
+pub fn detect(chart: &Chart, tempo_changes: &TempoChanges) -> Vec<Pattern> {
+    let rows = rows(chart);
+    let footed_rows = parity::solve(chart);
+    let timing_map = TimingMap::new(tempo_changes);
+
+    let mut patterns: Vec<Pattern> = find(&rows, &footed_rows)
+        .into_iter()
+        .map(|(kind, start, end)| Pattern {
+            kind,
+            start_beats: rows[start].beats,
+            end_beats: rows[end].beats,
+            start: timing_map.time_at(rows[start].beats),
+            end: timing_map.time_at(rows[end].beats),
+            rows: end - start + 1,
+        })
+        .collect();
+    patterns.sort_by(|a, b| {
+        a.start_beats
+            .partial_cmp(&b.start_beats)
+            .unwrap()
+            .then(a.kind.cmp(&b.kind))
+    });
+
+    patterns
+}
+
+/// Returns the number of occurrences of `kind`.
+pub fn count(patterns: &[Pattern], kind: PatternKind) -> usize {
+    patterns
+        .iter()
+        .filter(|pattern| pattern.kind == kind)
+        .count()
+}
+
+/// Returns tags like “crossover heavy” for the kinds of patterns that make up a large part of a
+/// chart with `steps` rows.
+pub fn tags(patterns: &[Pattern], steps: usize) -> Vec<String> {
+    if steps == 0 {
+        return Vec::new();
+    }
+
+    let mut kinds: Vec<PatternKind> = patterns.iter().map(|pattern| pattern.kind).collect();
+    kinds.sort();
+    kinds.dedup();
+
+    kinds
+        .into_iter()
+        .filter(|kind| {
+            let rows: usize = patterns
+                .iter()
+                .filter(|pattern| pattern.kind == *kind)
+                .map(|pattern| pattern.rows)
+                .sum();
+            rows as f32 / steps as f32 >= TAG_SHARE
+        })
+        .map(|kind| format!("{} heavy", kind))
+        .collect()
+}
+
+/// Returns the patterns as kind and index of the first and last row.
+fn find(rows: &[NoteRow], footed_rows: &[FootedRow]) -> Vec<(PatternKind, usize, usize)> {
+    let interval = |i: usize| rows[i].beats - rows[i - 1].beats;
+    let single = |i: usize| single_column(&rows[i]);
+
+    let mut patterns = Vec::new();
+    let mut add_runs = |kind, runs: Vec<(usize, usize)>| {
+        patterns.extend(runs.into_iter().map(|(start, end)| (kind, start, end)))
+    };
+
+    add_runs(
+        PatternKind::Jack,
+        runs(rows.len(), MIN_JACK_ROWS, |_, i| {
+            rows[i].columns == rows[i - 1].columns && interval(i) <= JACK_INTERVAL + TOLERANCE
+        }),
+    );
+
+    add_runs(
+        PatternKind::Drill,
+        runs(rows.len(), MIN_DRILL_ROWS, |start, i| {
+            single(i).is_some()
+                && single(i - 1).is_some()
+                && single(i) != single(i - 1)
+                && interval(i) <= DRILL_INTERVAL + TOLERANCE
+                && (i < start + 2
+                    || (single(i) == single(i - 2)
+                        && (interval(i) - interval(i - 1)).abs() <= TOLERANCE))
+        }),
+    );
+
+    add_runs(
+        PatternKind::Spin,
+        runs(rows.len(), MIN_SPIN_ROWS, |start, i| {
+            let direction = |i: usize| ring_direction(&rows[i - 1], &rows[i]);
+            direction(i).is_some() && (i == start + 1 || direction(i) == direction(start + 1))
+        }),
+    );
+
+    let stream_runs = |min_rows| {
+        runs(rows.len(), min_rows, |_, i| {
+            interval(i) <= STREAM_INTERVAL + TOLERANCE
+        })
+    };
+    add_runs(PatternKind::Stream, stream_runs(MIN_STREAM_ROWS));
+    add_runs(
+        PatternKind::JumpStream,
+        stream_runs(MIN_JUMP_STREAM_ROWS)
+            .into_iter()
+            .filter(|(start, end)| {
+                let jumps = rows[*start..=*end]
+                    .iter()
+                    .filter(|row| row.is_jump())
+                    .count();
+                jumps as f32 / (end - start + 1) as f32 >= JUMP_STREAM_SHARE
+            })
+            .collect(),
+    );
+
+    // gallops are two quick steps with longer pauses before and after them
+    let mut gallops: Vec<(usize, usize)> = Vec::new();
+    for i in 1..rows.len() {
+        let short = interval(i);
+        let before = if i >= 2 {
+            interval(i - 1)
+        } else {
+            f32::INFINITY
+        };
+        let after = if i + 1 < rows.len() {
+            interval(i + 1)
+        } else {
+            f32::INFINITY
+        };
+        if short <= GALLOP_INTERVAL + TOLERANCE && before >= short * 2.0 && after >= short * 2.0 {
+            match gallops.last_mut() {
+                // gallops directly following each other form one run
+                Some((_, end)) if *end + 1 == i - 1 => *end = i,
+                _ => gallops.push((i - 1, i)),
+            }
+        }
+    }
+    add_runs(PatternKind::Gallop, gallops);
+
+    // candles: a foot moves directly between the up and down panel
+    let mut last_columns: [Option<(usize, usize)>; 2] = [None, None];
+    for (i, footed_row) in footed_rows.iter().enumerate() {
+        for (column, foot) in footed_row.feet.iter().enumerate() {
+            if let Some(foot) = foot {
+                let index = match foot {
+                    Foot::Left => 0,
+                    Foot::Right => 1,
+                };
+                if let Some((last_row, last_column)) = last_columns[index] {
+                    if is_candle(footed_row.feet.len(), last_column, column) {
+                        patterns.push((PatternKind::Candle, last_row, i));
+                    }
+                }
+                last_columns[index] = Some((i, column));
+            }
+        }
+        if footed_row.techniques.contains(&Technique::Crossover) {
+            patterns.push((PatternKind::Crossover, i, i));
+        }
+    }
+
+    patterns
+}
+
+/// Returns maximal runs of rows (as index of the first and last row) with at least `min_rows`
+/// rows, where `continues(start, i)` decides if row `i` continues the run started at `start`.
+///
+/// When a run ends, the next run starts at its last row if row `i` continues a run started
+/// there, so back to back runs (e.g. two drills) share that row.
+fn runs<F: Fn(usize, usize) -> bool>(
+    count: usize,
+    min_rows: usize,
+    continues: F,
+) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+
+    for i in 1..=count {
+        if i == count || !continues(start, i) {
+            if i - start >= min_rows {
+                runs.push((start, i - 1));
+            }
+            start = if i < count && continues(i - 1, i) {
+                i - 1
+            } else {
+                i
+            };
+        }
+    }
+
+    runs
+}
+
+/// Returns the column of rows with exactly one active column.
+fn single_column(row: &NoteRow) -> Option<usize> {
+    if row.count_active() == 1 {
+        row.columns.iter().position(|active| *active)
+    } else {
+        None
+    }
+}
+
+/// Returns the direction (1: clockwise, 3: counterclockwise) of a step from the single arrow of
+/// `from` to the neighbouring panel on the same pad in `to`.
+fn ring_direction(from: &NoteRow, to: &NoteRow) -> Option<usize> {
+    // solo has no ring of four panels
+    if from.columns.len() == 6 {
+        return None;
+    }
+    let (from, to) = (single_column(from)?, single_column(to)?);
+    if from / 4 != to / 4 {
+        return None;
+    }
+    // position of left, down, up, right when going clockwise around the pad
+    let ring = |column: usize| [0, 3, 1, 2][column % 4];
+    match (ring(to) + 4 - ring(from)) % 4 {
+        direction @ 1 | direction @ 3 => Some(direction),
+        _ => None,
+    }
+}
+
+fn is_candle(columns: usize, from: usize, to: usize) -> bool {
+    let (down, up) = match columns {
+        6 => (2, 3),
+        _ => (1, 2),
+    };
+    let pad = |column: usize| if columns == 6 { 0 } else { column / 4 };
+    let panel = |column: usize| if columns == 6 { column } else { column % 4 };
+    pad(from) == pad(to)
+        && ((panel(from) == down && panel(to) == up) || (panel(from) == up && panel(to) == down))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Level, Row, Step, Style};
+
+    fn chart(steps: &[(f32, u8)]) -> Chart {
+        Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: steps
+                .iter()
+                .map(|(beats, columns)| Step::Step {
+                    beats: *beats,
+                    row: Row::new(*columns, Style::Single),
+                })
+                .collect(),
+        }
+    }
+
+    fn kinds(chart: &Chart) -> Vec<(PatternKind, usize, usize)> {
+        let mut patterns = find(&rows(chart), &parity::solve(chart));
+        patterns.sort();
+        patterns
+    }
+
+    #[test]
+    fn test_runs() {
+        let values = [1, 1, 1, 2, 2, 3, 3, 3, 3];
+        assert_eq!(
+            runs(values.len(), 3, |_, i| values[i] == values[i - 1]),
+            vec![(0, 2), (5, 8)]
+        );
+        assert_eq!(runs(0, 1, |_, _| true), Vec::new());
+
+        // runs of at most two different values, the second one starts at the last row of the first
+        let values = [1, 2, 1, 2, 3, 2, 3, 2];
+        assert_eq!(
+            runs(values.len(), 3, |start, i| {
+                i < start + 2 || values[i] == values[i - 2]
+            }),
+            vec![(0, 3), (3, 7)]
+        );
+    }
+
+    #[test]
+    fn test_jack() {
+        let chart = chart(&[(0.0, 0b0001), (0.5, 0b0001), (1.0, 0b0001), (3.0, 0b0001)]);
+        assert_eq!(kinds(&chart), vec![(PatternKind::Jack, 0, 2)]);
+    }
+
+    #[test]
+    fn test_drill() {
+        let steps: Vec<(f32, u8)> = (0..6)
+            .map(|i| (i as f32 * 0.5, if i % 2 == 0 { 0b0001 } else { 0b0010 }))
+            .collect();
+        assert_eq!(kinds(&chart(&steps)), vec![(PatternKind::Drill, 0, 5)]);
+
+        // ← ↓ ← ↓ ← ↑ ← ↑ ← (two drills back to back)
+        let columns = [
+            0b0001, 0b0010, 0b0001, 0b0010, 0b0001, 0b0100, 0b0001, 0b0100, 0b0001,
+        ];
+        let steps: Vec<(f32, u8)> = columns
+            .iter()
+            .enumerate()
+            .map(|(i, columns)| (i as f32 * 0.5, *columns))
+            .collect();
+        let drills: Vec<_> = kinds(&chart(&steps))
+            .into_iter()
+            .filter(|(kind, _, _)| *kind == PatternKind::Drill)
+            .collect();
+        assert_eq!(
+            drills,
+            vec![(PatternKind::Drill, 0, 4), (PatternKind::Drill, 4, 8)]
+        );
+    }
+
+    #[test]
+    fn test_gallop() {
+        let gallops = chart(&[(0.0, 0b0001), (0.25, 0b1000), (1.0, 0b0001), (1.25, 0b1000)]);
+        assert_eq!(kinds(&gallops), vec![(PatternKind::Gallop, 0, 3)]);
+        let gallop = chart(&[(0.0, 0b0001), (0.25, 0b1000), (2.0, 0b0001), (4.0, 0b1000)]);
+        assert_eq!(kinds(&gallop), vec![(PatternKind::Gallop, 0, 1)]);
+    }
+
+    #[test]
+    fn test_spin_crossover_candle() {
+        // ← ↑ → ↓ (spin, → crosses, ↑ and ↓ are stepped with the right foot)
+        let chart = chart(&[(0.0, 0b0001), (1.0, 0b0100), (2.0, 0b1000), (3.0, 0b0010)]);
+        let patterns = kinds(&chart);
+        assert!(patterns.contains(&(PatternKind::Spin, 0, 3)));
+        assert_eq!(
+            patterns
+                .iter()
+                .filter(|(kind, _, _)| *kind == PatternKind::Crossover)
+                .count(),
+            1
+        );
+        assert!(patterns.contains(&(PatternKind::Candle, 1, 3)));
+    }
+
+    #[test]
+    fn test_stream_and_jump_stream() {
+        let steps: Vec<(f32, u8)> = (0..16)
+            .map(|i| {
+                let columns = match i % 4 {
+                    0 => 0b1001,
+                    1 => 0b0010,
+                    2 => 0b1000,
+                    _ => 0b0100,
+                };
+                (i as f32 * 0.5, columns)
+            })
+            .collect();
+        let patterns = kinds(&chart(&steps));
+        assert!(patterns.contains(&(PatternKind::Stream, 0, 15)));
+        assert!(patterns.contains(&(PatternKind::JumpStream, 0, 15)));
+    }
+
+    #[test]
+    fn test_detect_and_tags() {
+        let chart = chart(&[(0.0, 0b0001), (0.5, 0b0001), (1.0, 0b0001), (3.0, 0b1000)]);
+        let patterns = detect(&chart, &TempoChanges::default());
+        assert_eq!(
+            patterns,
+            vec![Pattern {
+                kind: PatternKind::Jack,
+                start_beats: 0.0,
+                end_beats: 1.0,
+                start: None,
+                end: None,
+                rows: 3,
+            }]
+        );
+        assert_eq!(count(&patterns, PatternKind::Jack), 1);
+        assert_eq!(tags(&patterns, 4), vec!["jack heavy".to_string()]);
+        assert_eq!(tags(&patterns, 0), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,235 @@
+//! Quantization (snap) of step positions.
+//!
+//! SSQ files store positions in 1/4096 of a measure, which is not divisible by three. Steps on
+//! 12ths or 24ths are therefore only close to their exact position, so positions are compared
+//! with a tolerance. As every snap divides a measure of four beats, the snap relative to the
+//! measure is the same as the snap relative to the start of the chart.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::rows;
+use crate::ddr::ssq::Chart;
+
+/// Default tolerance (in beats): two ticks of the resolution of SSQ files
+pub const DEFAULT_TOLERANCE: f32 = 2.0 / 1024.0;
+
+/// Rhythmic snap of a position, ordered from coarse to fine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Snap {
+    Fourth,
+    Eighth,
+    Twelfth,
+    Sixteenth,
+    TwentyFourth,
+    ThirtySecond,
+    SixtyFourth,
+    HundredNinetySecond,
+}
+
+impl Snap {
+    pub const ALL: [Snap; 8] = [
+        Snap::Fourth,
+        Snap::Eighth,
+        Snap::Twelfth,
+        Snap::Sixteenth,
+        Snap::TwentyFourth,
+        Snap::ThirtySecond,
+        Snap::SixtyFourth,
+        Snap::HundredNinetySecond,
+    ];
+
+    /// Returns the number of notes of this snap in one measure.
+    pub fn per_measure(self) -> u16 {
+        match self {
+            Self::Fourth => 4,
+            Self::Eighth => 8,
+            Self::Twelfth => 12,
+            Self::Sixteenth => 16,
+            Self::TwentyFourth => 24,
+            Self::ThirtySecond => 32,
+            Self::SixtyFourth => 64,
+            Self::HundredNinetySecond => 192,
+        }
+    }
+
+    /// Returns the distance between two notes of this snap in beats.
+    pub fn beats(self) -> f32 {
+        4.0 / f32::from(self.per_measure())
+    }
+
+    /// Returns the snap of a position with the [`DEFAULT_TOLERANCE`].
+    ///
+    /// [`DEFAULT_TOLERANCE`]: constant.DEFAULT_TOLERANCE.html
+    pub fn of(beats: f32) -> Self {
+        Self::with_tolerance(beats, DEFAULT_TOLERANCE)
+    }
+
+    /// Returns the coarsest snap that has a note within `tolerance` beats of `beats`.
+    ///
+    /// Positions that are not on any grid are classified as 192nds.
+    pub fn with_tolerance(beats: f32, tolerance: f32) -> Self {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|snap| snap.contains(beats, tolerance))
+            .unwrap_or(Self::HundredNinetySecond)
+    }
+
+    /// Returns true if a note of this snap is within `tolerance` beats of `beats`.
+    pub fn contains(self, beats: f32, tolerance: f32) -> bool {
+        (self.round(beats) - beats).abs() <= tolerance
+    }
+
+    /// Returns the position of the closest note of this snap.
+    pub fn round(self, beats: f32) -> f32 {
+        (beats / self.beats()).round() * self.beats()
+    }
+
+    /// Returns the measure and the index of the closest note of this snap within the measure.
+    pub fn measure_index(self, beats: f32) -> (i32, u16) {
+        let index = (beats / self.beats()).round() as i32;
+        let per_measure = i32::from(self.per_measure());
+        (
+            index.div_euclid(per_measure),
+            index.rem_euclid(per_measure) as u16,
+        )
+    }
+}
+
+impl fmt::Display for Snap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = match self {
+            Self::ThirtySecond | Self::HundredNinetySecond => "nd",
+            _ => "th",
+        };
+        write!(f, "{}{}", self.per_measure(), suffix)
+    }
+}
+
+/// Returns the coarsest snap that contains all positions (e.g. the steps of a measure).
+///
+/// Positions with snaps that have no common grid except 192nds (like 12ths and 16ths) result in
+/// 192nds.
+pub fn common_snap(positions: &[f32]) -> Snap {
+    Snap::ALL
+        .iter()
+        .copied()
+        .find(|snap| {
+            positions
+                .iter()
+                .all(|beats| snap.contains(*beats, DEFAULT_TOLERANCE))
+        })
+        .unwrap_or(Snap::HundredNinetySecond)
+}
+
+/// Returns the snap of every row (steps and freeze starts) of a chart.
+pub fn snaps(chart: &Chart) -> Vec<(f32, Snap)> {
+    rows(chart)
+        .iter()
+        .map(|row| (row.beats, Snap::of(row.beats)))
+        .collect()
+}
+
+/// Returns the number of rows of each snap.
+pub fn distribution(chart: &Chart) -> BTreeMap<Snap, usize> {
+    let mut distribution = BTreeMap::new();
+    for (_, snap) in snaps(chart) {
+        *distribution.entry(snap).or_insert(0) += 1;
+    }
+    distribution
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Level, Row, Step, Style};
+
+    /// Position as it is stored in SSQ files
+    fn stored(beats: f32) -> f32 {
+        (beats * 1024.0).round() / 1024.0
+    }
+
+    #[test]
+    fn test_snap_of() {
+        assert_eq!(Snap::of(3.0), Snap::Fourth);
+        assert_eq!(Snap::of(3.5), Snap::Eighth);
+        assert_eq!(Snap::of(stored(1.0 / 3.0)), Snap::Twelfth);
+        assert_eq!(Snap::of(stored(2.0 / 3.0)), Snap::Twelfth);
+        assert_eq!(Snap::of(3.25), Snap::Sixteenth);
+        assert_eq!(Snap::of(stored(1.0 / 6.0)), Snap::TwentyFourth);
+        assert_eq!(Snap::of(3.125), Snap::ThirtySecond);
+        assert_eq!(Snap::of(3.0625), Snap::SixtyFourth);
+        assert_eq!(Snap::of(stored(1.0 / 48.0)), Snap::HundredNinetySecond);
+        assert_eq!(Snap::of(0.01), Snap::HundredNinetySecond);
+        assert_eq!(Snap::of(-0.5), Snap::Eighth);
+    }
+
+    #[test]
+    fn test_snap_with_tolerance() {
+        assert_eq!(Snap::with_tolerance(1.01, 0.02), Snap::Fourth);
+        assert_eq!(Snap::with_tolerance(1.01, 0.0), Snap::HundredNinetySecond);
+    }
+
+    #[test]
+    fn test_snap_round() {
+        assert_eq!(Snap::Eighth.round(1.3), 1.5);
+        assert_eq!(Snap::Fourth.round(stored(4.0 / 3.0)), 1.0);
+        assert_eq!(Snap::Twelfth.measure_index(stored(17.0 / 3.0)), (1, 5));
+        assert_eq!(Snap::Sixteenth.measure_index(-0.25), (-1, 15));
+    }
+
+    #[test]
+    fn test_snap_display() {
+        let names: Vec<String> = Snap::ALL.iter().map(|snap| snap.to_string()).collect();
+        assert_eq!(
+            names,
+            vec!["4th", "8th", "12th", "16th", "24th", "32nd", "64th", "192nd"]
+        );
+    }
+
+    #[test]
+    fn test_common_snap() {
+        assert_eq!(common_snap(&[]), Snap::Fourth);
+        assert_eq!(common_snap(&[0.0, 1.0, 2.5]), Snap::Eighth);
+        assert_eq!(common_snap(&[0.0, stored(1.0 / 3.0)]), Snap::Twelfth);
+        assert_eq!(
+            common_snap(&[stored(1.0 / 3.0), 0.25]),
+            Snap::HundredNinetySecond
+        );
+    }
+
+    #[test]
+    fn test_distribution() {
+        let chart = Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: vec![
+                Step::Step {
+                    beats: 0.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Step {
+                    beats: 0.5,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Freeze {
+                    start: 1.0,
+                    end: 2.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Shock { beats: 1.25 },
+            ],
+        };
+        assert_eq!(
+            snaps(&chart),
+            vec![
+                (0.0, Snap::Fourth),
+                (0.5, Snap::Eighth),
+                (1.0, Snap::Fourth)
+            ]
+        );
+        let distribution = distribution(&chart);
+        assert_eq!(distribution[&Snap::Fourth], 2);
+        assert_eq!(distribution[&Snap::Eighth], 1);
+        assert_eq!(distribution.get(&Snap::Sixteenth), None);
+    }
+}
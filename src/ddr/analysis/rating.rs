@@ -0,0 +1,218 @@
+//! Estimation of the foot rating (level) of charts without musicdb data.
+//!
+//! The estimate is a weighted sum of the Groove Radar values and the rate of crossovers, mapped
+//! linearly onto the levels from [`MIN_LEVEL`] to [`MAX_LEVEL`]. The weights are rough and have not
+//! been checked against official ratings, so the estimate is only a hint. It can not account for
+//! everything that makes a chart hard.
+//!
+//! [`MIN_LEVEL`]: constant.MIN_LEVEL.html
+//! [`MAX_LEVEL`]: constant.MAX_LEVEL.html
+use super::groove_radar::GrooveRadar;
+use super::stats::ChartStats;
+use super::{rows, NoteRow};
+use crate::ddr::ssq::{Chart, TempoChanges};
+
+pub const MIN_LEVEL: u8 = 1;
+pub const MAX_LEVEL: u8 = 19;
+
+/// Difficulty score that corresponds to one level.
+const SCORE_PER_LEVEL: f32 = 6.5;
+
+/// Returns a continuous difficulty score (0 for an empty chart) for a chart.
+pub fn difficulty_score(chart: &Chart, tempo_changes: &TempoChanges) -> f32 {
+    let radar = GrooveRadar::new(chart, tempo_changes);
+    let rows = rows(chart);
+
+    let length_minutes = (ChartStats::new(chart, tempo_changes).length / 60000.0) as f32;
+    let crossovers_per_minute = if length_minutes > 0.0 {
+        count_crossovers(&rows) as f32 / length_minutes
+    } else {
+        0.0
+    };
+
+    0.55 * radar.stream
+        + 0.25 * radar.voltage
+        + 0.1 * radar.air
+        + 0.05 * radar.freeze
+        + 0.1 * radar.chaos
+        + 0.5 * crossovers_per_minute
+}
+
+/// Returns the estimated foot rating (from [`MIN_LEVEL`] to [`MAX_LEVEL`]) of a chart.
+///
+/// [`MIN_LEVEL`]: constant.MIN_LEVEL.html
+/// [`MAX_LEVEL`]: constant.MAX_LEVEL.html
+pub fn estimate_level(chart: &Chart, tempo_changes: &TempoChanges) -> u8 {
+    level_from_score(difficulty_score(chart, tempo_changes))
+}
+
+fn level_from_score(score: f32) -> u8 {
+    let level = (f32::from(MIN_LEVEL) + score / SCORE_PER_LEVEL).round();
+    level.max(f32::from(MIN_LEVEL)).min(f32::from(MAX_LEVEL)) as u8
+}
+
+/// Returns the level as `f32` where 0.0 is [`MIN_LEVEL`] and 1.0 is [`MAX_LEVEL`].
+///
+/// [`MIN_LEVEL`]: constant.MIN_LEVEL.html
+/// [`MAX_LEVEL`]: constant.MAX_LEVEL.html
+pub fn relative_level(level: u8) -> f32 {
+    let level = level.clamp(MIN_LEVEL, MAX_LEVEL);
+    f32::from(level - MIN_LEVEL) / f32::from(MAX_LEVEL - MIN_LEVEL)
+}
+
+/// Counts the steps that are most likely hit with crossed feet.
+///
+/// Single arrows are assumed to alternate between the feet (starting with the foot that does not
+/// cross on the first step); jumps reset the alternation. A step is crossed if the left foot
+/// hits the rightmost column of a pad or the right foot the leftmost one.
+fn count_crossovers(rows: &[NoteRow]) -> usize {
+    let mut crossovers = 0;
+    // true if the next single arrow is expected to be hit with the left foot
+    let mut left_foot: Option<bool> = None;
+
+    for row in rows {
+        if row.count_active() != 1 {
+            left_foot = None;
+            continue;
+        }
+        let column = row.columns.iter().position(|active| *active).unwrap();
+        // solo has six columns on one pad, all other styles four per pad
+        let (pad_column, last_column) = match row.columns.len() {
+            6 => (column, 5),
+            _ => (column % 4, 3),
+        };
+        let is_left = pad_column == 0;
+        let is_right = pad_column == last_column;
+
+        let foot = match left_foot {
+            Some(foot) => foot,
+            None => !is_right,
+        };
+        if (foot && is_right) || (!foot && is_left) {
+            crossovers += 1;
+        }
+        left_foot = Some(!foot);
+    }
+
+    crossovers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Level, Row, Step, Style, TempoChange, TempoChangeKind};
+
+    /// 150 BPM for 240 beats
+    fn tempo_changes() -> TempoChanges {
+        TempoChanges {
+            ticks_per_second: 150,
+            first_measure: 0,
+            first_tick: 0,
+            changes: vec![
+                TempoChange {
+                    start_ms: 0.0,
+                    start_beats: 0.0,
+                    end_beats: 240.0,
+                    beat_length: 400.0,
+                    kind: TempoChangeKind::Scroll,
+                },
+                TempoChange {
+                    start_ms: 96000.0,
+                    start_beats: 240.0,
+                    end_beats: 240.0,
+                    beat_length: f64::INFINITY,
+                    kind: TempoChangeKind::Stop,
+                },
+            ],
+        }
+    }
+
+    /// Chart with single arrows every `interval` beats cycling through `columns`
+    fn chart(interval: f32, columns: &[u8]) -> Chart {
+        let count = (240.0 / interval) as usize;
+        Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: (0..count)
+                .map(|i| Step::Step {
+                    beats: i as f32 * interval,
+                    row: Row::new(columns[i % columns.len()], Style::Single),
+                })
+                .collect(),
+        }
+    }
+
+    fn row(columns: u8) -> NoteRow {
+        let columns: Vec<bool> = Row::new(columns, Style::Single).into();
+        NoteRow {
+            beats: 0.0,
+            freeze_ends: vec![None; columns.len()],
+            columns,
+        }
+    }
+
+    #[test]
+    fn test_estimate_level_ordered() {
+        let tempo_changes = tempo_changes();
+        let easy = estimate_level(&chart(2.0, &[0b0001, 0b1000]), &tempo_changes);
+        let medium = estimate_level(
+            &chart(1.0, &[0b0001, 0b0010, 0b0100, 0b1000]),
+            &tempo_changes,
+        );
+        let hard = estimate_level(
+            &chart(0.25, &[0b0001, 0b0010, 0b0100, 0b1000]),
+            &tempo_changes,
+        );
+        assert!(easy < medium);
+        assert!(medium < hard);
+        assert!(easy >= MIN_LEVEL);
+        assert!(hard <= MAX_LEVEL);
+    }
+
+    #[test]
+    fn test_difficulty_score_without_end_stop() {
+        // ← ↓ → has one crossover
+        let chart = chart(1.0, &[0b0001, 0b0010, 0b1000]);
+        let mut without_stop = tempo_changes();
+        without_stop.changes.pop();
+        assert_eq!(
+            difficulty_score(&chart, &without_stop),
+            difficulty_score(&chart, &tempo_changes())
+        );
+    }
+
+    #[test]
+    fn test_estimate_level_empty() {
+        let chart = Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: Vec::new(),
+        };
+        assert_eq!(estimate_level(&chart, &TempoChanges::default()), MIN_LEVEL);
+    }
+
+    #[test]
+    fn test_level_from_score() {
+        assert_eq!(level_from_score(0.0), MIN_LEVEL);
+        assert_eq!(level_from_score(SCORE_PER_LEVEL * 4.0), 5);
+        assert_eq!(level_from_score(1000.0), MAX_LEVEL);
+    }
+
+    #[test]
+    fn test_relative_level() {
+        assert_eq!(relative_level(MIN_LEVEL), 0.0);
+        assert_eq!(relative_level(10), 0.5);
+        assert_eq!(relative_level(MAX_LEVEL), 1.0);
+        assert_eq!(relative_level(0), 0.0);
+    }
+
+    #[test]
+    fn test_count_crossovers() {
+        // ← ↓ → (last step crossed), ← → ← → (no crossover)
+        let rows = vec![row(0b0001), row(0b0010), row(0b1000)];
+        assert_eq!(count_crossovers(&rows), 1);
+        let rows = vec![row(0b0001), row(0b1000), row(0b0001), row(0b1000)];
+        assert_eq!(count_crossovers(&rows), 0);
+        // jumps reset the alternation
+        let rows = vec![row(0b0001), row(0b1001), row(0b1000)];
+        assert_eq!(count_crossovers(&rows), 0);
+    }
+}
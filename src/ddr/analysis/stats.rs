@@ -0,0 +1,256 @@
+//! Note counts, timing and density of a chart.
+use log::warn;
+
+use super::{rows, shocks};
+use crate::ddr::ssq::{Chart, TempoChanges, TimingMap};
+
+/// Length of the window used for [`ChartStats.peak_nps`] (in milliseconds).
+///
+/// [`ChartStats.peak_nps`]: struct.ChartStats.html#structfield.peak_nps
+pub const NPS_WINDOW: f64 = 1000.0;
+
+/// Statistics about a chart.
+///
+/// All times are in milliseconds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChartStats {
+    /// Number of rows with steps or freeze starts (a jump counts as one step)
+    pub steps: u32,
+    /// Number of panels stepped on (a jump counts as two arrows)
+    pub arrows: u32,
+    pub jumps: u32,
+    pub freezes: u32,
+    pub shocks: u32,
+    /// Combo after a full combo (every step, freeze and shock adds one)
+    pub max_combo: u32,
+    pub first_note: Option<f64>,
+    /// Time of the last step, freeze end or shock
+    pub last_note: Option<f64>,
+    /// End of the tempo data (or the last note, if it is after it)
+    pub length: f64,
+    /// Highest number of steps per second in any window of [`NPS_WINDOW`]
+    ///
+    /// [`NPS_WINDOW`]: constant.NPS_WINDOW.html
+    pub peak_nps: f64,
+    /// Steps per second between the first and the last step
+    pub average_nps: f64,
+}
+
+impl ChartStats {
+    pub fn new(chart: &Chart, tempo_changes: &TempoChanges) -> Self {
+        Self::with_window(chart, tempo_changes, NPS_WINDOW)
+    }
+
+    /// Like [`new`], but uses a window of `window` milliseconds for [`peak_nps`].
+    ///
+    /// [`new`]: #method.new
+    /// [`peak_nps`]: #structfield.peak_nps
+    pub fn with_window(chart: &Chart, tempo_changes: &TempoChanges, window: f64) -> Self {
+        let timing_map = TimingMap::new(tempo_changes);
+        let time_at = |beats: f32| {
+            let time = timing_map.time_at(beats);
+            if time.is_none() {
+                warn!("Could not get time of step at beat {}, ignoring it", beats);
+            }
+            time
+        };
+
+        let rows = rows(chart);
+        let shocks = shocks(chart);
+
+        let steps = rows.len() as u32;
+        let arrows = rows.iter().map(|row| row.count_active() as u32).sum();
+        let jumps = rows.iter().filter(|row| row.is_jump()).count() as u32;
+        let freezes = rows.iter().map(|row| row.count_freezes() as u32).sum();
+
+        let step_times: Vec<f64> = rows.iter().filter_map(|row| time_at(row.beats)).collect();
+        let note_times: Vec<f64> = step_times
+            .iter()
+            .copied()
+            .chain(
+                rows.iter()
+                    .filter_map(|row| row.last_freeze_end())
+                    .filter_map(time_at),
+            )
+            .chain(shocks.iter().copied().filter_map(time_at))
+            .collect();
+
+        let first_note = note_times.iter().copied().fold(None, min);
+        let last_note = note_times.iter().copied().fold(None, max);
+
+        // the last tempo change ends after its beats, unless it is a stop (of unknown length)
+        let end_of_tempo_changes = tempo_changes.last().map(|change| {
+            if change.beat_length.is_finite() {
+                change.start_ms
+                    + change.beat_length * f64::from(change.end_beats - change.start_beats)
+            } else {
+                change.start_ms
+            }
+        });
+        let length = max(end_of_tempo_changes, last_note.unwrap_or(0.0)).unwrap_or(0.0);
+
+        let average_nps = match (step_times.first(), step_times.last()) {
+            (Some(first), Some(last)) if last > first => {
+                f64::from(steps) / ((last - first) / 1000.0)
+            }
+            _ => 0.0,
+        };
+
+        Self {
+            steps,
+            arrows,
+            jumps,
+            freezes,
+            shocks: shocks.len() as u32,
+            max_combo: steps + freezes + shocks.len() as u32,
+            first_note,
+            last_note,
+            length,
+            peak_nps: peak_density(&step_times, window),
+            average_nps,
+        }
+    }
+}
+
+fn min(acc: Option<f64>, value: f64) -> Option<f64> {
+    Some(acc.map_or(value, |acc| acc.min(value)))
+}
+
+fn max(acc: Option<f64>, value: f64) -> Option<f64> {
+    Some(acc.map_or(value, |acc| acc.max(value)))
+}
+
+/// Returns the highest number of notes per second in any window of `window` milliseconds.
+///
+/// `times` has to be sorted.
+pub fn peak_density(times: &[f64], window: f64) -> f64 {
+    if window <= 0.0 {
+        return 0.0;
+    }
+
+    let mut peak = 0;
+    let mut start = 0;
+    for (end, time) in times.iter().enumerate() {
+        while time - times[start] >= window {
+            start += 1;
+        }
+        peak = peak.max(end - start + 1);
+    }
+
+    peak as f64 / (window / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Level, Row, Step, Style, TempoChange, TempoChangeKind};
+
+    /// 120 BPM for 32 beats
+    fn tempo_changes() -> TempoChanges {
+        TempoChanges {
+            ticks_per_second: 150,
+            first_measure: 0,
+            first_tick: 0,
+            changes: vec![
+                TempoChange {
+                    start_ms: 0.0,
+                    start_beats: 0.0,
+                    end_beats: 32.0,
+                    beat_length: 500.0,
+                    kind: TempoChangeKind::Scroll,
+                },
+                TempoChange {
+                    start_ms: 16000.0,
+                    start_beats: 32.0,
+                    end_beats: 32.0,
+                    beat_length: f64::INFINITY,
+                    kind: TempoChangeKind::Stop,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_chart_stats() {
+        let chart = Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: vec![
+                Step::Step {
+                    beats: 4.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Step {
+                    beats: 4.5,
+                    row: Row::new(0b1001, Style::Single),
+                },
+                Step::Step {
+                    beats: 5.0,
+                    row: Row::new(0b0010, Style::Single),
+                },
+                Step::Freeze {
+                    start: 5.0,
+                    end: 6.0,
+                    row: Row::new(0b0100, Style::Single),
+                },
+                Step::Shock { beats: 8.0 },
+            ],
+        };
+        let stats = ChartStats::new(&chart, &tempo_changes());
+        assert_eq!(
+            stats,
+            ChartStats {
+                steps: 3,
+                arrows: 5,
+                jumps: 2,
+                freezes: 1,
+                shocks: 1,
+                max_combo: 5,
+                first_note: Some(2000.0),
+                last_note: Some(4000.0),
+                length: 16000.0,
+                peak_nps: 3.0,
+                average_nps: 6.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_chart_stats_trailing_scroll() {
+        let chart = Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: vec![Step::Step {
+                beats: 4.0,
+                row: Row::new(0b0001, Style::Single),
+            }],
+        };
+        let mut tempo_changes = tempo_changes();
+        tempo_changes.changes.pop();
+        let stats = ChartStats::new(&chart, &tempo_changes);
+        assert_eq!(stats.last_note, Some(2000.0));
+        assert_eq!(stats.length, 16000.0);
+    }
+
+    #[test]
+    fn test_chart_stats_empty() {
+        let chart = Chart {
+            difficulty: Level::new(Style::Double, 1).unwrap(),
+            steps: vec![],
+        };
+        let stats = ChartStats::new(&chart, &TempoChanges::default());
+        assert_eq!(stats.steps, 0);
+        assert_eq!(stats.first_note, None);
+        assert_eq!(stats.length, 0.0);
+        assert_eq!(stats.peak_nps, 0.0);
+        assert_eq!(stats.average_nps, 0.0);
+    }
+
+    #[test]
+    fn test_peak_density() {
+        assert_eq!(peak_density(&[], 1000.0), 0.0);
+        assert_eq!(
+            peak_density(&[0.0, 250.0, 500.0, 750.0, 1000.0], 1000.0),
+            4.0
+        );
+        assert_eq!(peak_density(&[0.0, 100.0, 2000.0], 500.0), 4.0);
+    }
+}
@@ -0,0 +1,150 @@
+//! Rendering of step charts for humans.
+//!
+//! Charts are drawn from top to bottom with one lane per column, ordered as in
+//! `Into<Vec<bool>>` of [`Row`]. Tempo changes and stops are annotated next to the lanes.
+//!
+//! [`Row`]: ../ssq/enum.Row.html
+pub mod svg;
+pub mod text;
+
+use std::fmt;
+
+use crate::ddr::ssq::{Row, Style, TempoChangeKind, TempoChanges};
+
+/// Tempo changes (in BPM) that differ by less than this are not annotated
+const BPM_TOLERANCE: f32 = 0.01;
+
+/// Returns the arrow of every column (e.g. `←↓↑→` for Single).
+pub fn column_glyphs(style: Style) -> Vec<char> {
+    let columns = usize::from(style.columns());
+    (0..columns)
+        .map(|column| {
+            let mut active = vec![false; columns];
+            active[column] = true;
+            Row::from_columns(&active, style)
+                .to_string()
+                .chars()
+                .find(|glyph| *glyph != ' ')
+                .unwrap_or('?')
+        })
+        .collect()
+}
+
+/// Change of the tempo that is shown next to a chart.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Annotation {
+    /// Tempo in BPM
+    Tempo(f32),
+    /// Duration of a stop in milliseconds
+    Stop(f64),
+    /// Duration of a delay in milliseconds
+    Delay(f64),
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tempo(bpm) => write!(f, "{} BPM", (bpm * 10.0).round() / 10.0),
+            Self::Stop(duration) => write!(f, "stop {:.0} ms", duration),
+            Self::Delay(duration) => write!(f, "delay {:.0} ms", duration),
+        }
+    }
+}
+
+/// Returns the annotations of the tempo changes, ordered by their position (in beats).
+///
+/// The tempo is annotated at the start and wherever it changes, stops and delays wherever they
+/// have a duration.
+pub fn annotations(tempo_changes: &TempoChanges) -> Vec<(f32, Annotation)> {
+    let mut annotations = Vec::new();
+    let mut last_bpm: Option<f32> = None;
+
+    for tempo_change in tempo_changes.iter() {
+        if tempo_change.kind != TempoChangeKind::Scroll {
+            continue;
+        }
+        let bpm = (60000.0 / tempo_change.beat_length) as f32;
+        if !matches!(last_bpm, Some(last) if (last - bpm).abs() < BPM_TOLERANCE) {
+            annotations.push((tempo_change.start_beats, Annotation::Tempo(bpm)));
+            last_bpm = Some(bpm);
+        }
+    }
+    for stop in tempo_changes.stops() {
+        if stop.duration_ms > 0.0 {
+            annotations.push((stop.beats, Annotation::Stop(stop.duration_ms)));
+        }
+    }
+    for delay in tempo_changes.delays() {
+        if delay.duration_ms > 0.0 {
+            annotations.push((delay.beats, Annotation::Delay(delay.duration_ms)));
+        }
+    }
+
+    // stable sort, so the tempo stays in front of stops at the same position
+    annotations.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    annotations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::TempoChange;
+
+    #[test]
+    fn test_column_glyphs() {
+        assert_eq!(column_glyphs(Style::Single), vec!['←', '↓', '↑', '→']);
+        assert_eq!(
+            column_glyphs(Style::Solo),
+            vec!['←', '↖', '↓', '↑', '↗', '→']
+        );
+        assert_eq!(
+            column_glyphs(Style::Double),
+            vec!['←', '↓', '↑', '→', '←', '↓', '↑', '→']
+        );
+    }
+
+    #[test]
+    fn test_annotations() {
+        let scroll = |start_ms, start_beats, end_beats, bpm: f64| TempoChange {
+            start_ms,
+            start_beats,
+            end_beats,
+            beat_length: 60000.0 / bpm,
+            kind: TempoChangeKind::Scroll,
+        };
+        let tempo_changes = TempoChanges {
+            ticks_per_second: 150,
+            first_measure: 0,
+            first_tick: 0,
+            changes: vec![
+                scroll(0.0, 0.0, 4.0, 150.0),
+                TempoChange {
+                    start_ms: 1600.0,
+                    start_beats: 4.0,
+                    end_beats: 4.0,
+                    beat_length: f64::INFINITY,
+                    kind: TempoChangeKind::Stop,
+                },
+                scroll(2100.0, 4.0, 8.0, 150.0),
+                scroll(3700.0, 8.0, 12.0, 200.0),
+                TempoChange {
+                    start_ms: 4900.0,
+                    start_beats: 12.0,
+                    end_beats: 12.0,
+                    beat_length: f64::INFINITY,
+                    kind: TempoChangeKind::Stop,
+                },
+            ],
+        };
+        assert_eq!(
+            annotations(&tempo_changes),
+            vec![
+                (0.0, Annotation::Tempo(150.0)),
+                (4.0, Annotation::Stop(500.0)),
+                (8.0, Annotation::Tempo(200.0)),
+            ]
+        );
+        assert_eq!(Annotation::Tempo(149.99).to_string(), "150 BPM");
+        assert_eq!(Annotation::Stop(500.2).to_string(), "stop 500 ms");
+    }
+}
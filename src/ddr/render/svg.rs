@@ -0,0 +1,403 @@
+//! Rendering of step charts as SVG chart sheets.
+//!
+//! A sheet starts with a title and a density strip (one bar per measure, its height is the number
+//! of rows in the measure). Below, the measures are drawn from top to bottom in columns of
+//! [`Options::measures_per_column`] measures. Several charts can be placed side by side to compare
+//! them.
+//!
+//! [`Options::measures_per_column`]: struct.Options.html#structfield.measures_per_column
+use std::fmt::Write;
+
+use super::{annotations, column_glyphs, Annotation};
+use crate::ddr::analysis::quantization::{Snap, DEFAULT_TOLERANCE};
+use crate::ddr::analysis::{rows, shocks};
+use crate::ddr::ssq::{Chart, TempoChanges};
+
+const BEATS_PER_MEASURE: f32 = 4.0;
+const LANE_WIDTH: f32 = 16.0;
+const BEAT_HEIGHT: f32 = 16.0;
+/// Space between the two pads of Double charts
+const PAD_GAP: f32 = 6.0;
+/// Space left of the lanes for measure numbers
+const NUMBER_WIDTH: f32 = 24.0;
+/// Space right of the lanes for tempo changes and stops
+const ANNOTATION_WIDTH: f32 = 64.0;
+const TITLE_HEIGHT: f32 = 24.0;
+const STRIP_HEIGHT: f32 = 24.0;
+const MARGIN: f32 = 8.0;
+/// Space between sheets of different charts
+const SHEET_GAP: f32 = 24.0;
+/// Arrow pointing up, centered at the origin
+const ARROW: &str = "0,-7 7,0 3,0 3,7 -3,7 -3,0 -7,0";
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Number of measures in each column of the sheet
+    pub measures_per_column: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            measures_per_column: 8,
+        }
+    }
+}
+
+/// Returns the fill color of arrows with this snap (similar to the note colors of DDR).
+fn color(snap: Snap) -> &'static str {
+    match snap {
+        Snap::Fourth => "#e53935",
+        Snap::Eighth => "#1e88e5",
+        Snap::Twelfth => "#8e24aa",
+        Snap::Sixteenth => "#fdd835",
+        Snap::TwentyFourth => "#ec407a",
+        Snap::ThirtySecond => "#fb8c00",
+        Snap::SixtyFourth => "#00acc1",
+        Snap::HundredNinetySecond => "#43a047",
+    }
+}
+
+/// Returns the rotation (in degrees) of an arrow glyph.
+fn rotation(glyph: char) -> i32 {
+    match glyph {
+        '←' => -90,
+        '↓' => 180,
+        '→' => 90,
+        '↖' => -45,
+        '↗' => 45,
+        _ => 0,
+    }
+}
+
+/// Escapes text for use in SVG elements and attributes.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn measure_of(beats: f32) -> i32 {
+    ((beats + DEFAULT_TOLERANCE) / BEATS_PER_MEASURE).floor() as i32
+}
+
+/// Sheet of one chart, drawn at the origin.
+struct Sheet {
+    content: String,
+    width: f32,
+    height: f32,
+}
+
+impl Sheet {
+    fn new(chart: &Chart, tempo_changes: &TempoChanges, options: &Options) -> Self {
+        let measures_per_column = options.measures_per_column.max(1);
+        let glyphs = column_glyphs(chart.difficulty.style);
+        let lanes_width =
+            glyphs.len() as f32 * LANE_WIDTH + if glyphs.len() == 8 { PAD_GAP } else { 0.0 };
+        let column_width = NUMBER_WIDTH + lanes_width + ANNOTATION_WIDTH;
+        let column_height = measures_per_column as f32 * BEATS_PER_MEASURE * BEAT_HEIGHT;
+
+        let rows = rows(chart);
+        let shocks = shocks(chart);
+        let positions = rows
+            .iter()
+            .map(|row| row.last_freeze_end().unwrap_or(row.beats))
+            .chain(shocks.iter().copied());
+        let measures = positions
+            .map(measure_of)
+            .max()
+            .map_or(0, |last| last.max(0) + 1) as usize;
+        let columns = (measures.max(1) - 1) / measures_per_column + 1;
+
+        let width = columns as f32 * column_width;
+        let top = TITLE_HEIGHT + STRIP_HEIGHT + MARGIN;
+        let height = top + column_height;
+
+        // position of a column of the chart at `beats` (`None` before the first measure)
+        let position = |column: usize, beats: f32| -> Option<(f32, f32)> {
+            let measure = measure_of(beats);
+            if measure < 0 {
+                return None;
+            }
+            let page = measure as usize / measures_per_column;
+            let page_beats = (page * measures_per_column) as f32 * BEATS_PER_MEASURE;
+            let gap = if glyphs.len() == 8 && column >= 4 {
+                PAD_GAP
+            } else {
+                0.0
+            };
+            let x = page as f32 * column_width
+                + NUMBER_WIDTH
+                + column as f32 * LANE_WIDTH
+                + LANE_WIDTH / 2.0
+                + gap;
+            let y = top + (beats - page_beats) * BEAT_HEIGHT;
+            Some((x, y))
+        };
+
+        let mut content = String::new();
+        writeln!(
+            content,
+            r#"<text x="0" y="16" font-size="14" font-weight="bold">{}</text>"#,
+            escape(&chart.difficulty.to_string())
+        )
+        .unwrap();
+
+        // density strip
+        let mut density = vec![0; measures];
+        for row in &rows {
+            if let Some(count) = density.get_mut(measure_of(row.beats).max(0) as usize) {
+                *count += 1;
+            }
+        }
+        let max_density = density.iter().copied().max().unwrap_or(0).max(1);
+        let bar_width = width / measures.max(1) as f32;
+        for (measure, count) in density.iter().enumerate() {
+            let bar_height = STRIP_HEIGHT * *count as f32 / max_density as f32;
+            writeln!(
+                content,
+                r##"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="#78909c"/>"##,
+                measure as f32 * bar_width,
+                TITLE_HEIGHT + STRIP_HEIGHT - bar_height,
+                bar_width,
+                bar_height
+            )
+            .unwrap();
+        }
+
+        // measure and beat lines
+        for measure in 0..measures {
+            for beat in 0..BEATS_PER_MEASURE as usize {
+                let beats = measure as f32 * BEATS_PER_MEASURE + beat as f32;
+                let (x, y) = position(0, beats).unwrap();
+                let x = x - LANE_WIDTH / 2.0;
+                let stroke = if beat == 0 { "#455a64" } else { "#cfd8dc" };
+                writeln!(
+                    content,
+                    r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}"/>"#,
+                    x,
+                    y,
+                    x + lanes_width,
+                    y,
+                    stroke
+                )
+                .unwrap();
+                if beat == 0 {
+                    writeln!(
+                        content,
+                        r#"<text x="{:.1}" y="{:.1}" font-size="9" text-anchor="end">{}</text>"#,
+                        x - 4.0,
+                        y + 9.0,
+                        measure + 1
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        // freezes, split where the chart continues in the next column
+        for row in &rows {
+            for (column, end) in row.freeze_ends.iter().enumerate() {
+                let end = match end {
+                    Some(end) => *end,
+                    None => continue,
+                };
+                let mut start = row.beats;
+                while start < end {
+                    let page_end = ((measure_of(start).max(0) as usize / measures_per_column + 1)
+                        * measures_per_column) as f32
+                        * BEATS_PER_MEASURE;
+                    let segment_end = end.min(page_end);
+                    if let Some((x, y)) = position(column, start) {
+                        writeln!(
+                            content,
+                            r##"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="#7cb342" fill-opacity="0.6"/>"##,
+                            x - LANE_WIDTH / 4.0,
+                            y,
+                            LANE_WIDTH / 2.0,
+                            (segment_end - start) * BEAT_HEIGHT
+                        )
+                        .unwrap();
+                    }
+                    start = segment_end;
+                }
+            }
+        }
+
+        for beats in &shocks {
+            if let Some((x, y)) = position(0, *beats) {
+                let x = x - LANE_WIDTH / 2.0;
+                writeln!(
+                    content,
+                    r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#29b6f6" stroke-width="3" stroke-dasharray="3 2"/>"##,
+                    x,
+                    y,
+                    x + lanes_width,
+                    y
+                )
+                .unwrap();
+            }
+        }
+
+        for row in &rows {
+            let fill = color(Snap::of(row.beats));
+            for (column, active) in row.columns.iter().enumerate() {
+                if !active {
+                    continue;
+                }
+                if let Some((x, y)) = position(column, row.beats) {
+                    writeln!(
+                        content,
+                        r##"<polygon points="{}" transform="translate({:.1} {:.1}) rotate({})" fill="{}" stroke="#263238" stroke-width="0.5"/>"##,
+                        ARROW,
+                        x,
+                        y,
+                        rotation(glyphs[column]),
+                        fill
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        for (beats, annotation) in annotations(tempo_changes) {
+            if measure_of(beats) >= measures as i32 {
+                continue;
+            }
+            if let Some((x, y)) = position(glyphs.len() - 1, beats) {
+                let fill = match annotation {
+                    Annotation::Tempo(_) => "#1565c0",
+                    Annotation::Stop(_) | Annotation::Delay(_) => "#c62828",
+                };
+                writeln!(
+                    content,
+                    r#"<text x="{:.1}" y="{:.1}" font-size="9" fill="{}">{}</text>"#,
+                    x + LANE_WIDTH / 2.0 + 4.0,
+                    y + 3.0,
+                    fill,
+                    escape(&annotation.to_string())
+                )
+                .unwrap();
+            }
+        }
+
+        Self {
+            content,
+            width,
+            height,
+        }
+    }
+}
+
+/// Renders a chart sheet.
+pub fn render(chart: &Chart, tempo_changes: &TempoChanges, options: &Options) -> String {
+    render_charts(&[chart], tempo_changes, options)
+}
+
+/// Renders the sheets of several charts (e.g. all difficulties of a song) side by side.
+pub fn render_charts(charts: &[&Chart], tempo_changes: &TempoChanges, options: &Options) -> String {
+    let sheets: Vec<Sheet> = charts
+        .iter()
+        .map(|chart| Sheet::new(chart, tempo_changes, options))
+        .collect();
+
+    let width = sheets.iter().map(|sheet| sheet.width).sum::<f32>()
+        + SHEET_GAP * sheets.len().saturating_sub(1) as f32
+        + 2.0 * MARGIN;
+    let height = sheets.iter().map(|sheet| sheet.height).fold(0.0, f32::max) + 2.0 * MARGIN;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}" font-family="sans-serif">"#,
+        width, height, width, height
+    )
+    .unwrap();
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+    let mut x = MARGIN;
+    for sheet in sheets {
+        writeln!(svg, r#"<g transform="translate({:.1} {:.1})">"#, x, MARGIN).unwrap();
+        svg.push_str(&sheet.content);
+        svg.push_str("</g>\n");
+        x += sheet.width + SHEET_GAP;
+    }
+    svg.push_str("</svg>\n");
+
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Level, Row, Step, Style, TempoChange, TempoChangeKind};
+
+    fn tempo_changes() -> TempoChanges {
+        TempoChanges {
+            ticks_per_second: 150,
+            first_measure: 0,
+            first_tick: 0,
+            changes: vec![TempoChange {
+                start_ms: 0.0,
+                start_beats: 0.0,
+                end_beats: 64.0,
+                beat_length: 400.0,
+                kind: TempoChangeKind::Scroll,
+            }],
+        }
+    }
+
+    fn chart(style: Style) -> Chart {
+        Chart {
+            difficulty: Level::new(style, 1).unwrap(),
+            steps: vec![
+                Step::Step {
+                    beats: 0.0,
+                    row: Row::new(0b0001, style),
+                },
+                Step::Step {
+                    beats: 1.5,
+                    row: Row::new(0b1010, style),
+                },
+                // continues in the second column
+                Step::Freeze {
+                    start: 30.0,
+                    end: 34.0,
+                    row: Row::new(0b0100, style),
+                },
+                Step::Shock { beats: 36.0 },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let svg = render(&chart(Style::Single), &tempo_changes(), &Options::default());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<polygon").count(), 4);
+        assert_eq!(svg.matches("#e53935").count(), 2);
+        assert_eq!(svg.matches("#1e88e5").count(), 2);
+        assert_eq!(svg.matches("#7cb342").count(), 2);
+        assert_eq!(svg.matches("stroke-dasharray").count(), 1);
+        assert!(svg.contains(">Single Basic</text>"));
+        assert!(svg.contains(">150 BPM</text>"));
+        // 10 measures in two columns
+        assert!(svg.contains(">10</text>"));
+        assert!(!svg.contains(">11</text>"));
+    }
+
+    #[test]
+    fn test_render_charts() {
+        let single = chart(Style::Single);
+        let double = chart(Style::Double);
+        let svg = render_charts(&[&single, &double], &tempo_changes(), &Options::default());
+        assert_eq!(svg.matches("<g ").count(), 2);
+        assert!(svg.contains(">Double Basic</text>"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape(r#"<a & "b">"#), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+}
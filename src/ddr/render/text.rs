@@ -0,0 +1,278 @@
+//! Rendering of step charts as text for terminals.
+//!
+//! Every measure is split into as many lines as its finest snap needs (at least
+//! [`Options::min_snap`]). The first line of a measure shows its number and a measure line, lines
+//! on a beat show dots in empty lanes.
+//!
+//! [`Options::min_snap`]: struct.Options.html#structfield.min_snap
+use super::{annotations, column_glyphs};
+use crate::ddr::analysis::quantization::{common_snap, Snap, DEFAULT_TOLERANCE};
+use crate::ddr::analysis::{rows, shocks};
+use crate::ddr::ssq::{Chart, TempoChanges};
+
+const BEATS_PER_MEASURE: f32 = 4.0;
+const FREEZE_BODY: char = '║';
+const FREEZE_TAIL: char = '╨';
+const SHOCK: char = '×';
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Color arrows by their snap (with ANSI escape codes)
+    pub color: bool,
+    /// Measures are split into at least as many lines as this snap has notes
+    pub min_snap: Snap,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            color: false,
+            min_snap: Snap::Fourth,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Cell {
+    Empty,
+    Arrow(Snap),
+    FreezeBody,
+    FreezeTail,
+    Shock,
+}
+
+/// Returns the ANSI color code of arrows with this snap (similar to the note colors of DDR).
+fn ansi_color(snap: Snap) -> u8 {
+    match snap {
+        Snap::Fourth => 31,
+        Snap::Eighth => 34,
+        Snap::Twelfth => 35,
+        Snap::Sixteenth => 33,
+        Snap::TwentyFourth => 95,
+        Snap::ThirtySecond => 36,
+        Snap::SixtyFourth => 32,
+        Snap::HundredNinetySecond => 37,
+    }
+}
+
+fn measure_of(beats: f32) -> i32 {
+    ((beats + DEFAULT_TOLERANCE) / BEATS_PER_MEASURE).floor() as i32
+}
+
+/// Renders a chart with one line per row of its measures.
+pub fn render(chart: &Chart, tempo_changes: &TempoChanges, options: &Options) -> String {
+    let glyphs = column_glyphs(chart.difficulty.style);
+    let rows = rows(chart);
+    let shocks = shocks(chart);
+    // (column, start, end)
+    let freezes: Vec<(usize, f32, f32)> = rows
+        .iter()
+        .flat_map(|row| {
+            row.freeze_ends
+                .iter()
+                .enumerate()
+                .filter_map(move |(column, end)| end.map(|end| (column, row.beats, end)))
+        })
+        .collect();
+
+    let positions: Vec<f32> = rows
+        .iter()
+        .map(|row| row.beats)
+        .chain(freezes.iter().map(|(_, _, end)| *end))
+        .chain(shocks.iter().copied())
+        .collect();
+    let (first, last) = match (
+        positions.iter().copied().map(measure_of).min(),
+        positions.iter().copied().map(measure_of).max(),
+    ) {
+        (Some(first), Some(last)) => (first.min(0), last),
+        _ => return String::new(),
+    };
+    let annotations: Vec<(f32, String)> = annotations(tempo_changes)
+        .into_iter()
+        .filter(|(beats, _)| measure_of(*beats) <= last)
+        .map(|(beats, annotation)| (beats, annotation.to_string()))
+        .collect();
+
+    let mut output = String::new();
+    for measure in first..=last {
+        let in_measure = |beats: &f32| measure_of(*beats) == measure;
+        let measure_positions: Vec<f32> = positions
+            .iter()
+            .copied()
+            .chain(annotations.iter().map(|(beats, _)| *beats))
+            .filter(in_measure)
+            .collect();
+        let snap = common_snap(&measure_positions).max(options.min_snap);
+        let start = measure as f32 * BEATS_PER_MEASURE;
+        let lines = usize::from(snap.per_measure());
+        let line_of = |beats: f32| {
+            (((beats - start) / snap.beats()).round().max(0.0) as usize).min(lines - 1)
+        };
+
+        let mut cells = vec![vec![Cell::Empty; glyphs.len()]; lines];
+        for (line, line_cells) in cells.iter_mut().enumerate() {
+            let beats = start + line as f32 * snap.beats();
+            for (column, freeze_start, end) in &freezes {
+                if (beats - end).abs() < snap.beats() / 2.0 {
+                    line_cells[*column] = Cell::FreezeTail;
+                } else if *freeze_start < beats && beats < *end {
+                    line_cells[*column] = Cell::FreezeBody;
+                }
+            }
+        }
+        for beats in shocks.iter().filter(|beats| in_measure(beats)) {
+            for cell in &mut cells[line_of(*beats)] {
+                if *cell == Cell::Empty {
+                    *cell = Cell::Shock;
+                }
+            }
+        }
+        for row in rows.iter().filter(|row| in_measure(&row.beats)) {
+            for (column, active) in row.columns.iter().enumerate() {
+                if *active {
+                    cells[line_of(row.beats)][column] = Cell::Arrow(Snap::of(row.beats));
+                }
+            }
+        }
+
+        for (line, line_cells) in cells.iter().enumerate() {
+            let empty = if line == 0 {
+                '─'
+            } else if (line as f32 * snap.beats()) % 1.0 == 0.0 {
+                '·'
+            } else {
+                ' '
+            };
+
+            let mut lanes = String::new();
+            for (column, cell) in line_cells.iter().enumerate() {
+                // gap between the pads
+                if glyphs.len() == 8 && column == 4 {
+                    lanes.push(if line == 0 { '─' } else { ' ' });
+                }
+                match cell {
+                    Cell::Empty => lanes.push(empty),
+                    Cell::Arrow(snap) if options.color => lanes.push_str(&format!(
+                        "\x1b[{}m{}\x1b[0m",
+                        ansi_color(*snap),
+                        glyphs[column]
+                    )),
+                    Cell::Arrow(_) => lanes.push(glyphs[column]),
+                    Cell::FreezeBody => lanes.push(FREEZE_BODY),
+                    Cell::FreezeTail => lanes.push(FREEZE_TAIL),
+                    Cell::Shock => lanes.push(SHOCK),
+                }
+            }
+
+            let margin = if line == 0 {
+                (measure + 1).to_string()
+            } else {
+                String::new()
+            };
+            let notes: Vec<&str> = annotations
+                .iter()
+                .filter(|(position, _)| in_measure(position) && line_of(*position) == line)
+                .map(|(_, annotation)| annotation.as_str())
+                .collect();
+            output.push_str(&format!("{:>4} │{}│", margin, lanes));
+            if !notes.is_empty() {
+                output.push(' ');
+                output.push_str(&notes.join(", "));
+            }
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Level, Row, Step, Style, TempoChange, TempoChangeKind};
+
+    fn tempo_changes() -> TempoChanges {
+        TempoChanges {
+            ticks_per_second: 150,
+            first_measure: 0,
+            first_tick: 0,
+            changes: vec![
+                TempoChange {
+                    start_ms: 0.0,
+                    start_beats: 0.0,
+                    end_beats: 8.0,
+                    beat_length: 400.0,
+                    kind: TempoChangeKind::Scroll,
+                },
+                TempoChange {
+                    start_ms: 3200.0,
+                    start_beats: 8.0,
+                    end_beats: 8.0,
+                    beat_length: f64::INFINITY,
+                    kind: TempoChangeKind::Stop,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let chart = Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: vec![
+                Step::Step {
+                    beats: 0.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Step {
+                    beats: 1.5,
+                    row: Row::new(0b1010, Style::Single),
+                },
+                Step::Freeze {
+                    start: 4.0,
+                    end: 6.0,
+                    row: Row::new(0b0100, Style::Single),
+                },
+                Step::Shock { beats: 7.0 },
+            ],
+        };
+        let expected = "   1 │←───│ 150 BPM
+     │    │
+     │····│
+     │ ↓ →│
+     │····│
+     │    │
+     │····│
+     │    │
+   2 │──↑─│
+     │··║·│
+     │··╨·│
+     │××××│
+";
+        assert_eq!(
+            render(&chart, &tempo_changes(), &Options::default()),
+            expected
+        );
+
+        let colored = render(
+            &chart,
+            &tempo_changes(),
+            &Options {
+                color: true,
+                min_snap: Snap::Eighth,
+            },
+        );
+        assert!(colored.starts_with("   1 │\x1b[31m←\x1b[0m───│ 150 BPM\n"));
+        assert!(colored.contains("│ \x1b[34m↓\x1b[0m \x1b[34m→\x1b[0m│"));
+    }
+
+    #[test]
+    fn test_render_empty() {
+        let chart = Chart {
+            difficulty: Level::new(Style::Double, 1).unwrap(),
+            steps: Vec::new(),
+        };
+        assert_eq!(render(&chart, &tempo_changes(), &Options::default()), "");
+    }
+}
@@ -0,0 +1,517 @@
+//! Judgement and scoring of played charts.
+//!
+//! Input events are matched to the steps of a chart like DDR A does it: every row is judged by
+//! the worst timing of its arrows, freezes have to be held until their end and shocks must not be
+//! stepped on. The results are summarized in a [`Score`] with the combo, EX score and money
+//! score.
+//!
+//! [`Score`]: struct.Score.html
+use std::cmp::Ordering;
+
+use log::warn;
+
+use crate::ddr::analysis::{rows, shocks};
+use crate::ddr::ssq::{Chart, TimingMap};
+
+/// Maximum money score
+pub const MAX_MONEY_SCORE: u32 = 1_000_000;
+
+/// Timing windows of the judgements (in milliseconds before or after the step)
+const JUDGEMENT_WINDOWS: [(Judgement, f64); 4] = [
+    (Judgement::Marvelous, 16.7),
+    (Judgement::Perfect, 33.3),
+    (Judgement::Great, 91.7),
+    (Judgement::Good, 141.7),
+];
+/// Time (in milliseconds) a freeze may be released before its end
+const FREEZE_RELEASE_WINDOW: f64 = 250.0;
+/// Time (in milliseconds) before and after a shock in which panels must not be stepped on
+const SHOCK_WINDOW: f64 = 91.7;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Judgement {
+    Marvelous,
+    Perfect,
+    Great,
+    Good,
+    Miss,
+}
+
+impl Judgement {
+    /// Returns the judgement for a step that was hit `offset` milliseconds too early or late.
+    pub fn from_offset(offset: f64) -> Self {
+        JUDGEMENT_WINDOWS
+            .iter()
+            .find(|(_, window)| offset.abs() <= *window)
+            .map_or(Self::Miss, |(judgement, _)| *judgement)
+    }
+
+    fn ex_score(self) -> u32 {
+        match self {
+            Self::Marvelous => 3,
+            Self::Perfect => 2,
+            Self::Great => 1,
+            Self::Good | Self::Miss => 0,
+        }
+    }
+
+    /// Goods keep the combo, only misses break it
+    fn keeps_combo(self) -> bool {
+        self != Self::Miss
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputKind {
+    Press,
+    Release,
+}
+
+/// Press or release of a panel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputEvent {
+    /// Time in milliseconds
+    pub time: f64,
+    /// Column of the panel (as in [`Row`])
+    ///
+    /// [`Row`]: ../ssq/enum.Row.html
+    pub column: u8,
+    pub kind: InputKind,
+}
+
+/// Number of judgements of each kind.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Judgements {
+    pub marvelous: u32,
+    pub perfect: u32,
+    pub great: u32,
+    pub good: u32,
+    pub miss: u32,
+    pub freeze_ok: u32,
+    pub freeze_ng: u32,
+    pub shock_ok: u32,
+    pub shock_ng: u32,
+}
+
+impl Judgements {
+    fn add(&mut self, judgement: Judgement) {
+        match judgement {
+            Judgement::Marvelous => self.marvelous += 1,
+            Judgement::Perfect => self.perfect += 1,
+            Judgement::Great => self.great += 1,
+            Judgement::Good => self.good += 1,
+            Judgement::Miss => self.miss += 1,
+        }
+    }
+
+    /// Number of judged steps, freezes and shocks.
+    pub fn notes(&self) -> u32 {
+        self.marvelous
+            + self.perfect
+            + self.great
+            + self.good
+            + self.miss
+            + self.freeze_ok
+            + self.freeze_ng
+            + self.shock_ok
+            + self.shock_ng
+    }
+
+    pub fn ex_score(&self) -> u32 {
+        Judgement::Marvelous.ex_score() * self.marvelous
+            + Judgement::Perfect.ex_score() * self.perfect
+            + Judgement::Great.ex_score() * self.great
+            + 3 * (self.freeze_ok + self.shock_ok)
+    }
+
+    /// Returns the money score (up to 1,000,000) as calculated since DDR SuperNOVA 2.
+    ///
+    /// Every note is worth the same share of the maximum score, perfects, greats and goods lose
+    /// some of it. The result is rounded down to a multiple of 10.
+    pub fn money_score(&self) -> u32 {
+        let notes = self.notes();
+        if notes == 0 {
+            return 0;
+        }
+        let step = f64::from(MAX_MONEY_SCORE) / f64::from(notes);
+        let score = step * f64::from(self.marvelous + self.freeze_ok + self.shock_ok)
+            + (step - 10.0) * f64::from(self.perfect)
+            + (step * 0.6 - 10.0) * f64::from(self.great)
+            + (step * 0.2 - 10.0) * f64::from(self.good);
+        // small epsilon, so rounding errors don’t cost 10 points
+        ((score + 1e-6).max(0.0) as u32 / 10) * 10
+    }
+}
+
+/// Result of playing a chart.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Score {
+    pub judgements: Judgements,
+    pub max_combo: u32,
+    pub ex_score: u32,
+    pub money_score: u32,
+}
+
+/// What happened at one position of the chart, in the order they affect the combo
+enum Event {
+    Row(Judgement),
+    Freeze(bool),
+    Shock(bool),
+}
+
+/// Judges the input events for a chart and returns the score.
+///
+/// Steps that can not be placed in time with `timing_map` are ignored.
+pub fn score(chart: &Chart, timing_map: &TimingMap, input: &[InputEvent]) -> Score {
+    let columns = usize::from(chart.difficulty.style.columns());
+    let mut events: Vec<(f64, Event)> = Vec::new();
+
+    let mut presses: Vec<Vec<f64>> = vec![Vec::new(); columns];
+    let mut sorted_input = input.to_vec();
+    sorted_input.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(Ordering::Equal));
+    for event in &sorted_input {
+        if event.kind == InputKind::Press {
+            if let Some(presses) = presses.get_mut(usize::from(event.column)) {
+                presses.push(event.time);
+            }
+        }
+    }
+    // index of the first press of each column that was not used for a step
+    let mut next_press = vec![0; columns];
+
+    for row in rows(chart) {
+        let time = match timing_map.time_at(row.beats) {
+            Some(time) => time,
+            None => {
+                warn!(
+                    "Could not get time of row at beat {}, ignoring it",
+                    row.beats
+                );
+                continue;
+            }
+        };
+
+        let mut judgement = Judgement::Marvelous;
+        let mut hits: Vec<Option<f64>> = vec![None; columns];
+
+        for (column, active) in row.columns.iter().enumerate() {
+            if !active {
+                continue;
+            }
+            let presses = &presses[column];
+            // skip presses that are too early for this step
+            while next_press[column] < presses.len()
+                && Judgement::from_offset(presses[next_press[column]] - time) == Judgement::Miss
+                && presses[next_press[column]] < time
+            {
+                next_press[column] += 1;
+            }
+            match presses.get(next_press[column]) {
+                Some(press) if Judgement::from_offset(press - time) != Judgement::Miss => {
+                    judgement = judgement.max(Judgement::from_offset(press - time));
+                    hits[column] = Some(*press);
+                    next_press[column] += 1;
+                }
+                _ => judgement = Judgement::Miss,
+            }
+        }
+        events.push((time, Event::Row(judgement)));
+
+        for (column, end) in row.freeze_ends.iter().enumerate() {
+            let end = match end.and_then(|end| timing_map.time_at(end)) {
+                Some(end) => end,
+                None => continue,
+            };
+            let held = judgement != Judgement::Miss
+                && match hits[column] {
+                    Some(press) => {
+                        let release = sorted_input.iter().find(|event| {
+                            usize::from(event.column) == column
+                                && event.kind == InputKind::Release
+                                && event.time >= press
+                        });
+                        !matches!(release, Some(release) if release.time < end - FREEZE_RELEASE_WINDOW)
+                    }
+                    None => false,
+                };
+            events.push((end, Event::Freeze(held)));
+        }
+    }
+
+    for beats in shocks(chart) {
+        let time = match timing_map.time_at(beats) {
+            Some(time) => time,
+            None => {
+                warn!("Could not get time of shock at beat {}, ignoring it", beats);
+                continue;
+            }
+        };
+        let stepped_on = (0..columns).any(|column| is_pressed(&sorted_input, column, time))
+            || sorted_input.iter().any(|event| {
+                event.kind == InputKind::Press && (event.time - time).abs() <= SHOCK_WINDOW
+            });
+        events.push((time, Event::Shock(!stepped_on)));
+    }
+
+    events.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+    let mut judgements = Judgements::default();
+    let mut combo = 0;
+    let mut max_combo = 0;
+    for (_, event) in events {
+        let keeps_combo = match event {
+            Event::Row(judgement) => {
+                judgements.add(judgement);
+                judgement.keeps_combo()
+            }
+            Event::Freeze(ok) => {
+                if ok {
+                    judgements.freeze_ok += 1;
+                } else {
+                    judgements.freeze_ng += 1;
+                }
+                ok
+            }
+            Event::Shock(ok) => {
+                if ok {
+                    judgements.shock_ok += 1;
+                } else {
+                    judgements.shock_ng += 1;
+                }
+                ok
+            }
+        };
+        combo = if keeps_combo { combo + 1 } else { 0 };
+        max_combo = max_combo.max(combo);
+    }
+
+    Score {
+        ex_score: judgements.ex_score(),
+        money_score: judgements.money_score(),
+        judgements,
+        max_combo,
+    }
+}
+
+/// Returns true if the panel in `column` is held down at `time`.
+fn is_pressed(input: &[InputEvent], column: usize, time: f64) -> bool {
+    input
+        .iter()
+        .rev()
+        .find(|event| usize::from(event.column) == column && event.time <= time)
+        .map(|event| event.kind)
+        == Some(InputKind::Press)
+}
+
+/// Returns the input of a perfect play: every arrow is pressed exactly in time and freezes are
+/// held until their end.
+pub fn perfect_input(chart: &Chart, timing_map: &TimingMap) -> Vec<InputEvent> {
+    let mut input = Vec::new();
+
+    for row in rows(chart) {
+        let time = match timing_map.time_at(row.beats) {
+            Some(time) => time,
+            None => continue,
+        };
+        for (column, active) in row.columns.iter().enumerate() {
+            if !active {
+                continue;
+            }
+            let release = row.freeze_ends[column]
+                .and_then(|end| timing_map.time_at(end))
+                .unwrap_or(time);
+            input.push(InputEvent {
+                time,
+                column: column as u8,
+                kind: InputKind::Press,
+            });
+            input.push(InputEvent {
+                time: release,
+                column: column as u8,
+                kind: InputKind::Release,
+            });
+        }
+    }
+
+    input.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap_or(Ordering::Equal));
+    input
+}
+
+/// Returns the score of a perfect play.
+pub fn max_score(chart: &Chart, timing_map: &TimingMap) -> Score {
+    score(chart, timing_map, &perfect_input(chart, timing_map))
+}
+
+/// Returns the highest possible EX score of a chart.
+pub fn max_ex_score(chart: &Chart, timing_map: &TimingMap) -> u32 {
+    max_score(chart, timing_map).ex_score
+}
+
+/// Returns the combo after a full combo of a chart.
+pub fn max_combo(chart: &Chart, timing_map: &TimingMap) -> u32 {
+    max_score(chart, timing_map).max_combo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Level, Row, Step, Style, TempoChange, TempoChangeKind, TempoChanges};
+
+    /// 120 BPM for 32 beats
+    fn timing_map() -> TimingMap {
+        TimingMap::new(&TempoChanges {
+            ticks_per_second: 150,
+            first_measure: 0,
+            first_tick: 0,
+            changes: vec![TempoChange {
+                start_ms: 0.0,
+                start_beats: 0.0,
+                end_beats: 32.0,
+                beat_length: 500.0,
+                kind: TempoChangeKind::Scroll,
+            }],
+        })
+    }
+
+    /// ← at beat 1, ← → jump at beat 2, ↓ freeze from beat 3 to 5, shock at beat 6
+    fn chart() -> Chart {
+        Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: vec![
+                Step::Step {
+                    beats: 1.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Step {
+                    beats: 2.0,
+                    row: Row::new(0b1001, Style::Single),
+                },
+                Step::Freeze {
+                    start: 3.0,
+                    end: 5.0,
+                    row: Row::new(0b0010, Style::Single),
+                },
+                Step::Shock { beats: 6.0 },
+            ],
+        }
+    }
+
+    fn press(time: f64, column: u8) -> InputEvent {
+        InputEvent {
+            time,
+            column,
+            kind: InputKind::Press,
+        }
+    }
+
+    fn release(time: f64, column: u8) -> InputEvent {
+        InputEvent {
+            time,
+            column,
+            kind: InputKind::Release,
+        }
+    }
+
+    #[test]
+    fn test_judgement_from_offset() {
+        assert_eq!(Judgement::from_offset(0.0), Judgement::Marvelous);
+        assert_eq!(Judgement::from_offset(-20.0), Judgement::Perfect);
+        assert_eq!(Judgement::from_offset(50.0), Judgement::Great);
+        assert_eq!(Judgement::from_offset(-120.0), Judgement::Good);
+        assert_eq!(Judgement::from_offset(200.0), Judgement::Miss);
+    }
+
+    #[test]
+    fn test_max_score() {
+        let score = max_score(&chart(), &timing_map());
+        assert_eq!(
+            score.judgements,
+            Judgements {
+                marvelous: 3,
+                freeze_ok: 1,
+                shock_ok: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(score.max_combo, 5);
+        assert_eq!(score.ex_score, 15);
+        assert_eq!(score.money_score, MAX_MONEY_SCORE);
+        assert_eq!(max_ex_score(&chart(), &timing_map()), 15);
+        assert_eq!(max_combo(&chart(), &timing_map()), 5);
+    }
+
+    #[test]
+    fn test_no_input() {
+        let score = score(&chart(), &timing_map(), &[]);
+        assert_eq!(
+            score.judgements,
+            Judgements {
+                miss: 3,
+                freeze_ng: 1,
+                shock_ok: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(score.max_combo, 1);
+        assert_eq!(score.ex_score, 3);
+        assert_eq!(score.money_score, 200_000);
+    }
+
+    #[test]
+    fn test_score() {
+        let input = vec![
+            // perfect
+            press(520.0, 0),
+            release(600.0, 0),
+            // jump is judged by the later arrow (great)
+            press(1000.0, 0),
+            press(1050.0, 3),
+            release(1100.0, 0),
+            release(1100.0, 3),
+            // freeze released too early
+            press(1500.0, 1),
+            release(2000.0, 1),
+            // shock stepped on
+            press(3010.0, 2),
+            release(3100.0, 2),
+        ];
+        let score = score(&chart(), &timing_map(), &input);
+        assert_eq!(
+            score.judgements,
+            Judgements {
+                marvelous: 1,
+                perfect: 1,
+                great: 1,
+                freeze_ng: 1,
+                shock_ng: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(score.max_combo, 3);
+        assert_eq!(score.ex_score, 6);
+        // 200000 + 199990 + 119990
+        assert_eq!(score.money_score, 519_980);
+    }
+
+    #[test]
+    fn test_stray_presses_are_ignored() {
+        let mut input = perfect_input(&chart(), &timing_map());
+        input.push(press(100.0, 0));
+        input.push(release(150.0, 0));
+        let score = score(&chart(), &timing_map(), &input);
+        assert_eq!(score.judgements.marvelous, 3);
+    }
+
+    #[test]
+    fn test_money_score() {
+        let judgements = Judgements {
+            marvelous: 1,
+            perfect: 1,
+            good: 1,
+            ..Default::default()
+        };
+        // 333333.3 + 333323.3 + 66656.7
+        assert_eq!(judgements.money_score(), 733_310);
+        assert_eq!(Judgements::default().money_score(), 0);
+    }
+}
@@ -1,3 +1,7 @@
+pub mod timing;
+pub use timing::TimingMap;
+
+use std::collections::BTreeMap;
 use std::convert::From;
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
@@ -6,7 +10,7 @@ use std::io::prelude::*;
 use std::io::Cursor;
 use std::num;
 
-use byteorder::{ReadBytesExt, LE};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use derive_more::Deref;
 use log::{debug, info, trace, warn};
 use thiserror::Error;
@@ -20,10 +24,14 @@ const MEASURE_LENGTH: f32 = 4096.0;
 pub enum Error {
     #[error("not enough freeze data was found")]
     NotEnoughFreezeData,
-    #[error("invalid player count {0} (valid options: 1, 2)")]
-    InvalidPlayerCount(u8),
-    #[error("invalid difficulty {0} (valid options: 4, 1, 2, 3, 6)")]
+    #[error("invalid style {0:#x} (valid options for the lower nibble: 4, 6, 8)")]
+    InvalidStyle(u8),
+    #[error("invalid difficulty {0} (valid options: 4, 1, 2, 3, 6, 5)")]
     InvalidDifficulty(u8),
+    #[error("tempo chunk has zero ticks per second")]
+    ZeroTicksPerSecond,
+    #[error("step at beat {0} can not be represented in SSQ")]
+    UnrepresentableStep(f32),
     #[error(transparent)]
     IOError(#[from] io::Error),
     #[error(transparent)]
@@ -38,6 +46,34 @@ fn measure_to_beats(measure: u32) -> f32 {
     4.0 * measure as f32 / MEASURE_LENGTH
 }
 
+/// Convert beats to time offset (inverse of [`measure_to_beats`])
+///
+/// [`measure_to_beats`]: fn.measure_to_beats.html
+fn beats_to_measure(beats: f32) -> i32 {
+    (beats * MEASURE_LENGTH / 4.0).round() as i32
+}
+
+/// Writes a chunk with header (length, type and parameter) to `buf` and pads its data to a
+/// multiple of four bytes.
+fn write_chunk(
+    buf: &mut Vec<u8>,
+    chunk_type: u16,
+    parameter: u16,
+    data: &[u8],
+) -> Result<(), Error> {
+    let padding = (4 - data.len() % 4) % 4;
+    // length including i32 and 2 × i16
+    let length: i32 = (data.len() + padding + 8).try_into()?;
+
+    buf.write_i32::<LE>(length)?;
+    buf.write_u16::<LE>(chunk_type)?;
+    buf.write_u16::<LE>(parameter)?;
+    buf.extend_from_slice(data);
+    buf.extend(vec![0; padding]);
+
+    Ok(())
+}
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct PlayerRow {
     pub left: bool,
@@ -46,6 +82,13 @@ pub struct PlayerRow {
     pub right: bool,
 }
 
+impl PlayerRow {
+    /// Moves every column to the column in `permutation` (as in `Into<Vec<bool>>`).
+    pub fn permute(&self, permutation: &[usize]) -> Self {
+        PlayerRow::from(u8::from(&Row::Single(self.clone()).permute(permutation)))
+    }
+}
+
 impl From<u8> for PlayerRow {
     fn from(byte: u8) -> Self {
         let columns = utils::byte_to_bitarray(byte);
@@ -64,6 +107,14 @@ impl Into<Vec<bool>> for PlayerRow {
     }
 }
 
+impl From<&PlayerRow> for u8 {
+    fn from(row: &PlayerRow) -> Self {
+        utils::bitarray_to_byte([
+            row.left, row.down, row.up, row.right, false, false, false, false,
+        ])
+    }
+}
+
 impl fmt::Display for PlayerRow {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -77,9 +128,84 @@ impl fmt::Display for PlayerRow {
     }
 }
 
+/// Row of the six panel layout used by Solo charts.
+///
+/// The lower four bits are the same as in [`PlayerRow`], the upper left and upper right panels
+/// use the following two bits.
+///
+/// [`PlayerRow`]: struct.PlayerRow.html
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SoloRow {
+    pub left: bool,
+    pub up_left: bool,
+    pub down: bool,
+    pub up: bool,
+    pub up_right: bool,
+    pub right: bool,
+}
+
+impl From<u8> for SoloRow {
+    fn from(byte: u8) -> Self {
+        let columns = utils::byte_to_bitarray(byte);
+        SoloRow {
+            left: columns[0],
+            down: columns[1],
+            up: columns[2],
+            right: columns[3],
+            up_left: columns[4],
+            up_right: columns[5],
+        }
+    }
+}
+
+/// Columns are ordered from left to right as they are arranged on the pad.
+impl Into<Vec<bool>> for SoloRow {
+    fn into(self) -> Vec<bool> {
+        vec![
+            self.left,
+            self.up_left,
+            self.down,
+            self.up,
+            self.up_right,
+            self.right,
+        ]
+    }
+}
+
+impl From<&SoloRow> for u8 {
+    fn from(row: &SoloRow) -> Self {
+        utils::bitarray_to_byte([
+            row.left,
+            row.down,
+            row.up,
+            row.right,
+            row.up_left,
+            row.up_right,
+            false,
+            false,
+        ])
+    }
+}
+
+impl fmt::Display for SoloRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}{}{}",
+            if self.left { "←" } else { " " },
+            if self.up_left { "↖" } else { " " },
+            if self.down { "↓" } else { " " },
+            if self.up { "↑" } else { " " },
+            if self.up_right { "↗" } else { " " },
+            if self.right { "→" } else { " " },
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Row {
     Single(PlayerRow),
+    Solo(SoloRow),
     Double(PlayerRow, PlayerRow),
 }
 
@@ -87,6 +213,7 @@ impl Into<Vec<bool>> for Row {
     fn into(self) -> Vec<bool> {
         match self {
             Self::Single(row) => row.into(),
+            Self::Solo(row) => row.into(),
             Self::Double(row1, row2) => {
                 let mut row: Vec<bool> = Vec::new();
                 row.append(&mut row1.into());
@@ -97,35 +224,87 @@ impl Into<Vec<bool>> for Row {
     }
 }
 
+impl From<&Row> for u8 {
+    fn from(row: &Row) -> Self {
+        match row {
+            Row::Single(row) => u8::from(row),
+            Row::Solo(row) => u8::from(row),
+            Row::Double(row1, row2) => u8::from(row1) | (u8::from(row2) << 4),
+        }
+    }
+}
+
 impl fmt::Display for Row {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let player_rows = match self {
-            Self::Single(player_row) => vec![player_row],
-            Self::Double(player_row1, player_row2) => vec![player_row1, player_row2],
-        };
-        write!(f, "{}", utils::join_display_values(player_rows, " "))
+        match self {
+            Self::Single(player_row) => write!(f, "{}", player_row),
+            Self::Solo(solo_row) => write!(f, "{}", solo_row),
+            Self::Double(player_row1, player_row2) => {
+                write!(f, "{} {}", player_row1, player_row2)
+            }
+        }
     }
 }
 
 impl Row {
-    fn new(byte: u8, players: u8) -> Result<Self, Error> {
-        match players {
-            1 => Ok(Self::Single(PlayerRow::from(byte))),
-            2 => Ok(Self::Double(
-                PlayerRow::from(byte),
-                PlayerRow::from(byte >> 4),
-            )),
-            _ => Err(Error::InvalidPlayerCount(players)),
+    /// Creates a row from the step byte of an SSQ file.
+    pub fn new(byte: u8, style: Style) -> Self {
+        match style {
+            Style::Single => Self::Single(PlayerRow::from(byte)),
+            Style::Solo => Self::Solo(SoloRow::from(byte)),
+            Style::Double | Style::Couple | Style::Battle => {
+                Self::Double(PlayerRow::from(byte), PlayerRow::from(byte >> 4))
+            }
         }
     }
 
-    fn count_active(&self) -> u8 {
+    /// Creates a row from its columns in the order of `Into<Vec<bool>>` (inverse of it).
+    ///
+    /// Missing columns are inactive and additional columns are ignored.
+    pub fn from_columns(columns: &[bool], style: Style) -> Self {
+        // solo columns are ordered as on the pad, not as in the step byte
+        let bits: &[u8] = match style {
+            Style::Solo => &[0, 4, 1, 2, 5, 3],
+            _ => &[0, 1, 2, 3, 4, 5, 6, 7],
+        };
+        let byte = columns
+            .iter()
+            .zip(bits.iter())
+            .filter(|(active, _)| **active)
+            .fold(0, |byte, (_, bit)| byte | (1 << bit));
+        Self::new(byte, style)
+    }
+
+    /// Moves every column to the column in `permutation` (as in `Into<Vec<bool>>`).
+    ///
+    /// Columns without an entry in `permutation` stay in place.
+    pub fn permute(&self, permutation: &[usize]) -> Self {
+        let style = match self {
+            Self::Single(_) => Style::Single,
+            Self::Solo(_) => Style::Solo,
+            Self::Double(..) => Style::Double,
+        };
+        let columns: Vec<bool> = self.clone().into();
+        let mut permuted = vec![false; columns.len()];
+        for (column, active) in columns.iter().enumerate() {
+            let target = permutation.get(column).copied().unwrap_or(column);
+            if let Some(permuted) = permuted.get_mut(target) {
+                *permuted |= *active;
+            }
+        }
+        Self::from_columns(&permuted, style)
+    }
+
+    pub fn count_active(&self) -> u8 {
         let mut rows = Vec::<bool>::new();
 
         match self {
             Self::Single(row) => {
                 rows.append(&mut row.clone().into());
             }
+            Self::Solo(row) => {
+                rows.append(&mut row.clone().into());
+            }
             Self::Double(player1, player2) => {
                 rows.append(&mut player1.clone().into());
                 rows.append(&mut player2.clone().into());
@@ -140,11 +319,14 @@ impl Row {
             (Self::Single(self_row), Self::Single(other_row)) => {
                 vec![(self_row.into(), other_row.into())]
             }
+            (Self::Solo(self_row), Self::Solo(other_row)) => {
+                vec![(self_row.into(), other_row.into())]
+            }
             (Self::Double(self_row1, self_row2), Self::Double(other_row1, other_row2)) => vec![
                 (self_row1.into(), other_row1.into()),
                 (self_row2.into(), other_row2.into()),
             ],
-            // rows with different player count can’t intersect
+            // rows with different styles can’t intersect
             _ => vec![],
         };
 
@@ -162,14 +344,43 @@ impl Row {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct TempoChange {
-    pub start_ms: u32,
+    /// Start time in milliseconds (not rounded, it is as precise as the tick values allow)
+    pub start_ms: f64,
     pub start_beats: f32,
     pub end_beats: f32,
-    pub beat_length: f32,
+    /// Length of one beat in milliseconds (infinite for stops and delays)
+    pub beat_length: f64,
+    pub kind: TempoChangeKind,
 }
 
-#[derive(Clone, Debug, Deref, PartialEq)]
-pub struct TempoChanges(pub Vec<TempoChange>);
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TempoChangeKind {
+    /// The chart scrolls with the tempo given by the beat length
+    Scroll,
+    /// The chart does not scroll; steps at its position are hit at the start of the stop
+    Stop,
+    /// The chart does not scroll; steps at its position are hit at the end of the delay
+    Delay,
+}
+
+/// A stop or delay with its position and duration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stop {
+    pub beats: f32,
+    pub duration_ms: f64,
+}
+
+#[derive(Clone, Debug, Default, Deref, PartialEq)]
+pub struct TempoChanges {
+    /// Resolution of the tick values stored in the tempo chunk
+    pub ticks_per_second: u32,
+    /// Measure of the first entry of the tempo chunk (the tempo changes start at beat 0)
+    pub first_measure: i32,
+    /// Tick of the first entry of the tempo chunk (the tempo changes start at 0 ms)
+    pub first_tick: i32,
+    #[deref]
+    pub changes: Vec<TempoChange>,
+}
 
 impl TempoChanges {
     fn parse(ticks_per_second: u32, data: &[u8]) -> Result<Self, Error> {
@@ -179,41 +390,158 @@ impl TempoChanges {
         let measure = cursor.read_n_i32(count)?;
         let tempo_data = cursor.read_n_i32(count)?;
 
+        if count > 1 && ticks_per_second == 0 {
+            return Err(Error::ZeroTicksPerSecond);
+        }
+
+        let ticks_to_ms = |ticks: i64| ticks as f64 * 1000.0 / f64::from(ticks_per_second);
+
         let mut entries = Vec::new();
 
-        let mut elapsed_ms = 0;
-        let mut elapsed_beats = 0.0;
+        // the elapsed time and beats are accumulated as integers, so no rounding errors add up
+        let mut elapsed_ticks: i64 = 0;
+        let mut elapsed_measure: u32 = 0;
         for i in 1..count {
-            let delta_measure: u32 = (measure[i] - measure[i - 1]).abs().try_into()?;
-            let delta_ticks: u32 = (tempo_data[i] - tempo_data[i - 1]).abs().try_into()?;
+            let delta_measure: u32 = (i64::from(measure[i]) - i64::from(measure[i - 1]))
+                .abs()
+                .try_into()?;
+            let delta_ticks = (i64::from(tempo_data[i]) - i64::from(tempo_data[i - 1])).abs();
 
-            let length_ms = 1000 * delta_ticks / ticks_per_second;
+            let start_ms = ticks_to_ms(elapsed_ticks);
+            let length_ms = ticks_to_ms(elapsed_ticks + delta_ticks) - start_ms;
             let length_beats = measure_to_beats(delta_measure);
 
-            let beat_length = length_ms as f32 / length_beats;
+            let beat_length = length_ms / f64::from(length_beats);
 
             let entry = TempoChange {
-                start_ms: elapsed_ms,
-                start_beats: elapsed_beats,
-                end_beats: elapsed_beats + length_beats,
+                start_ms,
+                start_beats: measure_to_beats(elapsed_measure),
+                end_beats: measure_to_beats(elapsed_measure + delta_measure),
                 beat_length,
+                // SSQ does not have delays
+                kind: if delta_measure == 0 {
+                    TempoChangeKind::Stop
+                } else {
+                    TempoChangeKind::Scroll
+                },
             };
 
             entries.push(entry);
 
-            elapsed_ms += length_ms;
-            elapsed_beats += length_beats;
+            elapsed_ticks += delta_ticks;
+            elapsed_measure += delta_measure;
         }
 
-        Ok(Self(entries))
+        Ok(Self {
+            ticks_per_second,
+            first_measure: measure.first().copied().unwrap_or(0),
+            first_tick: tempo_data.first().copied().unwrap_or(0),
+            changes: entries,
+        })
+    }
+
+    /// Returns all tempo changes of `kind` as [`Stop`]s.
+    ///
+    /// The duration of a stop or delay at the end is unknown, it is returned as 0.
+    ///
+    /// [`Stop`]: struct.Stop.html
+    fn pauses(&self, kind: TempoChangeKind) -> Vec<Stop> {
+        self.iter()
+            .enumerate()
+            .filter(|(_, tempo_change)| tempo_change.kind == kind)
+            .map(|(i, tempo_change)| Stop {
+                beats: tempo_change.start_beats,
+                duration_ms: self
+                    .get(i + 1)
+                    .map(|next| next.start_ms - tempo_change.start_ms)
+                    .unwrap_or(0.0),
+            })
+            .collect()
+    }
+
+    /// Returns the stops (steps at their position are hit before they start).
+    pub fn stops(&self) -> Vec<Stop> {
+        self.pauses(TempoChangeKind::Stop)
+    }
+
+    /// Returns the delays (steps at their position are hit after they end).
+    pub fn delays(&self) -> Vec<Stop> {
+        self.pauses(TempoChangeKind::Delay)
+    }
+
+    /// Serializes the tempo changes to the data of a tempo chunk.
+    ///
+    /// The measures and ticks are written relative to [`first_measure`] and [`first_tick`]. SSQ
+    /// does not support delays, they are written as stops.
+    ///
+    /// [`first_measure`]: #structfield.first_measure
+    /// [`first_tick`]: #structfield.first_tick
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        if !self.delays().is_empty() {
+            warn!("SSQ does not support delays, writing them as stops");
+        }
+
+        let ms_to_ticks = |ms: f64| (ms * f64::from(self.ticks_per_second) / 1000.0).round() as i64;
+
+        let mut measures = Vec::new();
+        let mut ticks = Vec::new();
+
+        for (i, tempo_change) in self.iter().enumerate() {
+            let end_ms = match self.get(i + 1) {
+                Some(next) => next.start_ms,
+                None if tempo_change.beat_length.is_finite() => {
+                    tempo_change.start_ms
+                        + tempo_change.beat_length
+                            * f64::from(tempo_change.end_beats - tempo_change.start_beats)
+                }
+                None => tempo_change.start_ms,
+            };
+
+            let measure = |beats| self.first_measure + beats_to_measure(beats);
+            let tick = |ms| i32::try_from(i64::from(self.first_tick) + ms_to_ticks(ms));
+            if i == 0 {
+                measures.push(measure(tempo_change.start_beats));
+                ticks.push(tick(tempo_change.start_ms)?);
+            }
+            measures.push(measure(tempo_change.end_beats));
+            ticks.push(tick(end_ms)?);
+        }
+
+        let mut data = Vec::new();
+        data.write_u32::<LE>(measures.len().try_into()?)?;
+        for measure in measures {
+            data.write_i32::<LE>(measure)?;
+        }
+        for tick in ticks {
+            data.write_i32::<LE>(tick)?;
+        }
+
+        Ok(data)
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Step {
-    Step { beats: f32, row: Row },
-    Freeze { start: f32, end: f32, row: Row },
-    Shock { beats: f32 },
+    Step {
+        beats: f32,
+        row: Row,
+    },
+    Freeze {
+        start: f32,
+        end: f32,
+        row: Row,
+    },
+    Shock {
+        beats: f32,
+    },
+    /// Extra step with a type that is not known (everything except freeze ends).
+    ///
+    /// It is kept as is, so it can be inspected and written back.
+    Extra {
+        beats: f32,
+        columns: u8,
+        kind: u8,
+    },
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -233,66 +561,95 @@ impl Chart {
         let mut steps = vec![0; count];
         cursor.read_exact(&mut steps)?;
 
-        let mut freeze_data = Vec::new();
-        cursor.read_to_end(&mut freeze_data)?;
-        // freeze data can be padded with zeroes
-        let mut freeze_data = freeze_data.iter().skip_while(|x| **x == 0).copied();
+        // extra data is aligned to four bytes
+        let padding = ((4 - cursor.position() % 4) % 4) as usize;
+        let mut extra_data = Vec::new();
+        cursor.read_to_end(&mut extra_data)?;
+        // every extra step has two bytes: columns and type
+        let mut extra_data = extra_data
+            .get(padding..)
+            .unwrap_or(&[])
+            .chunks_exact(2)
+            .map(|extra| (extra[0], extra[1]));
 
         let mut parsed_steps = Vec::new();
 
-        // indices of (normal) steps that start a freeze (they are not needed after processing all
-        // steps as they are already included in the freezes)
-        let mut freeze_steps = Vec::new();
+        // indices of (normal) steps that start a freeze and the columns used by the freezes (they
+        // are not needed after processing all steps as they are already included in the freezes)
+        let mut freeze_steps: BTreeMap<usize, u8> = BTreeMap::new();
 
         for step in 0..count {
             let beats = measure_to_beats(measures[step].try_into()?);
 
             // check if either all eight bits are set (shock for double) or the first four (shock for
-            // single)
-            if steps[step] == 0xff || steps[step] == 0xf {
+            // single), solo uses all six bits
+            let is_shock = match difficulty.style {
+                Style::Solo => steps[step] == 0x3f,
+                _ => steps[step] == 0xff || steps[step] == 0xf,
+            };
+            if is_shock {
                 // shock
                 trace!("Shock arrow at {}", beats);
 
                 parsed_steps.push(Step::Shock { beats });
             } else if steps[step] == 0x00 {
                 // extra data
-                let columns = freeze_data.next().ok_or(Error::NotEnoughFreezeData)?;
-                let extra_type = freeze_data.next().ok_or(Error::NotEnoughFreezeData)?;
+                let (columns, extra_type) = extra_data.next().ok_or(Error::NotEnoughFreezeData)?;
 
                 if extra_type == 1 {
                     // freeze end (start is the last normal step in that column)
-                    trace!("Freeze arrow at {}", beats);
-
-                    let row = Row::new(columns, difficulty.players)?;
-                    if row.count_active() != 1 {
-                        warn!("Found freeze with not exactly one column, which is not implemented, skipping");
-                        continue;
+                    let row = Row::new(columns, difficulty.style);
+                    trace!("Freeze arrow at {} ({} columns)", beats, row.count_active());
+
+                    // every column can have a different start step, columns with the same start
+                    // step are combined into one freeze
+                    let mut freezes: Vec<(usize, u8)> = Vec::new();
+                    let columns = u8::from(&row);
+                    for column in (0..8).map(|i| 1 << i).filter(|bit| columns & bit != 0) {
+                        let column_row = Row::new(column, difficulty.style);
+                        match Self::find_last(&parsed_steps, &column_row) {
+                            Some(last_step) => {
+                                match freezes.iter_mut().find(|(step, _)| *step == last_step) {
+                                    Some((_, freeze_columns)) => *freeze_columns |= column,
+                                    None => freezes.push((last_step, column)),
+                                }
+                            }
+                            None => {
+                                warn!(
+                                    "Could not find previous step for freeze, adding normal step"
+                                );
+                                parsed_steps.push(Step::Step {
+                                    beats,
+                                    row: column_row,
+                                });
+                            }
+                        }
                     }
 
-                    match Self::find_last(parsed_steps.clone(), &row) {
-                        Some(last_step) => {
-                            parsed_steps.push(Step::Freeze {
-                                start: if let Step::Step { beats, .. } = parsed_steps[last_step] {
-                                    beats
-                                } else {
-                                    unreachable!()
-                                },
-                                end: beats,
-                                row,
-                            });
-
-                            freeze_steps.push(last_step);
-                        }
-                        None => {
-                            warn!("Could not find previous step for freeze, adding normal step");
-                            parsed_steps.push(Step::Step { beats, row });
-                        }
+                    for (last_step, columns) in freezes {
+                        parsed_steps.push(Step::Freeze {
+                            start: if let Step::Step { beats, .. } = parsed_steps[last_step] {
+                                beats
+                            } else {
+                                unreachable!()
+                            },
+                            end: beats,
+                            row: Row::new(columns, difficulty.style),
+                        });
+
+                        *freeze_steps.entry(last_step).or_insert(0) |= columns;
                     }
                 } else {
                     debug!(
-                        "Encountered unknown extra step with type {}, ignoring",
-                        extra_type
+                        "Encountered unknown extra step with type {} at {}, keeping as is",
+                        extra_type, beats
                     );
+
+                    parsed_steps.push(Step::Extra {
+                        beats,
+                        columns,
+                        kind: extra_type,
+                    });
                 }
             } else {
                 // normal step
@@ -300,15 +657,22 @@ impl Chart {
 
                 parsed_steps.push(Step::Step {
                     beats,
-                    row: Row::new(steps[step], difficulty.players)?,
+                    row: Row::new(steps[step], difficulty.style),
                 });
             }
         }
 
-        // remove steps that start a freeze
-        freeze_steps.dedup();
-        for i in freeze_steps.iter().rev() {
-            parsed_steps.remove(*i);
+        // remove the columns of steps that start a freeze (and the steps when no column is left)
+        for (i, columns) in freeze_steps.iter().rev() {
+            let remaining = match &parsed_steps[*i] {
+                Step::Step { row, .. } => u8::from(row) & !columns,
+                _ => unreachable!(),
+            };
+            if remaining == 0 {
+                parsed_steps.remove(*i);
+            } else if let Step::Step { row, .. } = &mut parsed_steps[*i] {
+                *row = Row::new(remaining, difficulty.style);
+            }
         }
 
         debug!("Parsed {} steps", parsed_steps.len());
@@ -319,7 +683,99 @@ impl Chart {
         })
     }
 
-    fn find_last(steps: Vec<Step>, row: &Row) -> Option<usize> {
+    /// Serializes the chart to the data of a step chunk.
+    ///
+    /// Freezes are split into their start step and an extra step with the freeze end. Steps and
+    /// freeze starts at the same position are combined into one row, since the parser takes every
+    /// row as a separate step. Entries at the same position keep their order in [`steps`].
+    ///
+    /// # Errors
+    ///
+    /// Rows without active columns and rows with all columns active (also after combining) can
+    /// not be distinguished from extra steps and shocks, for these [`UnrepresentableStep`] is
+    /// returned.
+    ///
+    /// [`steps`]: #structfield.steps
+    /// [`UnrepresentableStep`]: enum.Error.html#variant.UnrepresentableStep
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let shock = match self.difficulty.style {
+            Style::Single => 0xf,
+            Style::Solo => 0x3f,
+            Style::Double | Style::Couple | Style::Battle => 0xff,
+        };
+        let row_to_byte = |beats: f32, row: &Row| match u8::from(row) {
+            0x00 => Err(Error::UnrepresentableStep(beats)),
+            byte if byte == shock => Err(Error::UnrepresentableStep(beats)),
+            byte => Ok(byte),
+        };
+
+        // (measure, step byte, extra data)
+        let mut entries: Vec<(i32, u8, Option<[u8; 2]>)> = Vec::new();
+        // index of the row entry of every measure, rows at the same measure are combined into it
+        let mut row_entries: BTreeMap<i32, usize> = BTreeMap::new();
+        let mut push_row = |entries: &mut Vec<(i32, u8, Option<[u8; 2]>)>, beats: f32, byte: u8| {
+            let measure = beats_to_measure(beats);
+            match row_entries.get(&measure) {
+                Some(i) => {
+                    entries[*i].1 |= byte;
+                    if entries[*i].1 == shock {
+                        return Err(Error::UnrepresentableStep(beats));
+                    }
+                }
+                None => {
+                    row_entries.insert(measure, entries.len());
+                    entries.push((measure, byte, None));
+                }
+            }
+            Ok(())
+        };
+
+        for step in &self.steps {
+            match step {
+                Step::Step { beats, row } => {
+                    push_row(&mut entries, *beats, row_to_byte(*beats, row)?)?;
+                }
+                Step::Freeze { start, end, row } => {
+                    let columns = row_to_byte(*start, row)?;
+                    push_row(&mut entries, *start, columns)?;
+                    entries.push((beats_to_measure(*end), 0x00, Some([columns, 1])));
+                }
+                Step::Shock { beats } => {
+                    entries.push((beats_to_measure(*beats), shock, None));
+                }
+                Step::Extra {
+                    beats,
+                    columns,
+                    kind,
+                } => {
+                    entries.push((beats_to_measure(*beats), 0x00, Some([*columns, *kind])));
+                }
+            }
+        }
+
+        // stable sort, so the order of entries at the same position is retained
+        entries.sort_by_key(|(measure, ..)| *measure);
+
+        let mut data = Vec::new();
+        data.write_u32::<LE>(entries.len().try_into()?)?;
+        for (measure, ..) in &entries {
+            data.write_i32::<LE>(*measure)?;
+        }
+        for (_, step, _) in &entries {
+            data.write_u8(*step)?;
+        }
+        // extra data is aligned to four bytes
+        data.extend(vec![0; (4 - data.len() % 4) % 4]);
+        for (.., extra) in &entries {
+            if let Some(extra) = extra {
+                data.extend_from_slice(extra);
+            }
+        }
+
+        Ok(data)
+    }
+
+    fn find_last(steps: &[Step], row: &Row) -> Option<usize> {
         for i in (0..steps.len()).rev() {
             if let Step::Step { row: step_row, .. } = &steps[i] {
                 if step_row.clone().intersects(row.clone()) {
@@ -332,17 +788,98 @@ impl Chart {
     }
 }
 
+/// Play style of a chart, it determines the number of panels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    Single,
+    /// Six panels (with additional upper left and upper right panels)
+    Solo,
+    Double,
+    /// Two players, each on their own pad, dancing one shared chart
+    Couple,
+    /// Two players, each on their own pad, playing against each other
+    Battle,
+}
+
+impl Style {
+    /// Returns the number of panels (and therefore columns) of the style.
+    pub fn columns(&self) -> u8 {
+        match self {
+            Self::Single => 4,
+            Self::Solo => 6,
+            Self::Double | Self::Couple | Self::Battle => 8,
+        }
+    }
+
+    /// Returns the number of players needed for the style.
+    pub fn players(&self) -> u8 {
+        match self {
+            Self::Single | Self::Solo | Self::Double => 1,
+            Self::Couple | Self::Battle => 2,
+        }
+    }
+}
+
+/// Maps the lower byte of the step chunk parameter to `Style`.
+///
+/// The lower nibble is the number of panels, the upper nibble distinguishes two player modes
+/// that use both pads (2 → Couple, 3 → Battle) and is ignored otherwise. The whole byte is kept
+/// in [`Level.style_byte`], so it is written back unchanged.
+///
+/// [`Level.style_byte`]: struct.Level.html#structfield.style_byte
+impl TryFrom<u8> for Style {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Error> {
+        match (value >> 4, value & 0xF) {
+            (2, 8) => Ok(Self::Couple),
+            (3, 8) => Ok(Self::Battle),
+            (_, 4) => Ok(Self::Single),
+            (_, 6) => Ok(Self::Solo),
+            (_, 8) => Ok(Self::Double),
+            _ => Err(Error::InvalidStyle(value)),
+        }
+    }
+}
+
+impl From<Style> for u8 {
+    fn from(style: Style) -> Self {
+        match style {
+            Style::Couple => 0x28,
+            Style::Battle => 0x38,
+            _ => style.columns(),
+        }
+    }
+}
+
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 /// Metadata about a level.
 ///
 /// It does not store the level visible to the user. However it can – when povided with
 /// [`ddr::musicdb::Entry.diff_lv`] – return the user visible level with the [`to_value`] method.
 ///
+/// Levels are equal if their style and difficulty are equal, the style byte is ignored.
+///
 /// [`ddr::musicdb::Entry.diff_lv`]: ../musicdb/struct.Entry.html#structfield.diff_lv
 /// [`to_value`]: #method.to_value
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct Level {
-    pub players: u8,
+    pub style: Style,
     pub difficulty: u8,
+    /// Style byte of the parsed step chunk, it is written back as long as it matches `style`
+    /// (`None` for new levels)
+    pub style_byte: Option<u8>,
+}
+
+impl PartialEq for Level {
+    fn eq(&self, other: &Self) -> bool {
+        self.style == other.style && self.difficulty == other.difficulty
+    }
 }
 
 impl Level {
@@ -350,25 +887,22 @@ impl Level {
     ///
     /// # Errors
     ///
-    /// This checks if the players and difficulty and valid, otherwise it returns
-    /// [`InvalidPlayerCount`] or [`InvalidDifficulty`].
+    /// This checks if the difficulty is valid, otherwise it returns [`InvalidDifficulty`].
     ///
-    /// [`InvalidPlayerCount`]: enum.Error.html#variant.InvalidPlayerCount
     /// [`InvalidDifficulty`]: enum.Error.html#variant.InvalidDifficulty
-    pub fn new(players: u8, ssq_difficulty: u8) -> Result<Self, Error> {
-        if ![1, 2].contains(&players) {
-            return Err(Error::InvalidPlayerCount(players));
-        }
+    pub fn new(style: Style, ssq_difficulty: u8) -> Result<Self, Error> {
         let difficulty = Self::ssq_to_ordered(&ssq_difficulty)?;
         Ok(Level {
-            players,
+            style,
             difficulty,
+            style_byte: None,
         })
     }
 
     /// The SSQ file stores the difficulty in a format, where the lower numeric value does not mean
     /// the easier level. This function maps the values from the SSQ file to ordered values (lower
-    /// values → easier, higher values → harder)
+    /// values → easier, higher values → harder). Edit charts have no place in this order and are
+    /// mapped after Challenge.
     ///
     /// # Errors
     ///
@@ -382,22 +916,56 @@ impl Level {
             2 => 2,
             3 => 3,
             6 => 4,
+            5 => 5,
             _ => return Err(Error::InvalidDifficulty(*ssq_difficulty)),
         })
     }
 
+    /// Inverse of [`ssq_to_ordered`].
+    ///
+    /// [`ssq_to_ordered`]: #method.ssq_to_ordered
+    fn ordered_to_ssq(difficulty: u8) -> Result<u8, Error> {
+        Ok(match difficulty {
+            0 => 4,
+            1 => 1,
+            2 => 2,
+            3 => 3,
+            4 => 6,
+            5 => 5,
+            _ => return Err(Error::InvalidDifficulty(difficulty)),
+        })
+    }
+
     /// Returns the difficulty as `f32` where 0.0 is the easiest and 1.0 is the hardest.
+    ///
+    /// Edit charts are treated like Challenge charts.
     pub fn relative_difficulty(&self) -> f32 {
-        f32::from(self.difficulty) / 4.0
+        f32::from(self.difficulty.min(4)) / 4.0
+    }
+
+    /// Returns true if the chart is an edit chart.
+    pub fn is_edit(&self) -> bool {
+        self.difficulty == 5
     }
 
     /// Returns the user visible level value for difficulty from [`ddr::musicdb::Entry.diff_lv`].
     ///
+    /// Returns `None` if the musicdb has no level for the style (Solo, Couple, Battle) or
+    /// difficulty (Edit).
+    ///
     /// [`ddr::musicdb::Entry.diff_lv`]: ../musicdb/struct.Entry.html#structfield.diff_lv
-    pub fn to_value(&self, levels: &[u8]) -> u8 {
-        let index: usize = (self.difficulty + (self.players - 1) * 5).into();
+    pub fn to_value(&self, levels: &[u8]) -> Option<u8> {
+        if self.is_edit() {
+            return None;
+        }
+        let offset = match self.style {
+            Style::Single => 0,
+            Style::Double => 5,
+            Style::Solo | Style::Couple | Style::Battle => return None,
+        };
+        let index: usize = (self.difficulty + offset).into();
 
-        levels[index]
+        levels.get(index).copied()
     }
 }
 
@@ -405,35 +973,192 @@ impl TryFrom<u16> for Level {
     type Error = Error;
 
     fn try_from(parameter: u16) -> Result<Self, Error> {
-        let players = (parameter & 0xF) as u8 / 4;
+        let style_byte = (parameter & 0xFF) as u8;
         let difficulty = ((parameter & 0xFF00) >> 8) as u8;
-        Self::new(players, difficulty)
+        Ok(Self {
+            style_byte: Some(style_byte),
+            ..Self::new(Style::try_from(style_byte)?, difficulty)?
+        })
+    }
+}
+
+impl TryFrom<&Level> for u16 {
+    type Error = Error;
+
+    fn try_from(level: &Level) -> Result<Self, Error> {
+        let difficulty = Level::ordered_to_ssq(level.difficulty)?;
+        let style = match level.style_byte {
+            Some(byte) if Style::try_from(byte).ok() == Some(level.style) => byte,
+            _ => u8::from(level.style),
+        };
+        Ok(u16::from(style) | (u16::from(difficulty) << 8))
     }
 }
 
 impl fmt::Display for Level {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let players = match self.players {
-            1 => "Single",
-            2 => "Double",
-            _ => "Unknown Number of Players",
-        };
         let difficulty = match self.difficulty {
             0 => "Beginner",
             1 => "Basic",
             2 => "Difficult",
             3 => "Expert",
             4 => "Challenge",
+            5 => "Edit",
             _ => "Unknown Difficulty",
         };
-        write!(f, "{} {}", players, difficulty)
+        write!(f, "{} {}", self.style, difficulty)
     }
 }
 
+/// Parses the entries of event chunks, which consist of a count, the positions and one `u16`
+/// value per entry.
+///
+/// Returns `None` when the data does not have this layout.
+fn parse_events(data: &[u8]) -> Result<Option<Vec<(f32, u16)>>, Error> {
+    if data.len() < 4 {
+        return Ok(None);
+    }
+
+    let mut cursor = Cursor::new(data);
+
+    let count: usize = cursor.read_u32::<LE>()?.try_into()?;
+    match count.checked_mul(6) {
+        Some(length) if 4 + length <= data.len() => {}
+        _ => return Ok(None),
+    }
+    let measures = cursor.read_n_i32(count)?;
+
+    let mut entries = Vec::new();
+    for measure in measures {
+        let value = cursor.read_u16::<LE>()?;
+        match u32::try_from(measure) {
+            Ok(measure) => entries.push((measure_to_beats(measure), value)),
+            Err(_) => return Ok(None),
+        }
+    }
+
+    Ok(Some(entries))
+}
+
+/// Inverse of [`parse_events`].
+///
+/// [`parse_events`]: fn.parse_events.html
+fn events_to_bytes(entries: &[(f32, u16)]) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    data.write_u32::<LE>(entries.len().try_into()?)?;
+    for (beats, _) in entries {
+        data.write_i32::<LE>(beats_to_measure(*beats))?;
+    }
+    for (_, value) in entries {
+        data.write_u16::<LE>(*value)?;
+    }
+
+    Ok(data)
+}
+
+/// Change of the background animation.
+///
+/// The meaning of the animation values is not known.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackgroundChange {
+    pub beats: f32,
+    pub animation: u16,
+}
+
+/// Content of a background change chunk (type 2).
+#[derive(Clone, Debug, Deref, PartialEq)]
+pub struct BackgroundChanges {
+    pub parameter: u16,
+    #[deref]
+    pub changes: Vec<BackgroundChange>,
+}
+
+impl BackgroundChanges {
+    fn parse(data: &[u8], parameter: u16) -> Result<Option<Self>, Error> {
+        Ok(parse_events(data)?.map(|entries| Self {
+            parameter,
+            changes: entries
+                .into_iter()
+                .map(|(beats, animation)| BackgroundChange { beats, animation })
+                .collect(),
+        }))
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        events_to_bytes(
+            &self
+                .iter()
+                .map(|change| (change.beats, change.animation))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+/// Cue for the cabinet lamps.
+///
+/// The lamps are stored as a bit field, the meaning of the individual bits is not known.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightCue {
+    pub beats: f32,
+    pub lamps: u16,
+}
+
+/// Content of a light cue chunk (type 4).
+#[derive(Clone, Debug, Deref, PartialEq)]
+pub struct LightCues {
+    pub parameter: u16,
+    #[deref]
+    pub cues: Vec<LightCue>,
+}
+
+impl LightCues {
+    fn parse(data: &[u8], parameter: u16) -> Result<Option<Self>, Error> {
+        Ok(parse_events(data)?.map(|entries| Self {
+            parameter,
+            cues: entries
+                .into_iter()
+                .map(|(beats, lamps)| LightCue { beats, lamps })
+                .collect(),
+        }))
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        events_to_bytes(
+            &self
+                .iter()
+                .map(|cue| (cue.beats, cue.lamps))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+/// Chunk that could not be decoded, it is stored as is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownChunk {
+    pub chunk_type: u16,
+    pub parameter: u16,
+    pub data: Vec<u8>,
+}
+
+/// Kind of a chunk, used to keep the order of the chunks of a parsed file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChunkKind {
+    Tempo,
+    Background,
+    Steps,
+    Lights,
+    Unknown,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct SSQ {
     pub tempo_changes: TempoChanges,
     pub charts: Vec<Chart>,
+    pub background_changes: Vec<BackgroundChanges>,
+    pub light_cues: Vec<LightCues>,
+    pub unknown_chunks: Vec<UnknownChunk>,
+    /// Order of the chunks in the parsed file (empty for new files)
+    pub chunk_order: Vec<ChunkKind>,
 }
 
 impl SSQ {
@@ -441,8 +1166,12 @@ impl SSQ {
         let mut cursor = Cursor::new(data);
 
         let mut ssq = Self {
-            tempo_changes: TempoChanges(Vec::new()),
+            tempo_changes: TempoChanges::default(),
             charts: Vec::new(),
+            background_changes: Vec::new(),
+            light_cues: Vec::new(),
+            unknown_chunks: Vec::new(),
+            chunk_order: Vec::new(),
         };
 
         loop {
@@ -463,18 +1192,34 @@ impl SSQ {
                 1 => {
                     debug!("Parsing tempo changes (ticks/s: {})", parameter);
                     ssq.tempo_changes = TempoChanges::parse(parameter.into(), &data)?;
+                    ssq.chunk_order.push(ChunkKind::Tempo);
                 }
                 3 => {
                     debug!("Parsing step chunk ({})", Level::try_from(parameter)?);
-                    ssq.charts.push(Chart::parse(&data, parameter)?)
+                    ssq.charts.push(Chart::parse(&data, parameter)?);
+                    ssq.chunk_order.push(ChunkKind::Steps);
                 }
-                _ => {
-                    debug!(
-                        "Found extra chunk (type {}, length {})",
-                        chunk_type,
-                        data.len()
-                    );
+                2 => {
+                    debug!("Parsing background changes");
+                    match BackgroundChanges::parse(&data, parameter)? {
+                        Some(background_changes) => {
+                            ssq.background_changes.push(background_changes);
+                            ssq.chunk_order.push(ChunkKind::Background);
+                        }
+                        None => ssq.push_unknown_chunk(chunk_type, parameter, data),
+                    }
+                }
+                4 => {
+                    debug!("Parsing light cues");
+                    match LightCues::parse(&data, parameter)? {
+                        Some(light_cues) => {
+                            ssq.light_cues.push(light_cues);
+                            ssq.chunk_order.push(ChunkKind::Lights);
+                        }
+                        None => ssq.push_unknown_chunk(chunk_type, parameter, data),
+                    }
                 }
+                _ => ssq.push_unknown_chunk(chunk_type, parameter, data),
             };
         }
 
@@ -482,28 +1227,134 @@ impl SSQ {
 
         Ok(ssq)
     }
+
+    fn push_unknown_chunk(&mut self, chunk_type: u16, parameter: u16, data: Vec<u8>) {
+        debug!(
+            "Found unknown chunk (type {}, length {}), keeping as is",
+            chunk_type,
+            data.len()
+        );
+        self.unknown_chunks.push(UnknownChunk {
+            chunk_type,
+            parameter,
+            data,
+        });
+        self.chunk_order.push(ChunkKind::Unknown);
+    }
+
+    /// Serializes the SSQ to its binary chunk format.
+    ///
+    /// The chunks are written in the order of [`chunk_order`], so parsed files keep their order.
+    /// Chunks that are not part of it (e.g. charts added by a conversion) are written after it in
+    /// the order tempo changes, background changes, step charts, light cues and unknown chunks,
+    /// except for the tempo changes, which are written first if no tempo chunk was parsed.
+    /// Parsing the result with [`parse`] returns an equal `SSQ`.
+    ///
+    /// [`chunk_order`]: #structfield.chunk_order
+    /// [`parse`]: #method.parse
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut order = self.chunk_order.clone();
+        if !order.contains(&ChunkKind::Tempo) {
+            order.insert(0, ChunkKind::Tempo);
+        }
+        for (kind, len) in &[
+            (ChunkKind::Background, self.background_changes.len()),
+            (ChunkKind::Steps, self.charts.len()),
+            (ChunkKind::Lights, self.light_cues.len()),
+            (ChunkKind::Unknown, self.unknown_chunks.len()),
+        ] {
+            let recorded = order.iter().filter(|other| *other == kind).count();
+            order.extend((recorded..*len).map(|_| *kind));
+        }
+
+        let mut buf = Vec::new();
+        let mut tempo_written = false;
+        let mut background_changes = self.background_changes.iter();
+        let mut charts = self.charts.iter();
+        let mut light_cues = self.light_cues.iter();
+        let mut unknown_chunks = self.unknown_chunks.iter();
+
+        // entries of removed chunks have nothing left to write and are skipped
+        for kind in order {
+            match kind {
+                ChunkKind::Tempo if !tempo_written => {
+                    debug!(
+                        "Writing tempo changes (ticks/s: {})",
+                        self.tempo_changes.ticks_per_second
+                    );
+                    write_chunk(
+                        &mut buf,
+                        1,
+                        self.tempo_changes.ticks_per_second.try_into()?,
+                        &self.tempo_changes.to_bytes()?,
+                    )?;
+                    tempo_written = true;
+                }
+                ChunkKind::Tempo => {}
+                ChunkKind::Background => {
+                    if let Some(background_changes) = background_changes.next() {
+                        debug!("Writing background changes");
+                        write_chunk(
+                            &mut buf,
+                            2,
+                            background_changes.parameter,
+                            &background_changes.to_bytes()?,
+                        )?;
+                    }
+                }
+                ChunkKind::Steps => {
+                    if let Some(chart) = charts.next() {
+                        debug!("Writing step chunk ({})", chart.difficulty);
+                        write_chunk(
+                            &mut buf,
+                            3,
+                            u16::try_from(&chart.difficulty)?,
+                            &chart.to_bytes()?,
+                        )?;
+                    }
+                }
+                ChunkKind::Lights => {
+                    if let Some(light_cues) = light_cues.next() {
+                        debug!("Writing light cues");
+                        write_chunk(&mut buf, 4, light_cues.parameter, &light_cues.to_bytes()?)?;
+                    }
+                }
+                ChunkKind::Unknown => {
+                    if let Some(chunk) = unknown_chunks.next() {
+                        debug!("Writing unknown chunk (type {})", chunk.chunk_type);
+                        write_chunk(&mut buf, chunk.chunk_type, chunk.parameter, &chunk.data)?;
+                    }
+                }
+            }
+        }
+
+        // chunk with length 0 terminates the file
+        buf.write_i32::<LE>(0)?;
+
+        info!("Wrote {} charts", self.charts.len());
+
+        Ok(buf)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use quickcheck::TestResult;
 
     #[quickcheck]
-    fn test_row_new(columns: u8, players: u8) -> TestResult {
-        match (Row::new(columns, players), players) {
-            (Ok(Row::Single(..)), 1) => TestResult::passed(),
-            (Ok(Row::Double(..)), 2) => TestResult::passed(),
-            (Ok(Row::Single(..)), 2) | (Ok(Row::Double(..)), 1) => TestResult::failed(),
-            (row, _) => TestResult::from_bool(row.is_err()),
-        }
+    fn test_row_new(columns: u8) -> bool {
+        matches!(Row::new(columns, Style::Single), Row::Single(..))
+            && matches!(Row::new(columns, Style::Solo), Row::Solo(..))
+            && matches!(Row::new(columns, Style::Double), Row::Double(..))
     }
 
     #[test]
     fn test_row_into_vec() {
-        let single: Vec<bool> = Row::new(0b0010, 1).unwrap().into();
-        let double: Vec<bool> = Row::new(0b01000000, 2).unwrap().into();
+        let single: Vec<bool> = Row::new(0b0010, Style::Single).into();
+        let solo: Vec<bool> = Row::new(0b010000, Style::Solo).into();
+        let double: Vec<bool> = Row::new(0b01000000, Style::Double).into();
         assert_eq!(single, vec![false, true, false, false]);
+        assert_eq!(solo, vec![false, true, false, false, false, false]);
         assert_eq!(
             double,
             vec![false, false, false, false, false, false, true, false]
@@ -511,15 +1362,15 @@ mod tests {
     }
 
     #[quickcheck]
-    fn test_row_intersects_itself(columns: u8, players: bool) -> bool {
-        let players = u8::from(players) + 1;
+    fn test_row_intersects_itself(columns: u8, double: bool) -> bool {
+        let style = if double { Style::Double } else { Style::Single };
         // only use first 4 bits for single player
-        let columns = if players == 1 {
+        let columns = if style == Style::Single {
             columns & 0b1111
         } else {
             columns
         };
-        let row = Row::new(columns, players).unwrap();
+        let row = Row::new(columns, style);
         let intersects = row.clone().intersects(row);
         // Rows don’t intersect when all columns are unset
         if columns == 0 {
@@ -532,61 +1383,93 @@ mod tests {
     #[test]
     fn test_row_intersects() {
         let values = [
-            (0b0010, 0b0011, 1, true),
-            (0b1000, 0b1000, 1, true),
-            (0b1111, 0b0100, 1, true),
-            (0b0000, 0b1111, 1, false),
-            (0b1001, 0b0110, 1, false),
-            (0b01010101, 0b11111111, 2, true),
-            (0b10000000, 0b10101010, 2, true),
-            (0b00100000, 0b00100000, 2, true),
-            (0b00000000, 0b11111111, 2, false),
-            (0b01100000, 0b10000100, 2, false),
+            (0b0010, 0b0011, Style::Single, true),
+            (0b1000, 0b1000, Style::Single, true),
+            (0b1111, 0b0100, Style::Single, true),
+            (0b0000, 0b1111, Style::Single, false),
+            (0b1001, 0b0110, Style::Single, false),
+            (0b01010101, 0b11111111, Style::Double, true),
+            (0b10000000, 0b10101010, Style::Double, true),
+            (0b00100000, 0b00100000, Style::Double, true),
+            (0b00000000, 0b11111111, Style::Double, false),
+            (0b01100000, 0b10000100, Style::Double, false),
+            (0b110000, 0b010000, Style::Solo, true),
+            (0b001111, 0b110000, Style::Solo, false),
         ];
-        for (a, b, players, intersects) in values.iter() {
-            let row_a = Row::new(*a, *players).unwrap();
-            let row_b = Row::new(*b, *players).unwrap();
+        for (a, b, style, intersects) in values.iter() {
+            let row_a = Row::new(*a, *style);
+            let row_b = Row::new(*b, *style);
             assert_eq!(row_a.intersects(row_b), *intersects);
         }
-        assert!(!Row::new(0b1111, 1)
-            .unwrap()
-            .intersects(Row::new(0b1111, 2).unwrap()));
+        assert!(!Row::new(0b1111, Style::Single).intersects(Row::new(0b1111, Style::Double)));
+        assert!(!Row::new(0b1111, Style::Single).intersects(Row::new(0b1111, Style::Solo)));
+    }
+
+    #[quickcheck]
+    fn test_row_from_columns(columns: u8, solo: bool) -> bool {
+        let style = if solo { Style::Solo } else { Style::Double };
+        let columns = if style == Style::Solo {
+            columns & 0b111111
+        } else {
+            columns
+        };
+        let row = Row::new(columns, style);
+        let vec: Vec<bool> = row.clone().into();
+        Row::from_columns(&vec, style) == row
+    }
+
+    #[test]
+    fn test_row_permute() {
+        // ← ↓ ↑ becomes ↓ ↑ → (↑ has no entry and stays in place)
+        assert_eq!(
+            Row::new(0b0111, Style::Single).permute(&[3, 1]),
+            Row::new(0b1110, Style::Single)
+        );
+        assert_eq!(
+            Row::new(0b0000_0001, Style::Double).permute(&[7, 6, 5, 4, 3, 2, 1, 0]),
+            Row::new(0b1000_0000, Style::Double)
+        );
+        assert_eq!(
+            PlayerRow::from(0b0001).permute(&[3, 2, 1, 0]),
+            PlayerRow::from(0b1000)
+        );
     }
 
     #[test]
     fn test_row_count_active() {
         let values = [
-            (0b0000, 0, 1),
-            (0b0010, 1, 1),
-            (0b1010, 2, 1),
-            (0b1111, 4, 1),
-            (0b00000000, 0, 2),
-            (0b00001000, 1, 2),
-            (0b00000110, 2, 2),
-            (0b11111111, 8, 2),
+            (0b0000, 0, Style::Single),
+            (0b0010, 1, Style::Single),
+            (0b1010, 2, Style::Single),
+            (0b1111, 4, Style::Single),
+            (0b00000000, 0, Style::Double),
+            (0b00001000, 1, Style::Double),
+            (0b00000110, 2, Style::Double),
+            (0b11111111, 8, Style::Double),
+            (0b110000, 2, Style::Solo),
+            (0b111111, 6, Style::Solo),
         ];
-        for (data, active, players) in values.iter() {
-            assert_eq!(Row::new(*data, *players).unwrap().count_active(), *active);
+        for (data, active, style) in values.iter() {
+            assert_eq!(Row::new(*data, *style).count_active(), *active);
         }
     }
 
     #[test]
     fn test_row_display() {
         let values = [
-            (0b0000, "    ", 1),
-            (0b0010, " ↓  ", 1),
-            (0b1100, "  ↑→", 1),
-            (0b1111, "←↓↑→", 1),
-            (0b00000000, "         ", 2),
-            (0b00001000, "   →     ", 2),
-            (0b00000110, " ↓↑      ", 2),
-            (0b11111111, "←↓↑→ ←↓↑→", 2),
+            (0b0000, "    ", Style::Single),
+            (0b0010, " ↓  ", Style::Single),
+            (0b1100, "  ↑→", Style::Single),
+            (0b1111, "←↓↑→", Style::Single),
+            (0b00000000, "         ", Style::Double),
+            (0b00001000, "   →     ", Style::Double),
+            (0b00000110, " ↓↑      ", Style::Double),
+            (0b11111111, "←↓↑→ ←↓↑→", Style::Double),
+            (0b010001, "←↖    ", Style::Solo),
+            (0b101010, "  ↓ ↗→", Style::Solo),
         ];
-        for (data, displayed, players) in values.iter() {
-            assert_eq!(
-                format!("{}", Row::new(*data, *players).unwrap()),
-                *displayed
-            );
+        for (data, displayed, style) in values.iter() {
+            assert_eq!(format!("{}", Row::new(*data, *style)), *displayed);
         }
     }
 
@@ -645,54 +1528,70 @@ mod tests {
 
     #[test]
     fn test_difficuly_new() {
-        assert_eq!(Level::new(2, 4).unwrap().difficulty, 0);
-        assert!(Level::new(0, 0).is_err());
-        assert!(Level::new(4, 0).is_err());
-        assert!(Level::new(1, 5).is_err());
-        assert!(Level::new(1, 8).is_err());
+        assert_eq!(Level::new(Style::Double, 4).unwrap().difficulty, 0);
+        assert!(Level::new(Style::Single, 0).is_err());
+        assert!(Level::new(Style::Solo, 7).is_err());
+        assert_eq!(Level::new(Style::Single, 5).unwrap().difficulty, 5);
+        assert!(Level::new(Style::Single, 8).is_err());
     }
 
     #[test]
     fn test_difficulty_relative() {
         let mut difficulty = Level {
-            players: 1,
+            style: Style::Single,
             difficulty: 0,
+            style_byte: None,
         };
         assert_eq!(difficulty.relative_difficulty(), 0.0);
         difficulty.difficulty = 2;
         assert_eq!(difficulty.relative_difficulty(), 0.5);
         difficulty.difficulty = 4;
         assert_eq!(difficulty.relative_difficulty(), 1.0);
+        difficulty.difficulty = 5;
+        assert_eq!(difficulty.relative_difficulty(), 1.0);
     }
 
     #[test]
     fn test_difficuly_from_u16() {
-        let values = [(0b0000010000001000, 0, 2), (0b0000011000000100, 4, 1)];
-        for (data, difficulty, players) in values.iter() {
+        let values = [
+            (0b0000010000001000, 0, Style::Double),
+            (0b0000011000000100, 4, Style::Single),
+            (0b0000000100000110, 1, Style::Solo),
+            (0b0000000100011000, 1, Style::Double),
+            (0b0000001000101000, 2, Style::Couple),
+            (0b0000001100111000, 3, Style::Battle),
+            (0b0000010100010100, 5, Style::Single),
+        ];
+        for (data, difficulty, style) in values.iter() {
             let diff = Level::try_from(*data).unwrap();
-            assert_eq!(diff.players, *players);
+            assert_eq!(diff.style, *style);
             assert_eq!(diff.difficulty, *difficulty);
         }
+        assert!(Level::try_from(0b0000010000000010).is_err());
     }
 
     #[test]
     fn test_difficulty_display() {
         let values = [
-            ("Single Beginner", 0, 1),
-            ("Double Basic", 1, 2),
-            ("Single Difficult", 2, 1),
-            ("Double Expert", 3, 2),
-            ("Double Challenge", 4, 2),
-            ("Unknown Number of Players Unknown Difficulty", 5, 3),
-            ("Unknown Number of Players Unknown Difficulty", 7, 0),
+            ("Single Beginner", 0, Style::Single),
+            ("Double Basic", 1, Style::Double),
+            ("Single Difficult", 2, Style::Single),
+            ("Double Expert", 3, Style::Double),
+            ("Double Challenge", 4, Style::Double),
+            ("Solo Difficult", 2, Style::Solo),
+            ("Couple Expert", 3, Style::Couple),
+            ("Battle Basic", 1, Style::Battle),
+            ("Single Edit", 5, Style::Single),
+            ("Single Unknown Difficulty", 7, Style::Single),
         ];
-        for (displayed, difficulty, players) in values.iter() {
+        for (displayed, difficulty, style) in values.iter() {
             assert_eq!(
                 format!(
                     "{}",
                     Level {
-                        players: *players,
+                        style: *style,
                         difficulty: *difficulty,
+                        style_byte: None,
                     }
                 ),
                 *displayed
@@ -704,13 +1603,536 @@ mod tests {
     fn test_difficulty_to_value() {
         let levels: Vec<u8> = (1..=10).collect();
         let mut last_level = 0;
-        for players in [1, 2].iter() {
+        for style in [Style::Single, Style::Double].iter() {
             for difficulty in [4, 1, 2, 3, 6].iter() {
-                let difficulty = Level::new(*players, *difficulty).unwrap();
-                let level = difficulty.to_value(&levels);
+                let difficulty = Level::new(*style, *difficulty).unwrap();
+                let level = difficulty.to_value(&levels).unwrap();
                 assert!(last_level < level);
                 last_level = level;
             }
         }
+        assert_eq!(Level::new(Style::Solo, 1).unwrap().to_value(&levels), None);
+        assert_eq!(
+            Level::new(Style::Couple, 1).unwrap().to_value(&levels),
+            None
+        );
+        assert_eq!(
+            Level::new(Style::Single, 5).unwrap().to_value(&levels),
+            None
+        );
+    }
+
+    #[quickcheck]
+    fn test_row_to_byte(columns: u8, double: bool) -> bool {
+        let style = if double { Style::Double } else { Style::Single };
+        let columns = if style == Style::Single {
+            columns & 0b1111
+        } else {
+            columns
+        };
+        u8::from(&Row::new(columns, style)) == columns
+    }
+
+    #[test]
+    fn test_beats_to_measure() {
+        assert_eq!(beats_to_measure(180.5), 184832);
+        assert_eq!(beats_to_measure(0.5), 512);
+        assert_eq!(measure_to_beats(beats_to_measure(42.25) as u32), 42.25);
+    }
+
+    #[test]
+    fn test_difficulty_to_u16() {
+        let styles = [
+            Style::Single,
+            Style::Solo,
+            Style::Double,
+            Style::Couple,
+            Style::Battle,
+        ];
+        for style in styles.iter() {
+            for difficulty in [4, 1, 2, 3, 6, 5].iter() {
+                let level = Level::new(*style, *difficulty).unwrap();
+                let parameter = u16::try_from(&level).unwrap();
+                assert_eq!(Level::try_from(parameter).unwrap(), level);
+            }
+        }
+
+        // the parsed style byte is kept, unless the style was changed
+        let mut level = Level::try_from(0x0118).unwrap();
+        assert_eq!(u16::try_from(&level).unwrap(), 0x0118);
+        level.style = Style::Single;
+        assert_eq!(u16::try_from(&level).unwrap(), 0x0104);
+    }
+
+    #[rustfmt::skip]
+    const SSQ_DATA: &[u8] = &[
+        // tempo chunk (150 ticks/s)
+        0x2c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x96, 0x00,
+        0x04, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x50, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0xb0, 0x04, 0x00, 0x00, 0xfb, 0x04, 0x00, 0x00, 0x91, 0x05, 0x00, 0x00,
+        // step chunk (single basic)
+        0x2c, 0x00, 0x00, 0x00, 0x03, 0x00, 0x04, 0x01,
+        0x05, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+        0x00, 0x18, 0x00, 0x00,
+        0x01, 0x02, 0x04, 0x00, 0x0f, 0x00, 0x00, 0x00,
+        0x04, 0x01, 0x00, 0x00,
+        // end
+        0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_ssq_parse() {
+        let ssq = SSQ::parse(SSQ_DATA).unwrap();
+        assert_eq!(ssq.tempo_changes.ticks_per_second, 150);
+        assert_eq!(
+            ssq.tempo_changes.changes,
+            vec![
+                TempoChange {
+                    start_ms: 0.0,
+                    start_beats: 0.0,
+                    end_beats: 16.0,
+                    beat_length: 500.0,
+                    kind: TempoChangeKind::Scroll,
+                },
+                TempoChange {
+                    start_ms: 8000.0,
+                    start_beats: 16.0,
+                    end_beats: 16.0,
+                    beat_length: f64::INFINITY,
+                    kind: TempoChangeKind::Stop,
+                },
+                TempoChange {
+                    start_ms: 8500.0,
+                    start_beats: 16.0,
+                    end_beats: 20.0,
+                    beat_length: 250.0,
+                    kind: TempoChangeKind::Scroll,
+                },
+            ]
+        );
+        assert_eq!(
+            ssq.charts,
+            vec![Chart {
+                difficulty: Level::new(Style::Single, 1).unwrap(),
+                steps: vec![
+                    Step::Step {
+                        beats: 0.0,
+                        row: Row::new(0b0001, Style::Single)
+                    },
+                    Step::Step {
+                        beats: 1.0,
+                        row: Row::new(0b0010, Style::Single)
+                    },
+                    Step::Freeze {
+                        start: 2.0,
+                        end: 4.0,
+                        row: Row::new(0b0100, Style::Single)
+                    },
+                    Step::Shock { beats: 6.0 },
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_tempo_changes_parse_precision() {
+        // three segments that are one tick (6.67 ms) long
+        #[rustfmt::skip]
+        let data = &[
+            0x04, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00,
+        ];
+        let tempo_changes = TempoChanges::parse(150, data).unwrap();
+        let start_times: Vec<f64> = tempo_changes.iter().map(|t| t.start_ms).collect();
+        assert_eq!(start_times, vec![0.0, 1000.0 / 150.0, 2000.0 / 150.0]);
+        assert_eq!(tempo_changes.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn test_tempo_changes_first_entry() {
+        // first entry at measure 1 and tick 150, followed by one measure at 120 BPM
+        #[rustfmt::skip]
+        let data = &[
+            0x02, 0x00, 0x00, 0x00,
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00,
+            0x96, 0x00, 0x00, 0x00, 0x2c, 0x01, 0x00, 0x00,
+        ];
+        let tempo_changes = TempoChanges::parse(150, data).unwrap();
+        assert_eq!(tempo_changes.first_measure, 0x1000);
+        assert_eq!(tempo_changes.first_tick, 150);
+        assert_eq!(tempo_changes[0].start_ms, 0.0);
+        assert_eq!(tempo_changes[0].start_beats, 0.0);
+        assert_eq!(tempo_changes[0].beat_length, 250.0);
+        assert_eq!(tempo_changes.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn test_tempo_changes_stops() {
+        let tempo_changes = SSQ::parse(SSQ_DATA).unwrap().tempo_changes;
+        assert_eq!(
+            tempo_changes.stops(),
+            vec![Stop {
+                beats: 16.0,
+                duration_ms: 500.0
+            }]
+        );
+        assert_eq!(tempo_changes.delays(), vec![]);
+    }
+
+    #[test]
+    fn test_tempo_changes_parse_large_ticks() {
+        // 5 000 000 ticks (would overflow when multiplied with 1000 in u32)
+        #[rustfmt::skip]
+        let data = &[
+            0x02, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x40, 0x4b, 0x4c, 0x00,
+        ];
+        let tempo_changes = TempoChanges::parse(1000, data).unwrap();
+        assert_eq!(tempo_changes[0].beat_length, 5_000_000.0 / 4.0);
+        assert!(TempoChanges::parse(0, data).is_err());
+    }
+
+    #[test]
+    fn test_chart_parse_multi_column_freeze() {
+        #[rustfmt::skip]
+        let data = &[
+            0x05, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+            0x00, 0x0c, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+            0x0c, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00,
+            0x05, 0x01, 0x09, 0x01,
+        ];
+        let chart = Chart::parse(data, 0x0104).unwrap();
+        assert_eq!(
+            chart.steps,
+            vec![
+                // freeze only starts in one of the columns
+                Step::Step {
+                    beats: 0.0,
+                    row: Row::new(0b1000, Style::Single)
+                },
+                // freeze with columns that have different start steps
+                Step::Freeze {
+                    start: 1.0,
+                    end: 2.0,
+                    row: Row::new(0b0001, Style::Single)
+                },
+                Step::Freeze {
+                    start: 0.0,
+                    end: 2.0,
+                    row: Row::new(0b0100, Style::Single)
+                },
+                // jump freeze
+                Step::Freeze {
+                    start: 3.0,
+                    end: 4.0,
+                    row: Row::new(0b1001, Style::Single)
+                },
+            ]
+        );
+        assert_eq!(
+            Chart::parse(&chart.to_bytes().unwrap(), 0x0104).unwrap(),
+            chart
+        );
+    }
+
+    #[test]
+    fn test_chart_parse_extra() {
+        #[rustfmt::skip]
+        let data = &[
+            0x03, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+            0x02, 0x00, 0x00, 0x00,
+            0x02, 0x01, 0x10, 0x07,
+        ];
+        let chart = Chart::parse(data, 0x0104).unwrap();
+        assert_eq!(
+            chart.steps,
+            vec![
+                Step::Freeze {
+                    start: 0.0,
+                    end: 1.0,
+                    row: Row::new(0b0010, Style::Single)
+                },
+                Step::Extra {
+                    beats: 2.0,
+                    columns: 0x10,
+                    kind: 7
+                },
+            ]
+        );
+        assert_eq!(chart.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn test_chart_extra_without_columns() {
+        let chart = Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: vec![
+                Step::Step {
+                    beats: 1.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Extra {
+                    beats: 2.0,
+                    columns: 0,
+                    kind: 2,
+                },
+            ],
+        };
+        assert_eq!(
+            Chart::parse(&chart.to_bytes().unwrap(), 0x0104).unwrap(),
+            chart
+        );
+    }
+
+    #[test]
+    fn test_chart_parse_solo() {
+        #[rustfmt::skip]
+        let data = &[
+            0x03, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+            0x30, 0x0f, 0x3f, 0x00,
+        ];
+        let chart = Chart::parse(data, 0x0106).unwrap();
+        assert_eq!(chart.difficulty.style, Style::Solo);
+        assert_eq!(
+            chart.steps,
+            vec![
+                Step::Step {
+                    beats: 0.0,
+                    row: Row::new(0b110000, Style::Solo)
+                },
+                Step::Step {
+                    beats: 1.0,
+                    row: Row::new(0b001111, Style::Solo)
+                },
+                Step::Shock { beats: 2.0 },
+            ]
+        );
+        assert_eq!(chart.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn test_parse_events() {
+        #[rustfmt::skip]
+        let data = &[
+            0x02, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+            0x01, 0x04, 0x02, 0x01,
+        ];
+        assert_eq!(
+            parse_events(data).unwrap(),
+            Some(vec![(0.0, 0x0401), (4.0, 0x0102)])
+        );
+        assert_eq!(
+            events_to_bytes(&[(0.0, 0x0401), (4.0, 0x0102)]).unwrap(),
+            data
+        );
+        // too short
+        assert_eq!(parse_events(&data[..14]).unwrap(), None);
+        assert_eq!(parse_events(&[0x00, 0x00]).unwrap(), None);
+        // negative position
+        assert_eq!(
+            parse_events(&[1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0, 0]).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_ssq_to_bytes() {
+        let ssq = SSQ::parse(SSQ_DATA).unwrap();
+        assert_eq!(ssq.to_bytes().unwrap(), SSQ_DATA);
+    }
+
+    #[test]
+    fn test_ssq_style_bytes() {
+        // tempo chunk of SSQ_DATA and its step chunk (with a double shock) as Couple Expert,
+        // Battle Basic and Double Basic (with an upper nibble of 1)
+        let mut data = SSQ_DATA[..0x2c].to_vec();
+        for parameter in [[0x28, 0x03], [0x38, 0x01], [0x18, 0x01]].iter() {
+            data.extend_from_slice(&[0x2c, 0x00, 0x00, 0x00, 0x03, 0x00]);
+            data.extend_from_slice(parameter);
+            data.extend_from_slice(&SSQ_DATA[0x34..0x4c]);
+            data.extend_from_slice(&[0x01, 0x02, 0x04, 0x00, 0xff, 0x00, 0x00, 0x00]);
+            data.extend_from_slice(&SSQ_DATA[0x54..0x58]);
+        }
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+
+        let ssq = SSQ::parse(&data).unwrap();
+        assert_eq!(
+            ssq.charts
+                .iter()
+                .map(|chart| chart.difficulty.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                Level::new(Style::Couple, 3).unwrap(),
+                Level::new(Style::Battle, 1).unwrap(),
+                Level::new(Style::Double, 1).unwrap(),
+            ]
+        );
+        assert_eq!(ssq.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn test_ssq_round_trip() {
+        let ssq = SSQ {
+            tempo_changes: TempoChanges::parse(
+                1000,
+                &[
+                    3, 0, 0, 0, 0, 0, 0, 0, 0, 32, 0, 0, 0, 64, 0, 0, 0, 0, 0, 0, 0x11, 0x1d, 0, 0,
+                    0x3a, 0x38, 0, 0,
+                ],
+            )
+            .unwrap(),
+            charts: vec![Chart {
+                difficulty: Level::new(Style::Double, 6).unwrap(),
+                steps: vec![
+                    Step::Step {
+                        beats: 1.0,
+                        row: Row::new(0b10000001, Style::Double),
+                    },
+                    // freezes are placed at their end
+                    Step::Freeze {
+                        start: 0.0,
+                        end: 2.5,
+                        row: Row::new(0b00010000, Style::Double),
+                    },
+                    Step::Shock { beats: 3.0 },
+                    Step::Step {
+                        beats: 7.75,
+                        row: Row::new(0b00100100, Style::Double),
+                    },
+                ],
+            }],
+            background_changes: vec![BackgroundChanges {
+                parameter: 0,
+                changes: vec![
+                    BackgroundChange {
+                        beats: 0.0,
+                        animation: 0x0401,
+                    },
+                    BackgroundChange {
+                        beats: 4.0,
+                        animation: 0x0102,
+                    },
+                ],
+            }],
+            light_cues: vec![LightCues {
+                parameter: 0,
+                cues: vec![LightCue {
+                    beats: 2.0,
+                    lamps: 0b1010,
+                }],
+            }],
+            unknown_chunks: vec![UnknownChunk {
+                chunk_type: 9,
+                parameter: 0x1234,
+                data: vec![1, 2, 3, 4],
+            }],
+            chunk_order: Vec::new(),
+        };
+        assert_eq!(
+            SSQ::parse(&ssq.to_bytes().unwrap()).unwrap(),
+            SSQ {
+                chunk_order: vec![
+                    ChunkKind::Tempo,
+                    ChunkKind::Background,
+                    ChunkKind::Steps,
+                    ChunkKind::Lights,
+                    ChunkKind::Unknown,
+                ],
+                ..ssq
+            }
+        );
+    }
+
+    #[test]
+    fn test_ssq_chunk_order() {
+        // SSQ_DATA with an unknown chunk between two step chunks
+        let mut data = SSQ_DATA[..0x58].to_vec();
+        data.extend_from_slice(&[
+            0x0c, 0x00, 0x00, 0x00, 0x09, 0x00, 0x34, 0x12, 0x01, 0x02, 0x03, 0x04,
+        ]);
+        data.extend_from_slice(&SSQ_DATA[0x2c..]);
+
+        let ssq = SSQ::parse(&data).unwrap();
+        assert_eq!(
+            ssq.chunk_order,
+            vec![
+                ChunkKind::Tempo,
+                ChunkKind::Steps,
+                ChunkKind::Unknown,
+                ChunkKind::Steps,
+            ]
+        );
+        assert_eq!(ssq.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn test_chart_to_bytes_unrepresentable() {
+        for columns in [0b0000, 0b1111].iter() {
+            let chart = Chart {
+                difficulty: Level::new(Style::Single, 1).unwrap(),
+                steps: vec![Step::Step {
+                    beats: 1.0,
+                    row: Row::new(*columns, Style::Single),
+                }],
+            };
+            assert!(chart.to_bytes().is_err());
+        }
+
+        // combined steps at the same position would become a shock
+        let chart = Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: vec![
+                Step::Step {
+                    beats: 1.0,
+                    row: Row::new(0b0011, Style::Single),
+                },
+                Step::Step {
+                    beats: 1.0,
+                    row: Row::new(0b1100, Style::Single),
+                },
+            ],
+        };
+        assert!(chart.to_bytes().is_err());
+    }
+
+    #[test]
+    fn test_chart_to_bytes_freeze_and_step() {
+        let chart = Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: vec![
+                Step::Step {
+                    beats: 1.0,
+                    row: Row::new(0b1000, Style::Single),
+                },
+                Step::Freeze {
+                    start: 1.0,
+                    end: 2.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+            ],
+        };
+
+        let data = chart.to_bytes().unwrap();
+        #[rustfmt::skip]
+        assert_eq!(
+            data,
+            vec![
+                0x02, 0x00, 0x00, 0x00,
+                0x00, 0x04, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+                0x09, 0x00, 0x00, 0x00,
+                0x01, 0x01,
+            ]
+        );
+        assert_eq!(Chart::parse(&data, 0x0104).unwrap(), chart);
     }
 }
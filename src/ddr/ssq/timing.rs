@@ -0,0 +1,269 @@
+//! Conversion between beats and time.
+//!
+//! [`TimingMap`] is built from [`TempoChanges`] and answers lookups with a binary search, so it
+//! can be used for every step of a chart.
+//!
+//! [`TimingMap`]: struct.TimingMap.html
+//! [`TempoChanges`]: ../struct.TempoChanges.html
+use std::cmp::Ordering;
+
+use super::{TempoChangeKind, TempoChanges};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Segment {
+    start_beats: f32,
+    end_beats: f32,
+    start_ms: f64,
+    end_ms: f64,
+    kind: TempoChangeKind,
+}
+
+impl Segment {
+    /// Stops and delays are segments where the time advances, but the beats don’t
+    fn is_pause(&self) -> bool {
+        self.kind != TempoChangeKind::Scroll
+    }
+
+    fn beat_length(&self) -> f64 {
+        (self.end_ms - self.start_ms) / f64::from(self.end_beats - self.start_beats)
+    }
+}
+
+/// Bidirectional mapping between beats and time (in milliseconds).
+///
+/// Steps at the position of a stop are hit at the start of the stop, steps at the position of a
+/// delay at the end of the delay.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimingMap {
+    segments: Vec<Segment>,
+}
+
+impl TimingMap {
+    pub fn new(tempo_changes: &TempoChanges) -> Self {
+        let mut segments: Vec<Segment> = Vec::new();
+
+        for (i, tempo_change) in tempo_changes.iter().enumerate() {
+            let start_ms = tempo_change.start_ms;
+            let end_ms = match tempo_changes.get(i + 1) {
+                Some(next) => next.start_ms,
+                None if tempo_change.beat_length.is_finite() => {
+                    start_ms
+                        + tempo_change.beat_length
+                            * f64::from(tempo_change.end_beats - tempo_change.start_beats)
+                }
+                // length of a stop at the end can not be determined
+                None => start_ms,
+            };
+
+            segments.push(Segment {
+                start_beats: tempo_change.start_beats,
+                end_beats: tempo_change.end_beats,
+                start_ms,
+                end_ms,
+                kind: tempo_change.kind,
+            });
+        }
+
+        Self { segments }
+    }
+
+    /// Returns the index of the first segment for which `predicate` is false (`predicate` has to
+    /// be true for all segments before and false for all segments after that index).
+    fn partition_point<P: Fn(&Segment) -> bool>(&self, predicate: P) -> usize {
+        self.segments
+            .binary_search_by(|segment| {
+                if predicate(segment) {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                }
+            })
+            .unwrap_or_else(|index| index)
+    }
+
+    /// Returns the time (in milliseconds) at which the step at `beats` has to be hit or `None`
+    /// if it is outside of the tempo changes.
+    pub fn time_at(&self, beats: f32) -> Option<f64> {
+        if beats < self.segments.first()?.start_beats {
+            return None;
+        }
+
+        // first segment that reaches beats; for stops this is the segment before the stop
+        let index = self.partition_point(|segment| segment.end_beats < beats);
+        let segment = self.segments.get(index)?;
+
+        if beats >= segment.end_beats {
+            let mut time = if segment.is_pause() {
+                segment.start_ms
+            } else {
+                segment.end_ms
+            };
+            // delays at this position are before the step, stops after it
+            for segment in self.segments[index..]
+                .iter()
+                .take_while(|segment| segment.start_beats <= beats)
+                .filter(|segment| segment.start_beats == beats)
+            {
+                match segment.kind {
+                    TempoChangeKind::Delay => time = segment.end_ms,
+                    TempoChangeKind::Stop => break,
+                    TempoChangeKind::Scroll => {}
+                }
+            }
+            Some(time)
+        } else {
+            Some(segment.start_ms + f64::from(beats - segment.start_beats) * segment.beat_length())
+        }
+    }
+
+    /// Returns the position (in beats) at `time` (in milliseconds) or `None` if it is outside of
+    /// the tempo changes.
+    ///
+    /// During a stop or delay, this returns its position.
+    pub fn beats_at(&self, time: f64) -> Option<f32> {
+        if time < self.segments.first()?.start_ms {
+            return None;
+        }
+
+        // first segment that reaches time
+        let index = self.partition_point(|segment| segment.end_ms < time);
+        let segment = self.segments.get(index)?;
+
+        if segment.is_pause() || time >= segment.end_ms {
+            Some(segment.end_beats)
+        } else {
+            Some(segment.start_beats + ((time - segment.start_ms) / segment.beat_length()) as f32)
+        }
+    }
+
+    /// Returns the tempo (in beats per minute) at `beats` or `None` if it is outside of the tempo
+    /// changes.
+    ///
+    /// Stops and delays are skipped, so this returns the tempo the chart scrolls with after them.
+    pub fn bpm_at(&self, beats: f32) -> Option<f32> {
+        if beats < self.segments.first()?.start_beats {
+            return None;
+        }
+
+        let index = self.partition_point(|segment| segment.end_beats <= beats);
+        // position at the end of the last segment has the tempo of the last segment
+        let segment = match self.segments.get(index) {
+            Some(segment) => segment,
+            None => self
+                .segments
+                .iter()
+                .rev()
+                .find(|segment| !segment.is_pause() && segment.end_beats == beats)?,
+        };
+
+        Some((60000.0 / segment.beat_length()) as f32)
+    }
+}
+
+impl From<&TempoChanges> for TimingMap {
+    fn from(tempo_changes: &TempoChanges) -> Self {
+        Self::new(tempo_changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{TempoChange, TempoChangeKind};
+    use super::*;
+
+    /// 120 BPM for 16 beats, 500 ms stop (or delay), 240 BPM for 4 beats
+    fn timing_map_with(kind: TempoChangeKind) -> TimingMap {
+        TimingMap::new(&TempoChanges {
+            ticks_per_second: 150,
+            first_measure: 0,
+            first_tick: 0,
+            changes: vec![
+                TempoChange {
+                    start_ms: 0.0,
+                    start_beats: 0.0,
+                    end_beats: 16.0,
+                    beat_length: 500.0,
+                    kind: TempoChangeKind::Scroll,
+                },
+                TempoChange {
+                    start_ms: 8000.0,
+                    start_beats: 16.0,
+                    end_beats: 16.0,
+                    beat_length: f64::INFINITY,
+                    kind,
+                },
+                TempoChange {
+                    start_ms: 8500.0,
+                    start_beats: 16.0,
+                    end_beats: 20.0,
+                    beat_length: 250.0,
+                    kind: TempoChangeKind::Scroll,
+                },
+            ],
+        })
+    }
+
+    fn timing_map() -> TimingMap {
+        timing_map_with(TempoChangeKind::Stop)
+    }
+
+    #[test]
+    fn test_time_at() {
+        let timing_map = timing_map();
+        assert_eq!(timing_map.time_at(0.0), Some(0.0));
+        assert_eq!(timing_map.time_at(1.5), Some(750.0));
+        assert_eq!(timing_map.time_at(16.0), Some(8000.0));
+        assert_eq!(timing_map.time_at(17.0), Some(8750.0));
+        assert_eq!(timing_map.time_at(20.0), Some(9500.0));
+        assert_eq!(timing_map.time_at(20.5), None);
+        assert_eq!(timing_map.time_at(-1.0), None);
+    }
+
+    #[test]
+    fn test_time_at_delay() {
+        let timing_map = timing_map_with(TempoChangeKind::Delay);
+        assert_eq!(timing_map.time_at(15.0), Some(7500.0));
+        assert_eq!(timing_map.time_at(16.0), Some(8500.0));
+        assert_eq!(timing_map.time_at(17.0), Some(8750.0));
+        assert_eq!(timing_map.beats_at(8250.0), Some(16.0));
+    }
+
+    #[test]
+    fn test_beats_at() {
+        let timing_map = timing_map();
+        assert_eq!(timing_map.beats_at(0.0), Some(0.0));
+        assert_eq!(timing_map.beats_at(750.0), Some(1.5));
+        assert_eq!(timing_map.beats_at(8000.0), Some(16.0));
+        assert_eq!(timing_map.beats_at(8250.0), Some(16.0));
+        assert_eq!(timing_map.beats_at(8750.0), Some(17.0));
+        assert_eq!(timing_map.beats_at(9500.0), Some(20.0));
+        assert_eq!(timing_map.beats_at(9501.0), None);
+        assert_eq!(timing_map.beats_at(-1.0), None);
+    }
+
+    #[quickcheck]
+    fn test_time_beats_inverse(beats: u16) -> bool {
+        // quarter beats from 0 to 20
+        let beats = f32::from(beats % 81) / 4.0;
+        let timing_map = timing_map();
+        timing_map.beats_at(timing_map.time_at(beats).unwrap()) == Some(beats)
+    }
+
+    #[test]
+    fn test_bpm_at() {
+        let timing_map = timing_map();
+        assert_eq!(timing_map.bpm_at(0.0), Some(120.0));
+        assert_eq!(timing_map.bpm_at(15.9), Some(120.0));
+        assert_eq!(timing_map.bpm_at(16.0), Some(240.0));
+        assert_eq!(timing_map.bpm_at(20.0), Some(240.0));
+        assert_eq!(timing_map.bpm_at(21.0), None);
+    }
+
+    #[test]
+    fn test_empty() {
+        let timing_map = TimingMap::new(&TempoChanges::default());
+        assert_eq!(timing_map.time_at(0.0), None);
+        assert_eq!(timing_map.beats_at(0.0), None);
+        assert_eq!(timing_map.bpm_at(0.0), None);
+    }
+}
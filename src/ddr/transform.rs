@@ -0,0 +1,169 @@
+//! Transformations of step charts.
+//!
+//! Transformations work on the [`rows`] of a chart and convert them back into steps with
+//! [`steps_from_rows`], so they don’t have to care about how freezes are stored.
+//!
+//! [`rows`]: ../analysis/fn.rows.html
+//! [`steps_from_rows`]: fn.steps_from_rows.html
+pub mod modifiers;
+pub mod simplify;
+pub mod style;
+
+use std::cmp::Ordering;
+
+use crate::ddr::analysis::NoteRow;
+use crate::ddr::ssq::{Row, Step, Style};
+
+/// Tolerance (in beats) for comparing positions
+const EPSILON: f32 = 0.001;
+
+/// Converts rows back into steps (inverse of [`rows`]).
+///
+/// Every row becomes one step with the columns that don’t start a freeze and one freeze for
+/// every freeze end, like [`Chart`]s parsed from SSQ files.
+///
+/// [`rows`]: ../analysis/fn.rows.html
+/// [`Chart`]: ../ssq/struct.Chart.html
+pub fn steps_from_rows(rows: &[NoteRow], style: Style) -> Vec<Step> {
+    let mut steps = Vec::new();
+
+    for row in rows {
+        let step_columns: Vec<bool> = row
+            .columns
+            .iter()
+            .zip(row.freeze_ends.iter())
+            .map(|(active, end)| *active && end.is_none())
+            .collect();
+        if step_columns.iter().any(|active| *active) {
+            steps.push(Step::Step {
+                beats: row.beats,
+                row: Row::from_columns(&step_columns, style),
+            });
+        }
+
+        let mut ends: Vec<f32> = row.freeze_ends.iter().filter_map(|end| *end).collect();
+        ends.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        ends.dedup();
+        for end in ends {
+            let freeze_columns: Vec<bool> = row
+                .freeze_ends
+                .iter()
+                .map(|freeze_end| *freeze_end == Some(end))
+                .collect();
+            steps.push(Step::Freeze {
+                start: row.beats,
+                end,
+                row: Row::from_columns(&freeze_columns, style),
+            });
+        }
+    }
+
+    steps
+}
+
+/// Returns true if `beats` is a multiple of `grid`.
+fn is_on_grid(beats: f32, grid: f32) -> bool {
+    let remainder = (beats / grid).fract();
+    !(EPSILON..=1.0 - EPSILON).contains(&remainder)
+}
+
+/// Reduces a jump to one column, preferring columns that were not stepped on in `last` and
+/// columns that start a freeze.
+fn reduce_jump(row: &mut NoteRow, last: Option<&NoteRow>) {
+    let active: Vec<usize> = (0..row.columns.len())
+        .filter(|column| row.columns[*column])
+        .collect();
+    let fresh: Vec<usize> = active
+        .iter()
+        .copied()
+        .filter(|column| !matches!(last, Some(last) if last.columns[*column]))
+        .collect();
+    let candidates = if fresh.is_empty() { active } else { fresh };
+    let column = candidates
+        .iter()
+        .copied()
+        .find(|column| row.freeze_ends[*column].is_some())
+        .unwrap_or(candidates[0]);
+
+    for other in 0..row.columns.len() {
+        if other != column {
+            row.columns[other] = false;
+            row.freeze_ends[other] = None;
+        }
+    }
+}
+
+/// Returns the position of a step (the start for freezes).
+fn position(step: &Step) -> f32 {
+    match step {
+        Step::Step { beats, .. } | Step::Shock { beats } | Step::Extra { beats, .. } => *beats,
+        Step::Freeze { start, .. } => *start,
+    }
+}
+
+/// Sorts steps by their position, steps at the same position keep their order.
+pub fn sort_steps(steps: &mut [Step]) {
+    steps.sort_by(|a, b| {
+        position(a)
+            .partial_cmp(&position(b))
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::analysis::rows;
+    use crate::ddr::ssq::{Chart, Level};
+
+    #[test]
+    fn test_steps_from_rows() {
+        let chart = Chart {
+            difficulty: Level::new(Style::Single, 1).unwrap(),
+            steps: vec![
+                Step::Step {
+                    beats: 1.0,
+                    row: Row::new(0b0110, Style::Single),
+                },
+                Step::Step {
+                    beats: 2.0,
+                    row: Row::new(0b1000, Style::Single),
+                },
+                Step::Freeze {
+                    start: 2.0,
+                    end: 3.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                Step::Freeze {
+                    start: 2.0,
+                    end: 4.0,
+                    row: Row::new(0b0010, Style::Single),
+                },
+            ],
+        };
+        assert_eq!(steps_from_rows(&rows(&chart), Style::Single), chart.steps);
+    }
+
+    #[test]
+    fn test_is_on_grid() {
+        assert!(is_on_grid(3.0, 1.0));
+        assert!(is_on_grid(2.5, 0.5));
+        assert!(!is_on_grid(2.5, 1.0));
+        assert!(!is_on_grid(0.25, 0.5));
+    }
+
+    #[test]
+    fn test_sort_steps() {
+        let mut steps = vec![
+            Step::Shock { beats: 2.0 },
+            Step::Freeze {
+                start: 1.0,
+                end: 3.0,
+                row: Row::new(0b0001, Style::Single),
+            },
+        ];
+        sort_steps(&mut steps);
+        assert_eq!(position(&steps[0]), 1.0);
+        assert_eq!(position(&steps[1]), 2.0);
+    }
+}
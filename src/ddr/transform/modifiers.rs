@@ -0,0 +1,469 @@
+//! Arrow and cut options as they are offered in game.
+//!
+//! Arrow options (Mirror, Left, Right and Shuffle) permute the columns of every row, cut options
+//! (Little, No Freeze, No Jump and Shock Off) remove steps.
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::{is_on_grid, reduce_jump, sort_steps, steps_from_rows};
+use crate::ddr::analysis::{rows, NoteRow};
+use crate::ddr::ssq::{Chart, Step, Style};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("unknown modifier {0} (valid options: mirror, left, right, shuffle, shuffle:SEED, little, no-freeze, no-jump, shock-off)")]
+    UnknownModifier(String),
+    #[error("invalid shuffle seed ({0})")]
+    InvalidSeed(#[from] std::num::ParseIntError),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Modifier {
+    /// Swaps left and right as well as up and down
+    Mirror,
+    /// Rotates the arrows by 90° counterclockwise (← becomes ↓)
+    Left,
+    /// Rotates the arrows by 90° clockwise (← becomes ↑)
+    Right,
+    /// Random permutation of the columns, the same seed always results in the same permutation
+    Shuffle(u64),
+    /// Removes all steps that are not on a beat
+    Little,
+    /// Turns freezes into normal steps
+    NoFreeze,
+    /// Reduces jumps to one arrow
+    NoJump,
+    /// Removes shocks
+    ShockOff,
+}
+
+impl Modifier {
+    /// Returns the new column of every column (as in `Into<Vec<bool>>` of [`Row`]) for arrow
+    /// options, `None` for cut options.
+    ///
+    /// Double charts are mirrored as a whole, Couple and Battle charts per player. All other
+    /// permutations are the same for both pads. Left and Right keep the upper left and upper right
+    /// panels of Solo charts in place.
+    ///
+    /// [`Row`]: ../../ssq/enum.Row.html
+    pub fn permutation(&self, style: Style) -> Option<Vec<usize>> {
+        let pad: Vec<usize> = match (self, style) {
+            (Self::Mirror, Style::Double) => return Some((0..8).rev().collect()),
+            (Self::Mirror, Style::Solo) => return Some((0..6).rev().collect()),
+            (Self::Left, Style::Solo) => vec![2, 1, 5, 0, 4, 3],
+            (Self::Right, Style::Solo) => vec![3, 1, 0, 5, 4, 2],
+            (Self::Shuffle(seed), Style::Solo) => shuffle(6, *seed),
+            (Self::Mirror, _) => vec![3, 2, 1, 0],
+            (Self::Left, _) => vec![1, 3, 0, 2],
+            (Self::Right, _) => vec![2, 0, 3, 1],
+            (Self::Shuffle(seed), _) => shuffle(4, *seed),
+            (Self::Little, _) | (Self::NoFreeze, _) | (Self::NoJump, _) | (Self::ShockOff, _) => {
+                return None
+            }
+        };
+
+        let size = pad.len();
+        let pads = usize::from(style.columns()) / size;
+        Some(
+            (0..pads)
+                .flat_map(|i| pad.iter().map(move |column| column + i * size))
+                .collect(),
+        )
+    }
+
+    /// Returns the chart with the modifier applied.
+    pub fn apply(&self, chart: &Chart) -> Chart {
+        match self {
+            Self::Mirror | Self::Left | Self::Right | Self::Shuffle(_) => {
+                // arrow options always have a permutation, an empty one keeps every column
+                let permutation = self.permutation(chart.difficulty.style).unwrap_or_default();
+                permute(chart, &permutation)
+            }
+            Self::Little => {
+                let mut little = map_rows(chart, |rows| {
+                    rows.into_iter()
+                        .filter(|row| is_on_grid(row.beats, 1.0))
+                        .collect()
+                });
+                little.steps.retain(|step| match step {
+                    Step::Shock { beats } => is_on_grid(*beats, 1.0),
+                    _ => true,
+                });
+                little
+            }
+            Self::NoFreeze => map_rows(chart, |rows| {
+                rows.into_iter()
+                    .map(|mut row| {
+                        row.freeze_ends = vec![None; row.columns.len()];
+                        row
+                    })
+                    .collect()
+            }),
+            Self::NoJump => map_rows(chart, |rows| {
+                let mut reduced: Vec<NoteRow> = Vec::new();
+                for mut row in rows {
+                    if row.is_jump() {
+                        reduce_jump(&mut row, reduced.last());
+                    }
+                    reduced.push(row);
+                }
+                reduced
+            }),
+            Self::ShockOff => Chart {
+                difficulty: chart.difficulty.clone(),
+                steps: chart
+                    .steps
+                    .iter()
+                    .filter(|step| !matches!(step, Step::Shock { .. }))
+                    .cloned()
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mirror => write!(f, "Mirror"),
+            Self::Left => write!(f, "Left"),
+            Self::Right => write!(f, "Right"),
+            Self::Shuffle(seed) => write!(f, "Shuffle #{}", seed),
+            Self::Little => write!(f, "Little"),
+            Self::NoFreeze => write!(f, "No Freeze"),
+            Self::NoJump => write!(f, "No Jump"),
+            Self::ShockOff => write!(f, "Shock Off"),
+        }
+    }
+}
+
+impl FromStr for Modifier {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<Self, Error> {
+        Ok(match string.to_lowercase().as_str() {
+            "mirror" => Self::Mirror,
+            "left" => Self::Left,
+            "right" => Self::Right,
+            "shuffle" => Self::Shuffle(0),
+            "little" => Self::Little,
+            "no-freeze" => Self::NoFreeze,
+            "no-jump" => Self::NoJump,
+            "shock-off" => Self::ShockOff,
+            other => match other.strip_prefix("shuffle:") {
+                Some(seed) => Self::Shuffle(seed.parse()?),
+                None => return Err(Error::UnknownModifier(string.to_string())),
+            },
+        })
+    }
+}
+
+/// Combination of modifiers, which are applied in order.
+///
+/// It is parsed from modifiers separated by `+` (e.g. `mirror+no-freeze`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Modifiers(pub Vec<Modifier>);
+
+impl Modifiers {
+    /// Returns the chart with all modifiers applied.
+    pub fn apply(&self, chart: &Chart) -> Chart {
+        self.0
+            .iter()
+            .fold(chart.clone(), |chart, modifier| modifier.apply(&chart))
+    }
+}
+
+impl fmt::Display for Modifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self.0.iter().map(|modifier| modifier.to_string()).collect();
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+impl FromStr for Modifiers {
+    type Err = Error;
+
+    fn from_str(string: &str) -> Result<Self, Error> {
+        Ok(Self(
+            string
+                .split('+')
+                .map(Modifier::from_str)
+                .collect::<Result<_, _>>()?,
+        ))
+    }
+}
+
+/// Moves the columns of all steps and freezes to the columns in `permutation`.
+fn permute(chart: &Chart, permutation: &[usize]) -> Chart {
+    let steps = chart
+        .steps
+        .iter()
+        .map(|step| match step {
+            Step::Step { beats, row } => Step::Step {
+                beats: *beats,
+                row: row.permute(permutation),
+            },
+            Step::Freeze { start, end, row } => Step::Freeze {
+                start: *start,
+                end: *end,
+                row: row.permute(permutation),
+            },
+            Step::Shock { .. } | Step::Extra { .. } => step.clone(),
+        })
+        .collect();
+
+    Chart {
+        difficulty: chart.difficulty.clone(),
+        steps,
+    }
+}
+
+/// Rebuilds a chart from its rows after passing them through `f`, other steps are kept.
+fn map_rows(chart: &Chart, f: impl FnOnce(Vec<NoteRow>) -> Vec<NoteRow>) -> Chart {
+    let mut steps = steps_from_rows(&f(rows(chart)), chart.difficulty.style);
+    steps.extend(
+        chart
+            .steps
+            .iter()
+            .filter(|step| matches!(step, Step::Shock { .. } | Step::Extra { .. }))
+            .cloned(),
+    );
+    sort_steps(&mut steps);
+
+    Chart {
+        difficulty: chart.difficulty.clone(),
+        steps,
+    }
+}
+
+/// xorshift64 random number generator, so shuffles are reproducible without another dependency
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // the state must not be zero
+        let state = seed ^ 0x9e37_79b9_7f4a_7c15;
+        Self(if state == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            state
+        })
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// Returns a random permutation of `columns` columns (Fisher-Yates shuffle).
+fn shuffle(columns: usize, seed: u64) -> Vec<usize> {
+    let mut rng = XorShift::new(seed);
+    let mut permutation: Vec<usize> = (0..columns).collect();
+    for i in (1..columns).rev() {
+        let j = (rng.next() % (i as u64 + 1)) as usize;
+        permutation.swap(i, j);
+    }
+    permutation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::{Level, Row};
+
+    fn chart(style: Style, steps: Vec<Step>) -> Chart {
+        Chart {
+            difficulty: Level::new(style, 3).unwrap(),
+            steps,
+        }
+    }
+
+    fn step(beats: f32, columns: u8) -> Step {
+        Step::Step {
+            beats,
+            row: Row::new(columns, Style::Single),
+        }
+    }
+
+    #[test]
+    fn test_permute_single() {
+        // ← ↓
+        let row = Row::new(0b0011, Style::Single);
+        assert_eq!(
+            row.permute(&Modifier::Mirror.permutation(Style::Single).unwrap()),
+            Row::new(0b1100, Style::Single)
+        );
+        assert_eq!(
+            row.permute(&Modifier::Left.permutation(Style::Single).unwrap()),
+            Row::new(0b1010, Style::Single)
+        );
+        assert_eq!(
+            row.permute(&Modifier::Right.permutation(Style::Single).unwrap()),
+            Row::new(0b0101, Style::Single)
+        );
+    }
+
+    #[quickcheck]
+    fn test_left_right_inverse(columns: u8) -> bool {
+        let row = Row::new(columns, Style::Double);
+        let left = Modifier::Left.permutation(Style::Double).unwrap();
+        let right = Modifier::Right.permutation(Style::Double).unwrap();
+        row.permute(&left).permute(&right) == row
+    }
+
+    #[test]
+    fn test_permute_double() {
+        let row = Row::new(0b0000_0001, Style::Double);
+        assert_eq!(
+            row.permute(&Modifier::Mirror.permutation(Style::Double).unwrap()),
+            Row::new(0b1000_0000, Style::Double)
+        );
+        assert_eq!(
+            row.permute(&Modifier::Mirror.permutation(Style::Couple).unwrap()),
+            Row::new(0b0000_1000, Style::Double)
+        );
+        let row = Row::new(0b0001_0000, Style::Double);
+        assert_eq!(
+            row.permute(&Modifier::Left.permutation(Style::Double).unwrap()),
+            Row::new(0b0010_0000, Style::Double)
+        );
+    }
+
+    #[test]
+    fn test_permute_solo() {
+        // ← ↖
+        let row = Row::new(0b01_0001, Style::Solo);
+        assert_eq!(
+            row.permute(&Modifier::Mirror.permutation(Style::Solo).unwrap()),
+            Row::new(0b10_1000, Style::Solo)
+        );
+        assert_eq!(
+            row.permute(&Modifier::Left.permutation(Style::Solo).unwrap()),
+            Row::new(0b01_0010, Style::Solo)
+        );
+    }
+
+    #[test]
+    fn test_shuffle() {
+        for seed in 0..100 {
+            let mut permutation = shuffle(4, seed);
+            assert_eq!(permutation, shuffle(4, seed));
+            permutation.sort_unstable();
+            assert_eq!(permutation, vec![0, 1, 2, 3]);
+        }
+        assert!((0..100).any(|seed| shuffle(4, seed) != shuffle(4, 0)));
+        // same permutation on both pads
+        let permutation = Modifier::Shuffle(1).permutation(Style::Double).unwrap();
+        assert_eq!(
+            permutation[4..].to_vec(),
+            permutation[..4]
+                .iter()
+                .map(|column| column + 4)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_apply_permutation() {
+        let original = chart(
+            Style::Single,
+            vec![
+                step(1.0, 0b0001),
+                Step::Freeze {
+                    start: 2.0,
+                    end: 3.0,
+                    row: Row::new(0b0010, Style::Single),
+                },
+                Step::Shock { beats: 4.0 },
+            ],
+        );
+        assert_eq!(
+            Modifier::Mirror.apply(&original).steps,
+            vec![
+                step(1.0, 0b1000),
+                Step::Freeze {
+                    start: 2.0,
+                    end: 3.0,
+                    row: Row::new(0b0100, Style::Single),
+                },
+                Step::Shock { beats: 4.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_apply_cuts() {
+        let original = chart(
+            Style::Single,
+            vec![
+                step(1.0, 0b1001),
+                step(1.5, 0b0010),
+                Step::Freeze {
+                    start: 2.0,
+                    end: 3.0,
+                    row: Row::new(0b0010, Style::Single),
+                },
+                Step::Shock { beats: 3.5 },
+                Step::Shock { beats: 4.0 },
+            ],
+        );
+        assert_eq!(
+            Modifier::Little.apply(&original).steps,
+            vec![
+                step(1.0, 0b1001),
+                Step::Freeze {
+                    start: 2.0,
+                    end: 3.0,
+                    row: Row::new(0b0010, Style::Single),
+                },
+                Step::Shock { beats: 4.0 },
+            ]
+        );
+        assert_eq!(
+            Modifier::NoFreeze.apply(&original).steps,
+            vec![
+                step(1.0, 0b1001),
+                step(1.5, 0b0010),
+                step(2.0, 0b0010),
+                Step::Shock { beats: 3.5 },
+                Step::Shock { beats: 4.0 },
+            ]
+        );
+        assert_eq!(
+            Modifier::NoJump.apply(&original).steps[0],
+            step(1.0, 0b0001)
+        );
+        assert_eq!(Modifier::ShockOff.apply(&original).steps.len(), 3);
+    }
+
+    #[test]
+    fn test_modifiers_parse() {
+        assert_eq!(
+            "mirror+No-Freeze+shuffle:42".parse::<Modifiers>().unwrap(),
+            Modifiers(vec![
+                Modifier::Mirror,
+                Modifier::NoFreeze,
+                Modifier::Shuffle(42)
+            ])
+        );
+        assert!("mirror+upside-down".parse::<Modifiers>().is_err());
+        assert!("shuffle:x".parse::<Modifier>().is_err());
+        assert_eq!(
+            Modifiers(vec![Modifier::Left, Modifier::ShockOff]).to_string(),
+            "Left Shock Off"
+        );
+    }
+
+    #[test]
+    fn test_modifiers_apply() {
+        let original = chart(Style::Single, vec![step(1.0, 0b0001)]);
+        let modifiers = Modifiers(vec![Modifier::Mirror, Modifier::Left]);
+        // ← → (mirror) → ↑ (left)
+        assert_eq!(modifiers.apply(&original).steps, vec![step(1.0, 0b0100)]);
+    }
+}
@@ -0,0 +1,360 @@
+//! Generation of easier charts from harder ones.
+//!
+//! Easier charts are derived by removing steps: only steps on a coarse grid are kept, jumps are
+//! reduced to one arrow, freezes are shortened or turned into normal steps and the number of
+//! steps per measure is limited. The rhythm of the remaining steps still follows the original
+//! chart, but the result is not as good as a chart made by hand.
+use log::debug;
+
+use super::{is_on_grid, reduce_jump, sort_steps, steps_from_rows, EPSILON};
+use crate::ddr::analysis::{rows, NoteRow};
+use crate::ddr::ssq::{Chart, Level, Step, Style};
+
+const BEATS_PER_MEASURE: f32 = 4.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// Only rows on this grid (in beats) are kept, e.g. 1.0 for quarter notes
+    pub grid: f32,
+    /// Minimum distance between two rows (in beats)
+    pub min_interval: f32,
+    /// Rows on the weakest positions of a measure are removed above this number
+    pub max_rows_per_measure: usize,
+    /// Keep jumps, otherwise they are reduced to one arrow
+    pub jumps: bool,
+    /// Freezes are shortened to this length (in beats), `None` turns them into normal steps
+    pub max_freeze_length: Option<f32>,
+    /// Keep rows while a freeze is held
+    pub steps_during_freezes: bool,
+    pub shocks: bool,
+}
+
+impl Config {
+    /// Quarter notes only, without jumps, freezes or shocks.
+    pub fn beginner() -> Self {
+        Self {
+            grid: 1.0,
+            min_interval: 1.0,
+            max_rows_per_measure: 3,
+            jumps: false,
+            max_freeze_length: None,
+            steps_during_freezes: false,
+            shocks: false,
+        }
+    }
+
+    /// Eighth notes with jumps and short freezes, but without shocks.
+    pub fn basic() -> Self {
+        Self {
+            grid: 0.5,
+            min_interval: 0.5,
+            max_rows_per_measure: 6,
+            jumps: true,
+            max_freeze_length: Some(4.0),
+            steps_during_freezes: false,
+            shocks: false,
+        }
+    }
+
+    /// Returns the configuration for an ordered difficulty (only Beginner and Basic).
+    pub fn for_difficulty(difficulty: u8) -> Option<Self> {
+        match difficulty {
+            0 => Some(Self::beginner()),
+            1 => Some(Self::basic()),
+            _ => None,
+        }
+    }
+}
+
+/// Returns how strong a position within a measure is (0 is the strongest).
+fn strength(beats: f32) -> usize {
+    [4.0, 2.0, 1.0, 0.5]
+        .iter()
+        .position(|grid| is_on_grid(beats, *grid))
+        .unwrap_or(4)
+}
+
+/// Returns the rows that are kept with `config`.
+fn simplify_rows(rows: Vec<NoteRow>, config: &Config) -> Vec<NoteRow> {
+    let mut kept: Vec<NoteRow> = Vec::new();
+
+    for mut row in rows {
+        if !is_on_grid(row.beats, config.grid) {
+            continue;
+        }
+        if let Some(last) = kept.last() {
+            if row.beats - last.beats < config.min_interval - EPSILON {
+                continue;
+            }
+        }
+
+        let held: Vec<bool> = (0..row.columns.len())
+            .map(|column| {
+                kept.iter()
+                    .any(|kept| matches!(kept.freeze_ends[column], Some(end) if end > row.beats))
+            })
+            .collect();
+        if held.iter().any(|held| *held) && !config.steps_during_freezes {
+            continue;
+        }
+        if row
+            .columns
+            .iter()
+            .zip(held.iter())
+            .any(|(active, held)| *active && *held)
+        {
+            continue;
+        }
+
+        match config.max_freeze_length {
+            Some(max_length) => {
+                for end in row.freeze_ends.iter_mut().flatten() {
+                    *end = end.min(row.beats + max_length);
+                }
+            }
+            None => row.freeze_ends = vec![None; row.columns.len()],
+        }
+        if !config.jumps && row.is_jump() {
+            reduce_jump(&mut row, kept.last());
+        }
+
+        kept.push(row);
+    }
+
+    limit_density(kept, config.max_rows_per_measure)
+}
+
+/// Removes the rows on the weakest positions of measures with more than `max_rows` rows.
+fn limit_density(rows: Vec<NoteRow>, max_rows: usize) -> Vec<NoteRow> {
+    let mut limited: Vec<NoteRow> = Vec::new();
+    let mut measure: Vec<NoteRow> = Vec::new();
+
+    let measure_of = |row: &NoteRow| ((row.beats + EPSILON) / BEATS_PER_MEASURE).floor() as i32;
+
+    for row in rows {
+        if let Some(first) = measure.first() {
+            if measure_of(first) != measure_of(&row) {
+                limited.append(&mut limit_measure(std::mem::take(&mut measure), max_rows));
+            }
+        }
+        measure.push(row);
+    }
+    limited.append(&mut limit_measure(measure, max_rows));
+
+    limited
+}
+
+fn limit_measure(mut rows: Vec<NoteRow>, max_rows: usize) -> Vec<NoteRow> {
+    if rows.len() > max_rows {
+        // stable sort, so earlier rows are kept among rows of the same strength
+        rows.sort_by_key(|row| strength(row.beats));
+        rows.truncate(max_rows);
+        rows.sort_by(|a, b| a.beats.partial_cmp(&b.beats).unwrap());
+    }
+    rows
+}
+
+/// Returns an easier version of a chart with the same difficulty.
+pub fn simplify(chart: &Chart, config: &Config) -> Chart {
+    let style = chart.difficulty.style;
+    let mut steps = steps_from_rows(&simplify_rows(rows(chart), config), style);
+
+    for step in &chart.steps {
+        match step {
+            Step::Shock { .. } if config.shocks => steps.push(step.clone()),
+            Step::Extra { .. } => steps.push(step.clone()),
+            _ => {}
+        }
+    }
+    sort_steps(&mut steps);
+
+    Chart {
+        difficulty: chart.difficulty.clone(),
+        steps,
+    }
+}
+
+/// Generates a chart for an ordered difficulty (Beginner or Basic) from a harder chart.
+///
+/// Returns `None` for other difficulties.
+pub fn generate(chart: &Chart, difficulty: u8) -> Option<Chart> {
+    let config = Config::for_difficulty(difficulty)?;
+    let mut generated = simplify(chart, &config);
+    generated.difficulty = Level {
+        difficulty,
+        ..chart.difficulty.clone()
+    };
+    Some(generated)
+}
+
+/// Generates Beginner and Basic charts for every style that does not have them.
+///
+/// They are generated from the easiest chart (except for edit charts) of the same style that is
+/// harder than the generated one.
+pub fn generate_missing(charts: &[Chart]) -> Vec<Chart> {
+    let mut styles: Vec<Style> = Vec::new();
+    for chart in charts {
+        if !styles.contains(&chart.difficulty.style) {
+            styles.push(chart.difficulty.style);
+        }
+    }
+
+    let mut generated = Vec::new();
+
+    for style in styles {
+        let style_charts: Vec<&Chart> = charts
+            .iter()
+            .filter(|chart| chart.difficulty.style == style && !chart.difficulty.is_edit())
+            .collect();
+
+        for difficulty in 0..=1 {
+            if style_charts
+                .iter()
+                .any(|chart| chart.difficulty.difficulty == difficulty)
+            {
+                continue;
+            }
+            let source = style_charts
+                .iter()
+                .filter(|chart| chart.difficulty.difficulty > difficulty)
+                .min_by_key(|chart| chart.difficulty.difficulty);
+            if let Some(chart) = source.and_then(|source| generate(source, difficulty)) {
+                debug!("Generated {} chart", chart.difficulty);
+                generated.push(chart);
+            }
+        }
+    }
+
+    generated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::Row;
+
+    fn step(beats: f32, columns: u8) -> Step {
+        Step::Step {
+            beats,
+            row: Row::new(columns, Style::Single),
+        }
+    }
+
+    fn chart(difficulty: u8, steps: Vec<Step>) -> Chart {
+        Chart {
+            difficulty: Level {
+                style: Style::Single,
+                difficulty,
+                style_byte: None,
+            },
+            steps,
+        }
+    }
+
+    #[test]
+    fn test_strength() {
+        assert_eq!(strength(4.0), 0);
+        assert_eq!(strength(6.0), 1);
+        assert_eq!(strength(5.0), 2);
+        assert_eq!(strength(5.5), 3);
+        assert_eq!(strength(5.25), 4);
+    }
+
+    #[test]
+    fn test_simplify_beginner() {
+        let original = chart(
+            3,
+            vec![
+                step(0.0, 0b0001),
+                step(0.5, 0b0010),
+                step(1.0, 0b1001),
+                step(1.5, 0b0100),
+                Step::Freeze {
+                    start: 2.0,
+                    end: 6.0,
+                    row: Row::new(0b0100, Style::Single),
+                },
+                step(3.0, 0b0001),
+                Step::Shock { beats: 3.5 },
+            ],
+        );
+        let simplified = simplify(&original, &Config::beginner());
+        assert_eq!(simplified.difficulty, original.difficulty);
+        // off-beat steps and shocks are removed, the jump is reduced to the arrow that was not
+        // stepped on before, the freeze becomes a step
+        assert_eq!(
+            simplified.steps,
+            vec![step(0.0, 0b0001), step(1.0, 0b1000), step(2.0, 0b0100)]
+        );
+    }
+
+    #[test]
+    fn test_simplify_basic() {
+        let original = chart(
+            3,
+            vec![
+                step(0.0, 0b1001),
+                Step::Freeze {
+                    start: 1.0,
+                    end: 9.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                // while the freeze is held
+                step(2.0, 0b1000),
+                step(6.0, 0b0010),
+            ],
+        );
+        let simplified = simplify(&original, &Config::basic());
+        assert_eq!(
+            simplified.steps,
+            vec![
+                step(0.0, 0b1001),
+                Step::Freeze {
+                    start: 1.0,
+                    end: 5.0,
+                    row: Row::new(0b0001, Style::Single),
+                },
+                step(6.0, 0b0010),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_limit_density() {
+        let original = chart(3, (0..16).map(|i| step(i as f32 * 0.5, 0b0001)).collect());
+        let config = Config {
+            max_rows_per_measure: 3,
+            ..Config::basic()
+        };
+        let simplified = simplify(&original, &config);
+        // downbeat, half measure and the first quarter note of each measure
+        assert_eq!(
+            simplified.steps,
+            vec![
+                step(0.0, 0b0001),
+                step(1.0, 0b0001),
+                step(2.0, 0b0001),
+                step(4.0, 0b0001),
+                step(5.0, 0b0001),
+                step(6.0, 0b0001),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_missing() {
+        let charts = vec![
+            chart(2, vec![step(0.0, 0b0001)]),
+            chart(4, vec![step(0.0, 0b0010)]),
+            chart(1, vec![step(0.0, 0b0100)]),
+        ];
+        let generated = generate_missing(&charts);
+        assert_eq!(generated.len(), 1);
+        assert_eq!(generated[0].difficulty.difficulty, 0);
+        // generated from the basic chart
+        assert_eq!(generated[0].steps, vec![step(0.0, 0b0100)]);
+
+        assert!(generate_missing(&[chart(0, Vec::new()), chart(1, Vec::new())]).is_empty());
+        assert_eq!(generate(&charts[0], 2), None);
+    }
+}
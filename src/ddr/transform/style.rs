@@ -0,0 +1,323 @@
+//! Conversion between Single and Double charts.
+//!
+//! Both directions use a window of four adjacent Double columns (left to right: ← ↓ ↑ → ← ↓ ↑ →)
+//! that is mapped onto the Single columns. Single charts are spread across both pads by moving
+//! the window every few measures, Double charts are folded back by choosing the window that
+//! covers most steps of a measure.
+use log::debug;
+
+use super::{reduce_jump, sort_steps, steps_from_rows, EPSILON};
+use crate::ddr::analysis::{rows, NoteRow};
+use crate::ddr::ssq::{Chart, Level, Step, Style};
+
+/// Number of beats before the window moves to the next position
+const PHRASE_BEATS: f32 = 8.0;
+/// Offsets of the window (in Double columns) for consecutive phrases: left pad, center, right
+/// pad and center again
+const WINDOW_OFFSETS: [usize; 4] = [0, 2, 4, 2];
+const BEATS_PER_MEASURE: f32 = 4.0;
+
+/// Converts a Single chart into a Double chart.
+///
+/// The window only moves when no freeze is held, so freezes stay on the panel they started on.
+/// Returns `None` if the chart is not a Single chart.
+pub fn to_double(chart: &Chart) -> Option<Chart> {
+    if chart.difficulty.style != Style::Single {
+        return None;
+    }
+
+    let mut offset = 0;
+    let mut held_until = f32::NEG_INFINITY;
+    let mut converted = Vec::new();
+
+    for row in rows(chart) {
+        if row.beats >= held_until {
+            let phrase = ((row.beats + EPSILON) / PHRASE_BEATS).floor().max(0.0) as usize;
+            offset = WINDOW_OFFSETS[phrase % WINDOW_OFFSETS.len()];
+        }
+        if let Some(end) = row.last_freeze_end() {
+            held_until = held_until.max(end);
+        }
+
+        let mut double = NoteRow {
+            beats: row.beats,
+            columns: vec![false; 8],
+            freeze_ends: vec![None; 8],
+        };
+        for column in 0..row.columns.len() {
+            double.columns[column + offset] = row.columns[column];
+            double.freeze_ends[column + offset] = row.freeze_ends[column];
+        }
+        converted.push(double);
+    }
+
+    Some(convert(chart, &converted, Style::Double))
+}
+
+/// Converts a Double chart into a Single chart.
+///
+/// Steps outside of the window of a measure are moved to the nearest column of the window.
+/// Rows are reduced to at most two arrows and to one arrow while a freeze is held, steps on
+/// held columns are removed. Returns `None` if the chart is not a Double chart.
+pub fn to_single(chart: &Chart) -> Option<Chart> {
+    if chart.difficulty.style != Style::Double {
+        return None;
+    }
+
+    let rows = rows(chart);
+    let measure_of = |row: &NoteRow| ((row.beats + EPSILON) / BEATS_PER_MEASURE).floor() as i32;
+
+    let mut offset = 0;
+    let mut converted: Vec<NoteRow> = Vec::new();
+
+    for (i, row) in rows.iter().enumerate() {
+        if i == 0 || measure_of(&rows[i - 1]) != measure_of(row) {
+            let measure: Vec<&NoteRow> = rows[i..]
+                .iter()
+                .take_while(|other| measure_of(other) == measure_of(row))
+                .collect();
+            offset = best_window(&measure, offset);
+        }
+
+        let mut single = NoteRow {
+            beats: row.beats,
+            columns: vec![false; 4],
+            freeze_ends: vec![None; 4],
+        };
+        for column in (0..row.columns.len()).filter(|column| row.columns[*column]) {
+            let target = column.max(offset).min(offset + 3) - offset;
+            single.columns[target] = true;
+            single.freeze_ends[target] = match (single.freeze_ends[target], row.freeze_ends[column])
+            {
+                (Some(a), Some(b)) => Some(a.max(b)),
+                (a, b) => a.or(b),
+            };
+        }
+
+        // columns of freezes that are still held
+        let held: Vec<bool> = (0..4)
+            .map(|column| {
+                converted
+                    .iter()
+                    .any(|other| matches!(other.freeze_ends[column], Some(end) if end > row.beats))
+            })
+            .collect();
+        for (column, held) in held.iter().enumerate() {
+            if *held {
+                single.columns[column] = false;
+                single.freeze_ends[column] = None;
+            }
+        }
+
+        let max_arrows = if held.contains(&true) { 1 } else { 2 };
+        if single.count_active() > max_arrows {
+            if max_arrows == 1 {
+                reduce_jump(&mut single, converted.last());
+            } else {
+                // keep the outermost arrows
+                let active: Vec<usize> = (0..4).filter(|column| single.columns[*column]).collect();
+                for column in &active[1..active.len() - 1] {
+                    single.columns[*column] = false;
+                    single.freeze_ends[*column] = None;
+                }
+            }
+        }
+
+        if single.count_active() > 0 {
+            converted.push(single);
+        }
+    }
+
+    Some(convert(chart, &converted, Style::Single))
+}
+
+/// Returns the window offset that covers most steps of `rows`, preferring offsets close to
+/// `current` to avoid unnecessary movement.
+fn best_window(rows: &[&NoteRow], current: usize) -> usize {
+    let mut counts = [0; 8];
+    for row in rows {
+        for (column, active) in row.columns.iter().enumerate().take(8) {
+            if *active {
+                counts[column] += 1;
+            }
+        }
+    }
+
+    (0..=4)
+        .max_by_key(|offset| {
+            let covered: usize = counts[*offset..*offset + 4].iter().sum();
+            let distance = (*offset as isize - current as isize).abs();
+            (covered, -distance)
+        })
+        .unwrap_or(current)
+}
+
+/// Builds a chart with the converted rows and the shocks of `chart`.
+fn convert(chart: &Chart, rows: &[NoteRow], style: Style) -> Chart {
+    let mut steps = steps_from_rows(rows, style);
+    for step in &chart.steps {
+        match step {
+            Step::Shock { .. } => steps.push(step.clone()),
+            Step::Extra { kind, .. } => {
+                debug!(
+                    "Extra step with type {} can not be converted, removing it",
+                    kind
+                )
+            }
+            Step::Step { .. } | Step::Freeze { .. } => {}
+        }
+    }
+    sort_steps(&mut steps);
+
+    Chart {
+        difficulty: Level {
+            style,
+            difficulty: chart.difficulty.difficulty,
+            style_byte: None,
+        },
+        steps,
+    }
+}
+
+/// Converts the charts of the style that is missing (Single or Double) from the other style.
+///
+/// Returns no charts if both or none of the styles exist.
+pub fn convert_missing(charts: &[Chart]) -> Vec<Chart> {
+    let has_style = |style| charts.iter().any(|chart| chart.difficulty.style == style);
+
+    let convert: fn(&Chart) -> Option<Chart> =
+        match (has_style(Style::Single), has_style(Style::Double)) {
+            (true, false) => to_double,
+            (false, true) => to_single,
+            _ => return Vec::new(),
+        };
+
+    charts.iter().filter_map(convert).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ddr::ssq::Row;
+
+    fn chart(style: Style, steps: Vec<Step>) -> Chart {
+        Chart {
+            difficulty: Level::new(style, 3).unwrap(),
+            steps,
+        }
+    }
+
+    fn step(beats: f32, columns: u8, style: Style) -> Step {
+        Step::Step {
+            beats,
+            row: Row::new(columns, style),
+        }
+    }
+
+    #[test]
+    fn test_to_double() {
+        let single = chart(
+            Style::Single,
+            vec![
+                step(0.0, 0b0001, Style::Single),
+                step(8.0, 0b0001, Style::Single),
+                step(16.0, 0b1001, Style::Single),
+                Step::Shock { beats: 20.0 },
+                // the window does not move while the freeze is held
+                Step::Freeze {
+                    start: 23.0,
+                    end: 25.0,
+                    row: Row::new(0b1000, Style::Single),
+                },
+                step(24.0, 0b0001, Style::Single),
+                step(26.0, 0b0001, Style::Single),
+            ],
+        );
+        let double = to_double(&single).unwrap();
+        assert_eq!(double.difficulty, Level::new(Style::Double, 3).unwrap());
+        assert_eq!(
+            double.steps,
+            vec![
+                step(0.0, 0b0000_0001, Style::Double),
+                step(8.0, 0b0000_0100, Style::Double),
+                step(16.0, 0b1001_0000, Style::Double),
+                Step::Shock { beats: 20.0 },
+                Step::Freeze {
+                    start: 23.0,
+                    end: 25.0,
+                    row: Row::new(0b1000_0000, Style::Double),
+                },
+                step(24.0, 0b0001_0000, Style::Double),
+                step(26.0, 0b0000_0100, Style::Double),
+            ]
+        );
+        assert_eq!(to_double(&double), None);
+    }
+
+    #[test]
+    fn test_to_single() {
+        let double = chart(
+            Style::Double,
+            vec![
+                // measure on the right pad
+                step(0.0, 0b0001_0000, Style::Double),
+                step(1.0, 0b1000_0000, Style::Double),
+                step(2.0, 0b1000_0000, Style::Double),
+                step(3.0, 0b0000_1000, Style::Double),
+                // jump with three arrows in the center
+                step(4.0, 0b0011_1000, Style::Double),
+                Step::Freeze {
+                    start: 5.0,
+                    end: 7.0,
+                    row: Row::new(0b0000_1000, Style::Double),
+                },
+                // while the freeze is held
+                step(6.0, 0b0001_1100, Style::Double),
+            ],
+        );
+        let single = to_single(&double).unwrap();
+        assert_eq!(single.difficulty, Level::new(Style::Single, 3).unwrap());
+        assert_eq!(
+            single.steps,
+            vec![
+                step(0.0, 0b0001, Style::Single),
+                step(1.0, 0b1000, Style::Single),
+                step(2.0, 0b1000, Style::Single),
+                // outside of the window, moved to the nearest column
+                step(3.0, 0b0001, Style::Single),
+                step(4.0, 0b1010, Style::Single),
+                Step::Freeze {
+                    start: 5.0,
+                    end: 7.0,
+                    row: Row::new(0b0010, Style::Single),
+                },
+                step(6.0, 0b0001, Style::Single),
+            ]
+        );
+        assert_eq!(to_single(&single), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // ← ↓ ↑ → in every measure
+        let single = chart(
+            Style::Single,
+            (0..32)
+                .map(|i| step(i as f32, 1 << (i % 4), Style::Single))
+                .collect(),
+        );
+        assert_eq!(to_single(&to_double(&single).unwrap()).unwrap(), single);
+    }
+
+    #[test]
+    fn test_convert_missing() {
+        let single = chart(Style::Single, vec![step(0.0, 0b0001, Style::Single)]);
+        let double = to_double(&single).unwrap();
+        assert!(convert_missing(&[single.clone(), double.clone()]).is_empty());
+        assert_eq!(
+            convert_missing(std::slice::from_ref(&single)),
+            vec![double.clone()]
+        );
+        assert_eq!(convert_missing(&[double]), vec![single]);
+    }
+}
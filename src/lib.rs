@@ -9,5 +9,7 @@ pub mod converter;
 pub mod ddr;
 mod mini_parser;
 pub mod osu;
+pub mod quaver;
+pub mod stepmania;
 pub mod utils;
 pub mod xact3;
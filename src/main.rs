@@ -2,7 +2,7 @@ use std::convert::TryInto;
 use std::fs;
 use std::io;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use anyhow::{anyhow, Context, Result};
@@ -13,8 +13,11 @@ use rayon::prelude::*;
 use tabwriter::TabWriter;
 
 use brd::converter;
-use brd::ddr::{arc::ARC, musicdb, ssq::SSQ};
+use brd::ddr::analysis::{self, patterns::PatternKind};
+use brd::ddr::{arc::ARC, musicdb, render, ssq, ssq::SSQ};
 use brd::osu;
+use brd::quaver;
+use brd::stepmania;
 use brd::utils;
 use brd::xact3::xwb::{Sound as XWBSound, WaveBank};
 
@@ -56,6 +59,65 @@ enum SubCommand {
         display_order = 1
     )]
     BatchDDR2osu(BatchDDR2osu),
+    #[clap(
+        name = "ddr2quaver",
+        about = "Converts DDR step charts to Quaver maps",
+        display_order = 1
+    )]
+    DDR2Quaver(Box<DDR2Quaver>),
+    #[clap(
+        name = "ssq2sm",
+        about = "Converts DDR step charts to a StepMania song folder",
+        display_order = 1
+    )]
+    SSQ2sm(Box<SSQ2sm>),
+    #[clap(
+        name = "sm2ssq",
+        about = "Converts StepMania simfiles (.sm and .ssc) to DDR step charts",
+        display_order = 1
+    )]
+    Sm2SSQ(Sm2SSQ),
+    #[clap(
+        name = "ssq",
+        about = "Analyses DDR step charts (.ssq files)",
+        display_order = 1
+    )]
+    SSQ(SSQCommand),
+}
+
+#[derive(Clap)]
+struct SSQCommand {
+    #[clap(subcommand)]
+    subcmd: SSQSubCommand,
+}
+
+#[derive(Clap)]
+enum SSQSubCommand {
+    #[clap(about = "Shows statistics for every chart", display_order = 1)]
+    Stats(SSQStats),
+    #[clap(about = "Shows step patterns found in every chart", display_order = 1)]
+    Patterns(SSQPatterns),
+    #[clap(
+        about = "Shows the differences between the charts of two files",
+        display_order = 1
+    )]
+    Diff(SSQDiff),
+    #[clap(about = "Shows charts as text", display_order = 1)]
+    Show(SSQShow),
+    #[clap(
+        name = "svg",
+        about = "Renders charts as SVG chart sheets (side by side)",
+        display_order = 1
+    )]
+    Svg(SSQSvg),
+}
+
+#[derive(Clap)]
+struct SSQStats {
+    #[clap(short = "r", long, about = "Also show Groove Radar values")]
+    radar: bool,
+    #[clap(name = "file")]
+    file: PathBuf,
 }
 
 #[derive(Clap)]
@@ -84,6 +146,69 @@ struct MusicDB {
     file: PathBuf,
 }
 
+#[derive(Clap)]
+struct SSQPatterns {
+    #[clap(
+        short,
+        long,
+        about = "List every pattern instead of the number of patterns"
+    )]
+    list: bool,
+    #[clap(name = "file")]
+    file: PathBuf,
+}
+
+#[derive(Clap)]
+struct SSQDiff {
+    #[clap(
+        short = "m",
+        long,
+        about = "Print one tab separated line per change (chart, beats, kind, old and new value)"
+    )]
+    machine_readable: bool,
+    #[clap(name = "old file")]
+    old_file: PathBuf,
+    #[clap(name = "new file")]
+    new_file: PathBuf,
+}
+
+#[derive(Clap)]
+struct SSQShow {
+    #[clap(
+        short = "c",
+        long = "chart",
+        about = "Only show this chart (e.g. “single-expert”) instead of every chart, can be repeated",
+        number_of_values = 1
+    )]
+    charts: Vec<String>,
+    #[clap(long, about = "Color arrows by their quantization")]
+    color: bool,
+    #[clap(name = "file")]
+    file: PathBuf,
+}
+
+#[derive(Clap)]
+struct SSQSvg {
+    #[clap(
+        short = "c",
+        long = "chart",
+        about = "Only render this chart (e.g. “single-expert”) instead of every chart, can be repeated",
+        number_of_values = 1
+    )]
+    charts: Vec<String>,
+    #[clap(
+        short = "m",
+        long,
+        default_value = "8",
+        about = "Number of measures in each column"
+    )]
+    measures_per_column: usize,
+    #[clap(short = "o", long = "out", name = "file.svg")]
+    out_file: PathBuf,
+    #[clap(name = "file")]
+    file: PathBuf,
+}
+
 #[derive(Clap)]
 struct DDR2osu {
     #[clap(
@@ -129,6 +254,110 @@ struct DDR2osu {
     convert: converter::ddr2osu::Config,
 }
 
+#[derive(Clap)]
+struct DDR2Quaver {
+    #[clap(
+        short = "s",
+        long = "ssq",
+        name = "file.ssq",
+        about = "DDR step chart file",
+        display_order = 1
+    )]
+    ssq_file: PathBuf,
+    #[clap(
+        short = "x",
+        long = "xwb",
+        name = "file.xwb",
+        about = "XAC3 wave bank file",
+        display_order = 1
+    )]
+    xwb_file: PathBuf,
+    #[clap(
+        short = "o",
+        long = "out",
+        name = "file.qp",
+        about = "Quaver map set archive",
+        display_order = 1
+    )]
+    out_file: PathBuf,
+    #[clap(
+        short = "m",
+        long = "musicdb",
+        name = "musicdb.xml|startup.arc",
+        about = "musicdb.xml or startup.arc for metadata",
+        display_order = 1
+    )]
+    musicdb_file: Option<PathBuf>,
+    #[clap(
+        short = "n",
+        name = "basename",
+        about = "Sound in wave bank, otherwise inferred from SSQ filename",
+        display_order = 2
+    )]
+    basename: Option<String>,
+    #[clap(flatten)]
+    convert: converter::ddr2quaver::Config,
+}
+
+#[derive(Clap)]
+struct SSQ2sm {
+    #[clap(
+        short = "s",
+        long = "ssq",
+        name = "file.ssq",
+        about = "DDR step chart file",
+        display_order = 1
+    )]
+    ssq_file: PathBuf,
+    #[clap(
+        short = "x",
+        long = "xwb",
+        name = "file.xwb",
+        about = "XAC3 wave bank file",
+        display_order = 1
+    )]
+    xwb_file: PathBuf,
+    #[clap(
+        short = "o",
+        long = "out",
+        name = "song folder",
+        about = "StepMania song folder (created if it does not exist)",
+        display_order = 1
+    )]
+    out_dir: PathBuf,
+    #[clap(
+        short = "m",
+        long = "musicdb",
+        name = "musicdb.xml|startup.arc",
+        about = "musicdb.xml or startup.arc for metadata",
+        display_order = 1
+    )]
+    musicdb_file: Option<PathBuf>,
+    #[clap(
+        short = "n",
+        name = "basename",
+        about = "Sound in wave bank, otherwise inferred from SSQ filename",
+        display_order = 2
+    )]
+    basename: Option<String>,
+    #[clap(flatten)]
+    convert: converter::ssq2sm::Config,
+}
+
+#[derive(Clap)]
+struct Sm2SSQ {
+    #[clap(
+        short = "o",
+        long = "out",
+        name = "file.ssq",
+        about = "DDR step chart file",
+        display_order = 1
+    )]
+    out_file: PathBuf,
+    #[clap(name = "file.sm|file.ssc")]
+    file: PathBuf,
+}
+
 #[derive(Clap)]
 
 struct BatchDDR2osu {
@@ -168,7 +397,37 @@ struct BatchDDR2osu {
     convert: converter::ddr2osu::Config,
 }
 
-fn read_musicdb(path: &PathBuf) -> Result<musicdb::MusicDB> {
+/// Returns the charts with the given names (e.g. “single-expert”) or every chart if no names are
+/// given.
+fn select_charts<'a>(ssq: &'a SSQ, names: &[String]) -> Result<Vec<&'a ssq::Chart>> {
+    if names.is_empty() {
+        return Ok(ssq.charts.iter().collect());
+    }
+
+    let name = |level: &ssq::Level| level.to_string().to_lowercase().replace(' ', "-");
+    names
+        .iter()
+        .map(|wanted| {
+            ssq.charts
+                .iter()
+                .find(|chart| name(&chart.difficulty) == wanted.to_lowercase().replace(' ', "-"))
+                .ok_or_else(|| {
+                    let available: Vec<String> = ssq
+                        .charts
+                        .iter()
+                        .map(|chart| name(&chart.difficulty))
+                        .collect();
+                    anyhow!(
+                        "Chart {} not found (available: {})",
+                        wanted,
+                        available.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+fn read_musicdb(path: &Path) -> Result<musicdb::MusicDB> {
     let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
 
     match extension {
@@ -192,34 +451,96 @@ fn read_musicdb(path: &PathBuf) -> Result<musicdb::MusicDB> {
     }
 }
 
-fn ddr2osu(
-    ssq_file: PathBuf,
-    xwb_file: PathBuf,
-    out_file: PathBuf,
-    basename: String,
-    convert_options: converter::ddr2osu::Config,
-) -> Result<()> {
-    debug!(
-        "Converting {} and sound {} from {} to {}",
-        ssq_file.display(),
-        basename,
-        xwb_file.display(),
-        out_file.display()
-    );
+fn read_simfile(path: &Path) -> Result<stepmania::simfile::Simfile> {
+    let simfile_data =
+        fs::read(path).with_context(|| format!("failed to read simfile {}", path.display()))?;
+    // simfiles are not always encoded as UTF-8
+    stepmania::simfile::Simfile::parse(&String::from_utf8_lossy(&simfile_data))
+        .context("failed to parse simfile")
+}
 
-    let ssq_data = fs::read(&ssq_file)
-        .with_context(|| format!("failed to read SSQ file {}", &ssq_file.display()))?;
-    let ssq = SSQ::parse(&ssq_data).context("failed to parse SSQ file")?;
+/// Reads an SSQ file or converts a StepMania simfile (.sm or .ssc) to SSQ.
+fn read_ssq(path: &Path) -> Result<SSQ> {
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
 
-    let beatmaps = ssq
-        .to_beatmaps(&convert_options)
-        .context("failed to convert DDR step chart to osu!mania beatmap")?;
+    match extension.to_lowercase().as_str() {
+        "sm" | "ssc" => read_simfile(path)?
+            .to_ssq()
+            .context("failed to convert simfile to SSQ"),
+        _ => {
+            let ssq_data = fs::read(path)
+                .with_context(|| format!("failed to read SSQ file {}", path.display()))?;
+            SSQ::parse(&ssq_data).context("failed to parse SSQ file")
+        }
+    }
+}
 
-    let xwb_data = fs::read(&xwb_file)
-        .with_context(|| format!("failed to read XWB file {}", &xwb_file.clone().display()))?;
+/// Formats a time in milliseconds as minutes, seconds and milliseconds.
+fn format_time(time: f64) -> String {
+    let time = time.max(0.0).round() as u64;
+    format!(
+        "{}:{:02}.{:03}",
+        time / 60000,
+        time / 1000 % 60,
+        time % 1000
+    )
+}
+
+/// Returns the basename (name of the sound in the wave bank and the musicdb entry) or infers it
+/// from the file name of the SSQ file.
+fn get_basename(basename: &Option<String>, ssq_file: &Path) -> Result<String> {
+    match basename {
+        Some(basename) => Ok(basename.clone()),
+        None => ssq_file
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(|basename| basename.to_string())
+            .ok_or_else(|| {
+                anyhow!("Could not extract chart id from file name. Please specify it manually.")
+            }),
+    }
+}
+
+/// Fills missing title and artist and the levels from the musicdb entry of `basename`.
+///
+/// Without musicdb, the basename is used as title if none is given.
+fn read_metadata(
+    musicdb_file: &Option<PathBuf>,
+    basename: &str,
+    metadata: &mut converter::ddr2osu::ConfigMetadata,
+) -> Result<()> {
+    if let Some(musicdb_file) = musicdb_file {
+        debug!("Reading metadata from {}", musicdb_file.display());
+        let musicdb = read_musicdb(musicdb_file)?;
+        let musicdb_entry = musicdb
+            .get_entry_from_basename(basename)
+            .ok_or_else(|| anyhow!("Entry not found in musicdb"))?;
+        if metadata.title.is_none() {
+            info!("Using title from musicdb: “{}”", musicdb_entry.title);
+            metadata.title = Some(musicdb_entry.title.clone());
+        }
+        if metadata.artist.is_none() {
+            info!("Using artist from musicdb: “{}”", musicdb_entry.artist);
+            metadata.artist = Some(musicdb_entry.artist.clone());
+        }
+        metadata.levels = Some(musicdb_entry.diff_lv.clone());
+    } else if metadata.title.is_none() {
+        metadata.title = Some(basename.to_string());
+    }
+
+    Ok(())
+}
+
+/// Extracts the sound `basename` from a wave bank as WAV.
+///
+/// If the wave bank has no sound with this name but two sounds, the larger one is assumed to be
+/// the full song (the other one the preview).
+fn read_audio(xwb_file: &Path, basename: &str) -> Result<Vec<u8>> {
+    let xwb_data = fs::read(xwb_file)
+        .with_context(|| format!("failed to read XWB file {}", xwb_file.display()))?;
     let wave_bank = WaveBank::parse(&xwb_data).context("failed to parse XWB file")?;
 
-    let audio_data = wave_bank.sounds.get(&basename)
+    wave_bank.sounds.get(basename)
         .map(|sound| sound.to_wav().with_context(|| {
             format!(
                 "failed to convert wave bank sound entry “{}” to WAV",
@@ -246,7 +567,31 @@ fn ddr2osu(
                     basename,
                 ))
             }
-        })?;
+        })
+}
+
+fn ddr2osu(
+    ssq_file: PathBuf,
+    xwb_file: PathBuf,
+    out_file: PathBuf,
+    basename: String,
+    convert_options: converter::ddr2osu::Config,
+) -> Result<()> {
+    debug!(
+        "Converting {} and sound {} from {} to {}",
+        ssq_file.display(),
+        basename,
+        xwb_file.display(),
+        out_file.display()
+    );
+
+    let ssq = read_ssq(&ssq_file)?;
+
+    let beatmaps = ssq
+        .to_beatmaps(&convert_options)
+        .context("failed to convert DDR step chart to osu!mania beatmap")?;
+
+    let audio_data = read_audio(&xwb_file, &basename)?;
 
     let osz = osu::osz::Archive {
         beatmaps,
@@ -367,39 +712,10 @@ fn main() -> Result<()> {
             tw.flush()?;
         }
         SubCommand::DDR2osu(opts) => {
-            let basename = opts.basename.clone().unwrap_or(
-                opts.ssq_file
-                    .file_stem()
-                    .map(|stem| stem.to_str())
-                    .flatten()
-                    .map(|basename| basename.to_string())
-                    .ok_or_else(|| {
-                        anyhow!(
-                        "Could not extract chart id from file name. Please specify it manually."
-                    )
-                    })?,
-            );
+            let basename = get_basename(&opts.basename, &opts.ssq_file)?;
 
             let mut convert_options = opts.convert;
-
-            if let Some(musicdb_file) = &opts.musicdb_file {
-                debug!("Reading metadata from {}", musicdb_file.display());
-                let musicdb = read_musicdb(&musicdb_file)?;
-                let musicdb_entry = musicdb
-                    .get_entry_from_basename(&basename)
-                    .ok_or_else(|| anyhow!("Entry not found in musicdb"))?;
-                if convert_options.metadata.title.is_none() {
-                    info!("Using title from musicdb: “{}”", musicdb_entry.title);
-                    convert_options.metadata.title = Some(musicdb_entry.title.clone());
-                }
-                if convert_options.metadata.artist.is_none() {
-                    info!("Using artist from musicdb: “{}”", musicdb_entry.artist);
-                    convert_options.metadata.artist = Some(musicdb_entry.artist.clone());
-                }
-                convert_options.metadata.levels = Some(musicdb_entry.diff_lv.clone());
-            } else if convert_options.metadata.title.is_none() {
-                convert_options.metadata.title = Some(basename.to_string());
-            }
+            read_metadata(&opts.musicdb_file, &basename, &mut convert_options.metadata)?;
 
             ddr2osu(
                 opts.ssq_file,
@@ -453,6 +769,223 @@ fn main() -> Result<()> {
                 pb.lock().unwrap().inc();
             })
         }
+        SubCommand::DDR2Quaver(opts) => {
+            let basename = get_basename(&opts.basename, &opts.ssq_file)?;
+
+            let mut convert_options = opts.convert;
+            read_metadata(&opts.musicdb_file, &basename, &mut convert_options.metadata)?;
+
+            let ssq = read_ssq(&opts.ssq_file)?;
+            let maps = ssq.to_quaver_maps(&convert_options);
+            let audio_data = read_audio(&opts.xwb_file, &basename)?;
+
+            let qp = quaver::qp::Archive {
+                maps,
+                assets: vec![(&convert_options.audio_filename, &audio_data)],
+            };
+            let out_file = &opts.out_file;
+            qp.write(out_file)
+                .with_context(|| format!("failed to write QP file to {}", out_file.display()))?;
+        }
+        SubCommand::SSQ2sm(opts) => {
+            let basename = get_basename(&opts.basename, &opts.ssq_file)?;
+
+            let mut convert_options = opts.convert;
+            read_metadata(&opts.musicdb_file, &basename, &mut convert_options.metadata)?;
+
+            let ssq = read_ssq(&opts.ssq_file)?;
+            let simfile = ssq.to_simfile(&convert_options);
+            let audio_data = read_audio(&opts.xwb_file, &basename)?;
+
+            let song = stepmania::song::Song {
+                name: basename,
+                simfile,
+                assets: vec![(&convert_options.audio_filename, &audio_data)],
+            };
+            let out_dir = &opts.out_dir;
+            song.write(out_dir)
+                .with_context(|| format!("failed to write song folder to {}", out_dir.display()))?;
+        }
+        SubCommand::Sm2SSQ(opts) => {
+            let simfile = read_simfile(&opts.file)?;
+            if simfile.offset != 0.0 {
+                warn!(
+                    "SSQ files start at the first beat, the offset of {} s is lost",
+                    simfile.offset
+                );
+            }
+            let ssq = simfile
+                .to_ssq()
+                .context("failed to convert simfile to SSQ")?;
+
+            let ssq_data = ssq.to_bytes().context("failed to serialize SSQ")?;
+            fs::write(&opts.out_file, ssq_data).with_context(|| {
+                format!("failed to write SSQ file to {}", opts.out_file.display())
+            })?;
+        }
+        SubCommand::SSQ(opts) => match opts.subcmd {
+            SSQSubCommand::Stats(opts) => {
+                let ssq = read_ssq(&opts.file)?;
+
+                let mut tw = TabWriter::new(io::stdout());
+
+                write!(
+                    tw,
+                    "Chart\tSteps\tArrows\tJumps\tFreezes\tShocks\tMax Combo\tFirst Note\tLast Note\tLength\tPeak NPS\tAverage NPS"
+                )?;
+                if opts.radar {
+                    write!(tw, "\tStream\tVoltage\tAir\tFreeze\tChaos")?;
+                }
+                writeln!(tw)?;
+
+                for chart in &ssq.charts {
+                    let stats = analysis::stats::ChartStats::new(chart, &ssq.tempo_changes);
+
+                    write!(
+                        tw,
+                        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:.2}\t{:.2}",
+                        chart.difficulty,
+                        stats.steps,
+                        stats.arrows,
+                        stats.jumps,
+                        stats.freezes,
+                        stats.shocks,
+                        stats.max_combo,
+                        stats.first_note.map_or("-".to_string(), format_time),
+                        stats.last_note.map_or("-".to_string(), format_time),
+                        format_time(stats.length),
+                        stats.peak_nps,
+                        stats.average_nps,
+                    )?;
+                    if opts.radar {
+                        let radar =
+                            analysis::groove_radar::GrooveRadar::new(chart, &ssq.tempo_changes);
+                        write!(
+                            tw,
+                            "\t{:.0}\t{:.0}\t{:.0}\t{:.0}\t{:.0}",
+                            radar.stream, radar.voltage, radar.air, radar.freeze, radar.chaos
+                        )?;
+                    }
+                    writeln!(tw)?;
+                }
+
+                tw.flush()?;
+            }
+            SSQSubCommand::Patterns(opts) => {
+                let ssq = read_ssq(&opts.file)?;
+
+                let kinds = [
+                    PatternKind::Jack,
+                    PatternKind::Drill,
+                    PatternKind::Candle,
+                    PatternKind::Gallop,
+                    PatternKind::Spin,
+                    PatternKind::Crossover,
+                    PatternKind::Stream,
+                    PatternKind::JumpStream,
+                ];
+
+                let mut tw = TabWriter::new(io::stdout());
+
+                if opts.list {
+                    writeln!(tw, "Chart\tPattern\tBeats\tTime\tRows")?;
+                } else {
+                    writeln!(
+                        tw,
+                        "Chart\tJacks\tDrills\tCandles\tGallops\tSpins\tCrossovers\tStreams\tJump Streams\tTags"
+                    )?;
+                }
+
+                for chart in &ssq.charts {
+                    let patterns = analysis::patterns::detect(chart, &ssq.tempo_changes);
+
+                    if opts.list {
+                        for pattern in patterns {
+                            writeln!(
+                                tw,
+                                "{}\t{}\t{}–{}\t{}–{}\t{}",
+                                chart.difficulty,
+                                pattern.kind,
+                                pattern.start_beats,
+                                pattern.end_beats,
+                                pattern.start.map_or("-".to_string(), format_time),
+                                pattern.end.map_or("-".to_string(), format_time),
+                                pattern.rows,
+                            )?;
+                        }
+                    } else {
+                        let counts: Vec<usize> = kinds
+                            .iter()
+                            .map(|kind| analysis::patterns::count(&patterns, *kind))
+                            .collect();
+                        let steps = analysis::rows(chart).len();
+                        writeln!(
+                            tw,
+                            "{}\t{}\t{}",
+                            chart.difficulty,
+                            utils::join_display_values(counts, "\t"),
+                            analysis::patterns::tags(&patterns, steps).join(", "),
+                        )?;
+                    }
+                }
+
+                tw.flush()?;
+            }
+            SSQSubCommand::Diff(opts) => {
+                let old = read_ssq(&opts.old_file)?;
+                let new = read_ssq(&opts.new_file)?;
+
+                let changes = analysis::diff::diff(&old, &new);
+
+                if opts.machine_readable {
+                    for change in &changes {
+                        println!("{}", change.record().join("\t"));
+                    }
+                } else if changes.is_empty() {
+                    println!("No differences");
+                } else {
+                    let mut level = None;
+                    for change in &changes {
+                        if level != Some(&change.level) {
+                            level = Some(&change.level);
+                            match &change.level {
+                                Some(level) => println!("{}", level),
+                                None => println!("Tempo"),
+                            }
+                        }
+                        println!("  {}", change);
+                    }
+                }
+            }
+            SSQSubCommand::Show(opts) => {
+                let ssq = read_ssq(&opts.file)?;
+                let charts = select_charts(&ssq, &opts.charts)?;
+
+                let options = render::text::Options {
+                    color: opts.color,
+                    ..Default::default()
+                };
+                for chart in charts {
+                    println!("{}", chart.difficulty);
+                    print!(
+                        "{}",
+                        render::text::render(chart, &ssq.tempo_changes, &options)
+                    );
+                }
+            }
+            SSQSubCommand::Svg(opts) => {
+                let ssq = read_ssq(&opts.file)?;
+                let charts = select_charts(&ssq, &opts.charts)?;
+
+                let options = render::svg::Options {
+                    measures_per_column: opts.measures_per_column,
+                };
+                let svg = render::svg::render_charts(&charts, &ssq.tempo_changes, &options);
+                fs::write(&opts.out_file, svg).with_context(|| {
+                    format!("failed to write SVG file {}", opts.out_file.display())
+                })?;
+            }
+        },
     }
     Ok(())
 }
@@ -1,6 +1,6 @@
 use std::fs::File;
 use std::io::{Result, Write};
-use std::path::PathBuf;
+use std::path::Path;
 
 use zip::write::{FileOptions, ZipWriter};
 
@@ -12,7 +12,7 @@ pub struct Archive<'a> {
 }
 
 impl Archive<'_> {
-    pub fn write(&self, filename: &PathBuf) -> Result<()> {
+    pub fn write(&self, filename: &Path) -> Result<()> {
         let file = File::create(filename)?;
         let mut zip = ZipWriter::new(file);
 
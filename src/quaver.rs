@@ -0,0 +1,2 @@
+pub mod qp;
+pub mod qua;
@@ -0,0 +1,41 @@
+use std::fs::File;
+use std::io::{Result, Write};
+use std::path::Path;
+
+use zip::write::{FileOptions, ZipWriter};
+
+use crate::quaver::qua;
+
+/// Quaver map set (.qp), a zip archive with the maps of all difficulties and their assets.
+pub struct Archive<'a> {
+    pub maps: Vec<qua::Map>,
+    pub assets: Vec<(&'a str, &'a [u8])>,
+}
+
+impl Archive<'_> {
+    pub fn write(&self, filename: &Path) -> Result<()> {
+        let file = File::create(filename)?;
+        let mut zip = ZipWriter::new(file);
+
+        for map in &self.maps {
+            let filename = format!(
+                "{} - {} [{}].qua",
+                map.artist, map.title, map.difficulty_name
+            );
+            let options = FileOptions::default();
+            zip.start_file(filename, options)?;
+            zip.write_all(map.to_string().as_bytes())?;
+        }
+
+        for asset in &self.assets {
+            // Assets mostly are already compressed (e.g. JPEG, MP3)
+            let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+            zip.start_file(asset.0, options)?;
+            zip.write_all(asset.1)?;
+        }
+
+        zip.finish()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+//! The description format of a Quaver map (.qua).
+//!
+//! A map is a YAML document with the metadata, timing points, slider velocities (scroll speed
+//! changes) and hit objects of one difficulty. The format is defined by the [Quaver API].
+//!
+//! [Quaver API]: https://github.com/Quaver/Quaver.API/blob/master/Quaver.API/Maps/Qua.cs
+use std::fmt;
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum GameMode {
+    Keys4,
+    /// Seven keys, with [`Map::has_scratch_key`] an eighth (scratch) key is added
+    ///
+    /// [`Map::has_scratch_key`]: struct.Map.html#structfield.has_scratch_key
+    Keys7,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TimingPoint {
+    pub start_time: f32,
+    pub bpm: f32,
+}
+
+/// Scroll speed change (0 stops the scrolling).
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SliderVelocity {
+    pub start_time: f32,
+    pub multiplier: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct HitObject {
+    pub start_time: i32,
+    /// Lane starting from 1
+    pub lane: u8,
+    /// End of long notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<i32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Map {
+    pub audio_file: String,
+    pub song_preview_time: i32,
+    pub background_file: String,
+    /// ID on the Quaver servers, -1 for maps that are not uploaded
+    pub map_id: i32,
+    pub map_set_id: i32,
+    pub mode: GameMode,
+    pub title: String,
+    pub artist: String,
+    pub source: String,
+    pub tags: String,
+    pub creator: String,
+    pub difficulty_name: String,
+    pub description: String,
+    pub has_scratch_key: bool,
+    pub timing_points: Vec<TimingPoint>,
+    pub slider_velocities: Vec<SliderVelocity>,
+    pub hit_objects: Vec<HitObject>,
+}
+
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yaml = serde_yaml::to_string(self).map_err(|_| fmt::Error)?;
+        writeln!(f, "{}", yaml.trim_end())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_display() {
+        let map = Map {
+            audio_file: "audio.wav".to_string(),
+            song_preview_time: 0,
+            background_file: String::new(),
+            map_id: -1,
+            map_set_id: -1,
+            mode: GameMode::Keys4,
+            title: "Title: Subtitle".to_string(),
+            artist: "Artist".to_string(),
+            source: "Dance Dance Revolution".to_string(),
+            tags: String::new(),
+            creator: "ddr2quaver".to_string(),
+            difficulty_name: "Single Expert (Lv. 9)".to_string(),
+            description: String::new(),
+            has_scratch_key: false,
+            timing_points: vec![TimingPoint {
+                start_time: 0.0,
+                bpm: 150.0,
+            }],
+            slider_velocities: vec![
+                SliderVelocity {
+                    start_time: 800.0,
+                    multiplier: 0.0,
+                },
+                SliderVelocity {
+                    start_time: 1200.0,
+                    multiplier: 1.0,
+                },
+            ],
+            hit_objects: vec![
+                HitObject {
+                    start_time: 400,
+                    lane: 1,
+                    end_time: None,
+                },
+                HitObject {
+                    start_time: 800,
+                    lane: 4,
+                    end_time: Some(1600),
+                },
+            ],
+        };
+
+        assert_eq!(
+            map.to_string(),
+            r#"---
+AudioFile: audio.wav
+SongPreviewTime: 0
+BackgroundFile: ""
+MapId: -1
+MapSetId: -1
+Mode: Keys4
+Title: "Title: Subtitle"
+Artist: Artist
+Source: Dance Dance Revolution
+Tags: ""
+Creator: ddr2quaver
+DifficultyName: Single Expert (Lv. 9)
+Description: ""
+HasScratchKey: false
+TimingPoints:
+  - StartTime: 0.0
+    Bpm: 150.0
+SliderVelocities:
+  - StartTime: 800.0
+    Multiplier: 0.0
+  - StartTime: 1200.0
+    Multiplier: 1.0
+HitObjects:
+  - StartTime: 400
+    Lane: 1
+  - StartTime: 800
+    Lane: 4
+    EndTime: 1600
+"#
+        );
+    }
+}
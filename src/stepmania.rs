@@ -0,0 +1,2 @@
+pub mod simfile;
+pub mod song;
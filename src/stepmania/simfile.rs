@@ -0,0 +1,743 @@
+//! The description format of a StepMania simfile (.sm).
+//!
+//! A simfile consists of tags (`#NAME:value;`). The timing is global, the notes of every chart
+//! are stored in a `#NOTES` tag as measures, which are split into lines of equal length. The
+//! format is described in the [StepMania wiki].
+//!
+//! Simfiles are written as .sm, but both .sm and the newer .ssc format (which stores every chart
+//! in a `#NOTEDATA` section with one tag per field) can be parsed.
+//!
+//! [StepMania wiki]: https://github.com/stepmania/stepmania/wiki/sm
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use log::{debug, warn};
+use thiserror::Error;
+
+use crate::utils;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("invalid value for #{tag} (found “{value}”)")]
+    InvalidValue { tag: String, value: String },
+    #[error("#NOTES has {0} instead of 6 parameters")]
+    InvalidNotesTag(usize),
+    #[error("note line “{line}” has less than {columns} columns")]
+    InvalidNoteLine { line: String, columns: usize },
+    #[error("unsupported steps type {0}")]
+    UnsupportedStepsType(String),
+    #[error("unknown difficulty {0}")]
+    UnknownDifficulty(String),
+    #[error("unknown note {0}")]
+    UnknownNote(char),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepsType {
+    DanceSingle,
+    DanceDouble,
+    DanceCouple,
+    DanceSolo,
+}
+
+impl StepsType {
+    pub fn columns(self) -> usize {
+        match self {
+            Self::DanceSingle => 4,
+            Self::DanceSolo => 6,
+            Self::DanceDouble | Self::DanceCouple => 8,
+        }
+    }
+}
+
+impl fmt::Display for StepsType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::DanceSingle => "dance-single",
+                Self::DanceDouble => "dance-double",
+                Self::DanceCouple => "dance-couple",
+                Self::DanceSolo => "dance-solo",
+            }
+        )
+    }
+}
+
+impl FromStr for StepsType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "dance-single" => Ok(Self::DanceSingle),
+            "dance-double" => Ok(Self::DanceDouble),
+            "dance-couple" => Ok(Self::DanceCouple),
+            "dance-solo" => Ok(Self::DanceSolo),
+            _ => Err(Error::UnsupportedStepsType(s.trim().to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty {
+    Beginner,
+    Easy,
+    Medium,
+    Hard,
+    Challenge,
+    Edit,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Beginner => "Beginner",
+                Self::Easy => "Easy",
+                Self::Medium => "Medium",
+                Self::Hard => "Hard",
+                Self::Challenge => "Challenge",
+                Self::Edit => "Edit",
+            }
+        )
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = Error;
+
+    /// Parses a difficulty, including the names used by old simfiles (e.g. `Basic` or `Heavy`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "beginner" => Ok(Self::Beginner),
+            "easy" | "basic" | "light" => Ok(Self::Easy),
+            "medium" | "another" | "trick" | "standard" | "difficult" => Ok(Self::Medium),
+            "hard" | "ssr" | "maniac" | "heavy" => Ok(Self::Hard),
+            "challenge" | "smaniac" | "expert" | "oni" => Ok(Self::Challenge),
+            "edit" => Ok(Self::Edit),
+            _ => Err(Error::UnknownDifficulty(s.trim().to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Note {
+    Empty,
+    Tap,
+    HoldHead,
+    /// End of a hold or roll
+    Tail,
+    RollHead,
+    Mine,
+    Lift,
+    Fake,
+}
+
+impl From<Note> for char {
+    fn from(note: Note) -> Self {
+        match note {
+            Note::Empty => '0',
+            Note::Tap => '1',
+            Note::HoldHead => '2',
+            Note::Tail => '3',
+            Note::RollHead => '4',
+            Note::Mine => 'M',
+            Note::Lift => 'L',
+            Note::Fake => 'F',
+        }
+    }
+}
+
+impl TryFrom<char> for Note {
+    type Error = Error;
+
+    fn try_from(character: char) -> Result<Self, Self::Error> {
+        match character {
+            '0' => Ok(Note::Empty),
+            '1' => Ok(Note::Tap),
+            '2' => Ok(Note::HoldHead),
+            '3' => Ok(Note::Tail),
+            '4' => Ok(Note::RollHead),
+            'M' => Ok(Note::Mine),
+            'L' => Ok(Note::Lift),
+            'F' => Ok(Note::Fake),
+            _ => Err(Error::UnknownNote(character)),
+        }
+    }
+}
+
+/// One measure, split into lines of equal length with one note per column.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Measure(pub Vec<Vec<Note>>);
+
+impl Measure {
+    /// Creates a measure without notes.
+    pub fn empty(lines: usize, columns: usize) -> Self {
+        Self(vec![vec![Note::Empty; columns]; lines])
+    }
+}
+
+impl fmt::Display for Measure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.0 {
+            let line: String = line.iter().map(|note| char::from(*note)).collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoteData {
+    pub steps_type: StepsType,
+    pub description: String,
+    pub difficulty: Difficulty,
+    pub meter: u8,
+    /// Groove Radar values (stream, voltage, air, freeze, chaos) from 0 to 1
+    pub radar_values: Vec<f32>,
+    pub measures: Vec<Measure>,
+}
+
+impl fmt::Display for NoteData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "//---------------{} - {}----------------",
+            self.steps_type, self.description
+        )?;
+        writeln!(f, "#NOTES:")?;
+        writeln!(f, "     {}:", self.steps_type)?;
+        writeln!(f, "     {}:", escape(&self.description))?;
+        writeln!(f, "     {}:", self.difficulty)?;
+        writeln!(f, "     {}:", self.meter)?;
+        let radar_values: Vec<String> = self
+            .radar_values
+            .iter()
+            .map(|value| format!("{:.3}", value))
+            .collect();
+        writeln!(f, "     {}:", radar_values.join(","))?;
+
+        if self.measures.is_empty() {
+            write!(f, "{}", Measure::empty(4, self.steps_type.columns()))?;
+        }
+        for (i, measure) in self.measures.iter().enumerate() {
+            if i > 0 {
+                writeln!(f, ",")?;
+            }
+            write!(f, "{}", measure)?;
+        }
+        writeln!(f, ";")
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Simfile {
+    pub title: String,
+    pub subtitle: String,
+    pub artist: String,
+    pub genre: String,
+    pub credit: String,
+    pub music: String,
+    /// Negated time of the first beat in seconds
+    pub offset: f64,
+    /// Start of the preview in seconds
+    pub sample_start: f64,
+    /// Length of the preview in seconds
+    pub sample_length: f64,
+    /// Position (in beats) and tempo (in BPM)
+    pub bpms: Vec<(f32, f64)>,
+    /// Position (in beats) and duration (in seconds)
+    pub stops: Vec<(f32, f64)>,
+    /// Position (in beats) and duration (in seconds)
+    pub delays: Vec<(f32, f64)>,
+    pub charts: Vec<NoteData>,
+}
+
+/// Escapes characters with a special meaning in tag values.
+fn escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for character in value.chars() {
+        if matches!(character, '\\' | ':' | ';' | '#') {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+/// Formats a list of positions with values (e.g. `0.000=150.000,64.000=75.000`).
+fn format_pairs(pairs: &[(f32, f64)]) -> String {
+    utils::join_display_values(
+        pairs
+            .iter()
+            .map(|(beats, value)| format!("{:.3}={:.3}", beats, value))
+            .collect(),
+        ",",
+    )
+}
+
+impl fmt::Display for Simfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "#TITLE:{};", escape(&self.title))?;
+        writeln!(f, "#SUBTITLE:{};", escape(&self.subtitle))?;
+        writeln!(f, "#ARTIST:{};", escape(&self.artist))?;
+        writeln!(f, "#GENRE:{};", escape(&self.genre))?;
+        writeln!(f, "#CREDIT:{};", escape(&self.credit))?;
+        writeln!(f, "#MUSIC:{};", escape(&self.music))?;
+        writeln!(f, "#OFFSET:{:.3};", self.offset)?;
+        writeln!(f, "#SAMPLESTART:{:.3};", self.sample_start)?;
+        writeln!(f, "#SAMPLELENGTH:{:.3};", self.sample_length)?;
+        writeln!(f, "#SELECTABLE:YES;")?;
+        writeln!(f, "#BPMS:{};", format_pairs(&self.bpms))?;
+        writeln!(f, "#STOPS:{};", format_pairs(&self.stops))?;
+        if !self.delays.is_empty() {
+            writeln!(f, "#DELAYS:{};", format_pairs(&self.delays))?;
+        }
+
+        for chart in &self.charts {
+            writeln!(f)?;
+            write!(f, "{}", chart)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits a simfile into tags with their (unescaped) parameters.
+///
+/// Comments (from `//` to the end of the line) are removed. Like in StepMania, a tag without a
+/// semicolon ends at the next line that starts with `#`.
+fn tags(data: &str) -> Vec<(String, Vec<String>)> {
+    let mut tags = Vec::new();
+    // parameters of the current tag, the first one is the name
+    let mut parameters: Option<Vec<String>> = None;
+    let mut line_start = true;
+
+    let mut chars = data.chars().peekable();
+    while let Some(character) = chars.next() {
+        if character == '/' && chars.peek() == Some(&'/') {
+            while chars.peek().filter(|next| **next != '\n').is_some() {
+                chars.next();
+            }
+            continue;
+        }
+
+        let was_line_start = line_start;
+        line_start = character == '\n' || (line_start && character.is_whitespace());
+
+        let current = match &mut parameters {
+            Some(current) => current,
+            None => {
+                if character == '#' {
+                    parameters = Some(vec![String::new()]);
+                }
+                continue;
+            }
+        };
+        match character {
+            '#' if was_line_start => {
+                tags.push(current.clone());
+                *current = vec![String::new()];
+            }
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    current.last_mut().unwrap().push(escaped);
+                }
+            }
+            ':' => current.push(String::new()),
+            ';' => {
+                tags.push(current.clone());
+                parameters = None;
+            }
+            _ => current.last_mut().unwrap().push(character),
+        }
+    }
+    tags.extend(parameters);
+
+    tags.into_iter()
+        .map(|mut parameters| {
+            let name = parameters.remove(0).trim().to_uppercase();
+            (name, parameters)
+        })
+        .collect()
+}
+
+/// Parses a number, empty values are parsed as the default value (0).
+fn parse_number<T: FromStr + Default>(tag: &str, value: &str) -> Result<T, Error> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(T::default());
+    }
+    value.parse().map_err(|_| Error::InvalidValue {
+        tag: tag.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// Parses a list of positions with values (e.g. `0.000=150.000,64.000=75.000`).
+fn parse_pairs(tag: &str, value: &str) -> Result<Vec<(f32, f64)>, Error> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut parts = pair.split('=');
+            match (parts.next(), parts.next()) {
+                (Some(beats), Some(value)) => {
+                    Ok((parse_number(tag, beats)?, parse_number(tag, value)?))
+                }
+                _ => Err(Error::InvalidValue {
+                    tag: tag.to_string(),
+                    value: pair.to_string(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Parses the measures of a chart with `columns` columns.
+///
+/// Unknown notes (e.g. keysounds) are read as empty.
+fn parse_measures(notes: &str, columns: usize) -> Result<Vec<Measure>, Error> {
+    if notes.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    notes
+        .split(',')
+        .map(|measure| {
+            measure
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    let notes: Vec<Note> = line
+                        .chars()
+                        .take(columns)
+                        .map(|character| {
+                            Note::try_from(character).unwrap_or_else(|err| {
+                                debug!("{}, reading it as empty", err);
+                                Note::Empty
+                            })
+                        })
+                        .collect();
+                    if notes.len() < columns {
+                        return Err(Error::InvalidNoteLine {
+                            line: line.to_string(),
+                            columns,
+                        });
+                    }
+                    Ok(notes)
+                })
+                .collect::<Result<_, _>>()
+                .map(Measure)
+        })
+        .collect()
+}
+
+/// Fields of a chart as they are stored in the simfile.
+#[derive(Default)]
+struct ChartTags {
+    steps_type: String,
+    description: String,
+    difficulty: String,
+    meter: String,
+    radar_values: String,
+}
+
+impl ChartTags {
+    /// Parses the fields and the notes of a chart.
+    ///
+    /// Charts of steps types that are not supported (e.g. pump) are skipped.
+    fn to_note_data(&self, notes: &str) -> Result<Option<NoteData>, Error> {
+        let steps_type = match self.steps_type.parse::<StepsType>() {
+            Ok(steps_type) => steps_type,
+            Err(err) => {
+                warn!("{}, skipping chart", err);
+                return Ok(None);
+            }
+        };
+        let difficulty = self.difficulty.parse().unwrap_or_else(|err| {
+            warn!("{}, using Edit", err);
+            Difficulty::Edit
+        });
+        let radar_values = self
+            .radar_values
+            .split(',')
+            .filter(|value| !value.trim().is_empty())
+            .map(|value| parse_number("RADARVALUES", value))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some(NoteData {
+            steps_type,
+            description: self.description.trim().to_string(),
+            difficulty,
+            meter: parse_number("METER", &self.meter)?,
+            radar_values,
+            measures: parse_measures(notes, steps_type.columns())?,
+        }))
+    }
+}
+
+impl Simfile {
+    /// Parses a simfile in .sm or .ssc format.
+    ///
+    /// Tags that are not supported (e.g. background changes) are ignored. The timing of .ssc
+    /// charts is not supported, all charts use the timing of the song.
+    pub fn parse(data: &str) -> Result<Self, Error> {
+        let mut simfile = Self::default();
+        // fields of the current chart of an .ssc file
+        let mut ssc_chart: Option<ChartTags> = None;
+        let mut warned_chart_timing = false;
+
+        for (name, parameters) in tags(data) {
+            let value = parameters.first().map_or("", |value| value.trim());
+
+            if name == "NOTEDATA" {
+                ssc_chart = Some(ChartTags::default());
+                continue;
+            }
+
+            if let Some(chart) = &mut ssc_chart {
+                match name.as_str() {
+                    "STEPSTYPE" => chart.steps_type = value.to_string(),
+                    "DESCRIPTION" => chart.description = value.to_string(),
+                    "DIFFICULTY" => chart.difficulty = value.to_string(),
+                    "METER" => chart.meter = value.to_string(),
+                    "RADARVALUES" => chart.radar_values = value.to_string(),
+                    "NOTES" => simfile.charts.extend(chart.to_note_data(value)?),
+                    "OFFSET" | "BPMS" | "STOPS" | "DELAYS" | "WARPS" if !warned_chart_timing => {
+                        warn!("Timing of charts is not supported, using the timing of the song");
+                        warned_chart_timing = true;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            match name.as_str() {
+                "TITLE" => simfile.title = value.to_string(),
+                "SUBTITLE" => simfile.subtitle = value.to_string(),
+                "ARTIST" => simfile.artist = value.to_string(),
+                "GENRE" => simfile.genre = value.to_string(),
+                "CREDIT" => simfile.credit = value.to_string(),
+                "MUSIC" => simfile.music = value.to_string(),
+                "OFFSET" => simfile.offset = parse_number(&name, value)?,
+                "SAMPLESTART" => simfile.sample_start = parse_number(&name, value)?,
+                "SAMPLELENGTH" => simfile.sample_length = parse_number(&name, value)?,
+                "BPMS" => simfile.bpms = parse_pairs(&name, value)?,
+                // old simfiles call stops freezes
+                "STOPS" | "FREEZES" => simfile.stops = parse_pairs(&name, value)?,
+                "DELAYS" => simfile.delays = parse_pairs(&name, value)?,
+                "NOTES" => {
+                    if parameters.len() != 6 {
+                        return Err(Error::InvalidNotesTag(parameters.len()));
+                    }
+                    let chart = ChartTags {
+                        steps_type: parameters[0].clone(),
+                        description: parameters[1].clone(),
+                        difficulty: parameters[2].clone(),
+                        meter: parameters[3].clone(),
+                        radar_values: parameters[4].clone(),
+                    };
+                    simfile.charts.extend(chart.to_note_data(&parameters[5])?);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(simfile)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("A: B; #1 \\o/"), "A\\: B\\; \\#1 \\\\o/");
+    }
+
+    fn simfile() -> Simfile {
+        let mut measure = Measure::empty(4, 4);
+        measure.0[0][0] = Note::Tap;
+        measure.0[1][1] = Note::HoldHead;
+        measure.0[3][1] = Note::Tail;
+        measure.0[2] = vec![Note::Mine; 4];
+
+        Simfile {
+            title: "Title".to_string(),
+            artist: "Artist".to_string(),
+            genre: "Dance Dance Revolution".to_string(),
+            credit: "ssq2sm".to_string(),
+            music: "audio.wav".to_string(),
+            sample_length: 12.0,
+            bpms: vec![(0.0, 150.0), (64.0, 75.0)],
+            stops: vec![(32.0, 0.4)],
+            charts: vec![NoteData {
+                steps_type: StepsType::DanceSingle,
+                description: "".to_string(),
+                difficulty: Difficulty::Hard,
+                meter: 9,
+                radar_values: vec![0.5, 0.6, 0.1, 0.0, 0.25],
+                measures: vec![measure, Measure::empty(4, 4)],
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_simfile_display() {
+        assert_eq!(
+            simfile().to_string(),
+            "#TITLE:Title;
+#SUBTITLE:;
+#ARTIST:Artist;
+#GENRE:Dance Dance Revolution;
+#CREDIT:ssq2sm;
+#MUSIC:audio.wav;
+#OFFSET:0.000;
+#SAMPLESTART:0.000;
+#SAMPLELENGTH:12.000;
+#SELECTABLE:YES;
+#BPMS:0.000=150.000,64.000=75.000;
+#STOPS:32.000=0.400;
+
+//---------------dance-single - ----------------
+#NOTES:
+     dance-single:
+     :
+     Hard:
+     9:
+     0.500,0.600,0.100,0.000,0.250:
+1000
+0200
+MMMM
+0300
+,
+0000
+0000
+0000
+0000
+;
+"
+        );
+    }
+
+    #[test]
+    fn test_note_data_without_measures() {
+        let note_data = NoteData {
+            steps_type: StepsType::DanceDouble,
+            description: "Edit".to_string(),
+            difficulty: Difficulty::Edit,
+            meter: 1,
+            radar_values: Vec::new(),
+            measures: Vec::new(),
+        };
+        assert!(note_data
+            .to_string()
+            .ends_with(":\n00000000\n00000000\n00000000\n00000000\n;\n"));
+    }
+
+    #[test]
+    fn test_tags() {
+        assert_eq!(
+            tags("#TITLE:A\\;B // comment\n;\n// #ARTIST:X;\n#NOTES:a:b\n#BPMS:0=150;"),
+            vec![
+                ("TITLE".to_string(), vec!["A;B \n".to_string()]),
+                (
+                    "NOTES".to_string(),
+                    vec!["a".to_string(), "b\n".to_string()]
+                ),
+                ("BPMS".to_string(), vec!["0=150".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_difficulty_from_str() {
+        assert_eq!("Hard".parse::<Difficulty>().unwrap(), Difficulty::Hard);
+        assert_eq!("basic".parse::<Difficulty>().unwrap(), Difficulty::Easy);
+        assert_eq!(
+            " SMANIAC ".parse::<Difficulty>().unwrap(),
+            Difficulty::Challenge
+        );
+        assert!("unknown".parse::<Difficulty>().is_err());
+    }
+
+    #[test]
+    fn test_parse_sm() {
+        assert_eq!(Simfile::parse(&simfile().to_string()).unwrap(), simfile());
+    }
+
+    #[test]
+    fn test_parse_ssc() {
+        let simfile = Simfile::parse(
+            "#VERSION:0.83;
+#TITLE:Title;
+#OFFSET:-0.5;
+#BPMS:0=150,32=300;
+#FREEZES:16=0.5;
+
+#NOTEDATA:;
+#STEPSTYPE:pump-single;
+#NOTES:
+00000
+;
+
+#NOTEDATA:;
+#STEPSTYPE:dance-single;
+#DIFFICULTY:Challenge;
+#METER:12;
+#RADARVALUES:0.1,0.2;
+#BPMS:0=150;
+#NOTES:
+1000
+0K00
+,
+0002
+0003
+0000
+MMMM
+;
+",
+        )
+        .unwrap();
+
+        assert_eq!(simfile.title, "Title");
+        assert_eq!(simfile.offset, -0.5);
+        assert_eq!(simfile.bpms, vec![(0.0, 150.0), (32.0, 300.0)]);
+        assert_eq!(simfile.stops, vec![(16.0, 0.5)]);
+
+        let mut first = Measure::empty(2, 4);
+        first.0[0][0] = Note::Tap;
+        let mut second = Measure::empty(4, 4);
+        second.0[0][3] = Note::HoldHead;
+        second.0[1][3] = Note::Tail;
+        second.0[3] = vec![Note::Mine; 4];
+        assert_eq!(
+            simfile.charts,
+            vec![NoteData {
+                steps_type: StepsType::DanceSingle,
+                description: String::new(),
+                difficulty: Difficulty::Challenge,
+                meter: 12,
+                radar_values: vec![0.1, 0.2],
+                measures: vec![first, second],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_note_line() {
+        assert!(matches!(
+            Simfile::parse("#NOTES:dance-single::Hard:1::100;"),
+            Err(Error::InvalidNoteLine { .. })
+        ));
+    }
+}
@@ -0,0 +1,31 @@
+use std::fs;
+use std::io::Result;
+use std::path::Path;
+
+use crate::stepmania::simfile::Simfile;
+
+/// Song folder with a simfile and its assets (e.g. the audio file referenced in `#MUSIC`).
+pub struct Song<'a> {
+    /// File name of the simfile (without extension)
+    pub name: String,
+    pub simfile: Simfile,
+    pub assets: Vec<(&'a str, &'a [u8])>,
+}
+
+impl Song<'_> {
+    /// Writes the simfile and the assets into `directory`, which is created if it does not exist.
+    pub fn write(&self, directory: &Path) -> Result<()> {
+        fs::create_dir_all(directory)?;
+
+        fs::write(
+            directory.join(format!("{}.sm", self.name)),
+            self.simfile.to_string(),
+        )?;
+
+        for (filename, data) in &self.assets {
+            fs::write(directory.join(filename), data)?;
+        }
+
+        Ok(())
+    }
+}